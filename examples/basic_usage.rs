@@ -84,6 +84,7 @@ fn execution_limits() {
     let limits = ExecLimits {
         max_ticks: Some(10000),
         max_failures: 1000,
+        ..Default::default()
     };
 
     let start = std::time::Instant::now();
@@ -0,0 +1,796 @@
+//! Static analysis of compiled bytecode
+//!
+//! Derives facts about a pattern that let `search` skip start positions
+//! where a match is impossible, in the spirit of re_compile_fastmap in
+//! regexpr.c.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
+};
+
+use crate::{
+    opcodes::{instruction_len, CompiledOp, SyntaxType, REPEAT_UNBOUNDED},
+    Anchor,
+};
+
+/// Where a match must start, from the pattern's first instruction
+pub fn start_anchor(buffer: &[u8]) -> Anchor {
+    match buffer.first().copied().and_then(CompiledOp::from_byte) {
+        Some(CompiledOp::Bol) => Anchor::Line,
+        Some(CompiledOp::BegBuf) => Anchor::Buffer,
+        Some(CompiledOp::SearchStart) => Anchor::Search,
+        _ => Anchor::None,
+    }
+}
+
+/// Where a match must end, from the pattern's last instruction
+///
+/// The pattern is end-anchored when `$` or `` \' `` is the final instruction
+/// and no jump bypasses it, as a top-level alternative would.
+pub fn end_anchor(buffer: &[u8]) -> Anchor {
+    let mut pos = 0;
+    let mut last = None;
+    let mut jump_targets = Vec::new();
+
+    while let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) {
+        if op == CompiledOp::End {
+            break;
+        }
+        if let Some(target) = jump_target(buffer, pos) {
+            jump_targets.push(target);
+        }
+        last = Some((pos, op));
+        match instruction_len(buffer, pos) {
+            Some(len) => pos += len,
+            None => return Anchor::None,
+        }
+    }
+
+    let end_pos = pos;
+    let anchor = match last {
+        Some((_, CompiledOp::Eol)) => Anchor::Line,
+        Some((_, CompiledOp::EndBuf)) => Anchor::Buffer,
+        _ => return Anchor::None,
+    };
+
+    if jump_targets.contains(&end_pos) {
+        Anchor::None
+    } else {
+        anchor
+    }
+}
+
+/// The `failure_jump` starting each alternation branch but the last, mapped
+/// to that branch's index within its alternation
+///
+/// An alternation compiles to a chain of
+///   failure_jump next; branch; jump end; next: ...
+/// so a `failure_jump` is a branch point when a forward `jump` ends just
+/// before its target. The last branch has no `failure_jump` of its own; it
+/// is entered by backtracking out of the one before it.
+pub fn branch_points(buffer: &[u8]) -> BTreeMap<usize, usize> {
+    let mut ops = BTreeMap::new();
+    let mut pos = 0;
+    while let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) {
+        ops.insert(pos, op);
+        match instruction_len(buffer, pos) {
+            Some(len) => pos += len,
+            None => break,
+        }
+    }
+
+    let mut points = BTreeMap::new();
+    for (&pos, &op) in &ops {
+        if op != CompiledOp::FailureJump {
+            continue;
+        }
+        let Some(next) = jump_target(buffer, pos) else {
+            continue;
+        };
+        let ends_branch = next >= 3
+            && ops.get(&(next - 3)) == Some(&CompiledOp::Jump)
+            && jump_target(buffer, next - 3).is_some_and(|end| end >= next);
+        if !ends_branch {
+            continue;
+        }
+
+        // Branches after the first are entered at the previous one's target
+        let index = points
+            .iter()
+            .find(|&(&point, _)| jump_target(buffer, point) == Some(pos))
+            .map_or(0, |(_, &index)| index + 1);
+        points.insert(pos, index);
+    }
+    points
+}
+
+/// The most chars any match can consume, or `None` if unbounded or unknown
+///
+/// Loops, back-references and recursion make the length unbounded as far as
+/// this analysis is concerned; counted repetitions with a maximum do not.
+pub fn max_match_len(buffer: &[u8]) -> Option<usize> {
+    let mut memo = HashMap::new();
+    // Each `(pos, stop)` waits on the later ones it needs; patterns can run
+    // to many thousands of instructions, so this stack lives on the heap
+    let mut pending = vec![(0, None)];
+    while let Some(&(pos, stop)) = pending.last() {
+        match max_len_step(buffer, pos, stop, &memo) {
+            Ok(len) => {
+                memo.insert((pos, stop), len);
+                pending.pop();
+            }
+            Err(needed) => pending.push(needed),
+        }
+    }
+    memo[&(0, None)]
+}
+
+/// Longest path in chars from `pos` to `stop`, or to the end of the pattern
+///
+/// Returns `Err` with the `(pos, stop)` pair whose length must be worked out
+/// first. Jumps only ever lead forwards here, so the pairs cannot cycle.
+fn max_len_step(
+    buffer: &[u8],
+    pos: usize,
+    stop: Option<usize>,
+    memo: &HashMap<(usize, Option<usize>), Option<usize>>,
+) -> Result<Option<usize>, (usize, Option<usize>)> {
+    let known = |pos: usize, stop: Option<usize>| {
+        if Some(pos) == stop {
+            Ok(Some(0))
+        } else {
+            memo.get(&(pos, stop)).copied().ok_or((pos, stop))
+        }
+    };
+    if Some(pos) == stop {
+        return Ok(Some(0));
+    }
+
+    let Some(op) = buffer
+        .get(pos)
+        .and_then(|&byte| CompiledOp::from_byte(byte))
+    else {
+        return Ok(None);
+    };
+    let Some(next) = instruction_len(buffer, pos).map(|len| pos + len) else {
+        return Ok(None);
+    };
+
+    Ok(match op {
+        CompiledOp::End => Some(0),
+
+        CompiledOp::Exact
+        | CompiledOp::AnyChar
+        | CompiledOp::Set
+        | CompiledOp::SyntaxSpec
+        | CompiledOp::NotSyntaxSpec => known(next, stop)?.map(|len| len + 1),
+
+        CompiledOp::ExactString => {
+            let run = buffer
+                .get(pos + 2..next)
+                .and_then(|run| std::str::from_utf8(run).ok());
+            match run {
+                Some(run) => known(next, stop)?.map(|len| len + run.chars().count()),
+                None => None,
+            }
+        }
+
+        CompiledOp::Bol
+        | CompiledOp::Eol
+        | CompiledOp::BegBuf
+        | CompiledOp::EndBuf
+        | CompiledOp::SearchStart
+        | CompiledOp::WordBeg
+        | CompiledOp::WordEnd
+        | CompiledOp::WordBound
+        | CompiledOp::NotWordBound
+        | CompiledOp::StartMemory
+        | CompiledOp::EndMemory
+        | CompiledOp::RepeatPop
+        | CompiledOp::AtomicStart
+        | CompiledOp::AtomicEnd => known(next, stop)?,
+
+        CompiledOp::Jump | CompiledOp::UpdateFailureJump => match jump_target(buffer, pos) {
+            Some(target) if target > pos => known(target, stop)?,
+            _ => None,
+        },
+
+        CompiledOp::FailureJump => match jump_target(buffer, pos) {
+            Some(target) if target > pos => {
+                let fall_through = known(next, stop)?;
+                let jumped = known(target, stop)?;
+                fall_through
+                    .zip(jumped)
+                    .map(|(fall_through, jumped)| fall_through.max(jumped))
+            }
+            _ => None,
+        },
+
+        // Skips the failure jump that follows it (see the matcher)
+        CompiledOp::DummyFailureJump => known(next + 3, stop)?,
+
+        // The body runs at most `max` times
+        CompiledOp::RepeatStart => match Repeat::read(buffer, pos) {
+            Some(Repeat {
+                max: Some(max),
+                end,
+                exit,
+                ..
+            }) => {
+                let body = known(next, Some(end))?;
+                let rest = known(exit, stop)?;
+                body.zip(rest)
+                    .and_then(|(body, rest)| body.checked_mul(max)?.checked_add(rest))
+            }
+            _ => None,
+        },
+
+        CompiledOp::StarJump
+        | CompiledOp::RepeatEnd
+        | CompiledOp::MatchMemory
+        | CompiledOp::Call => None,
+    })
+}
+
+/// The fewest chars any match can consume
+///
+/// Back-references and recursion are assumed to consume nothing, so this is
+/// a lower bound.
+pub fn min_match_len(buffer: &[u8]) -> usize {
+    min_len_from(buffer, 0, None).unwrap_or(0)
+}
+
+/// Shortest path in chars from `from` to `stop`, or to the end of the pattern
+///
+/// The bytecode is a graph whose edges consume chars, loops included, so
+/// this is Dijkstra's algorithm over instruction positions. `None` when no
+/// path gets there.
+fn min_len_from(buffer: &[u8], from: usize, stop: Option<usize>) -> Option<usize> {
+    let mut best = HashMap::new();
+    let mut queue = BinaryHeap::from([Reverse((0, from))]);
+
+    while let Some(Reverse((len, pos))) = queue.pop() {
+        if Some(pos) == stop {
+            return Some(len);
+        }
+        if best.get(&pos).is_some_and(|&known| known <= len) {
+            continue;
+        }
+        best.insert(pos, len);
+
+        let op = CompiledOp::from_byte(*buffer.get(pos)?)?;
+        let next = pos + instruction_len(buffer, pos)?;
+        let edges = match op {
+            CompiledOp::End if stop.is_none() => return Some(len),
+            CompiledOp::End | CompiledOp::RepeatEnd => vec![],
+
+            CompiledOp::Exact
+            | CompiledOp::AnyChar
+            | CompiledOp::Set
+            | CompiledOp::SyntaxSpec
+            | CompiledOp::NotSyntaxSpec => vec![(next, 1)],
+
+            CompiledOp::ExactString => {
+                let run = std::str::from_utf8(buffer.get(pos + 2..next)?).ok()?;
+                vec![(next, run.chars().count())]
+            }
+
+            CompiledOp::Bol
+            | CompiledOp::Eol
+            | CompiledOp::BegBuf
+            | CompiledOp::EndBuf
+            | CompiledOp::SearchStart
+            | CompiledOp::WordBeg
+            | CompiledOp::WordEnd
+            | CompiledOp::WordBound
+            | CompiledOp::NotWordBound
+            | CompiledOp::StartMemory
+            | CompiledOp::EndMemory
+            | CompiledOp::MatchMemory
+            | CompiledOp::Call
+            | CompiledOp::RepeatPop
+            | CompiledOp::AtomicStart
+            | CompiledOp::AtomicEnd => vec![(next, 0)],
+
+            CompiledOp::Jump | CompiledOp::StarJump | CompiledOp::UpdateFailureJump => {
+                vec![(jump_target(buffer, pos)?, 0)]
+            }
+            CompiledOp::FailureJump => vec![(next, 0), (jump_target(buffer, pos)?, 0)],
+
+            // Skips the failure jump that follows it (see the matcher)
+            CompiledOp::DummyFailureJump => vec![(next + 3, 0)],
+
+            // The body runs at least `min` times
+            CompiledOp::RepeatStart => {
+                let repeat = Repeat::read(buffer, pos)?;
+                let body = match repeat.min {
+                    0 => 0,
+                    min => min * min_len_from(buffer, next, Some(repeat.end))?,
+                };
+                vec![(repeat.exit, body)]
+            }
+        };
+
+        for (target, cost) in edges {
+            queue.push(Reverse((len + cost, target)));
+        }
+    }
+    None
+}
+
+/// The operands of a `RepeatStart` instruction
+struct Repeat {
+    min: usize,
+    /// `None` when unbounded
+    max: Option<usize>,
+    /// Position of the matching `RepeatEnd`
+    end: usize,
+    /// Position of the matching `RepeatPop`
+    exit: usize,
+}
+
+impl Repeat {
+    fn read(buffer: &[u8], pos: usize) -> Option<Self> {
+        let word = |offset: usize| {
+            Some(u16::from_le_bytes([
+                *buffer.get(pos + offset)?,
+                *buffer.get(pos + offset + 1)?,
+            ]))
+        };
+        let max = word(3)?;
+        let exit = pos + 8 + word(6)? as usize;
+        Some(Self {
+            min: word(1)? as usize,
+            max: (max != REPEAT_UNBOUNDED).then_some(max as usize),
+            end: exit.checked_sub(3)?,
+            exit,
+        })
+    }
+}
+
+/// Whether a failed resume state `(code_pos, text_pos)` fails every time
+///
+/// Back-references, recursion, counted repetitions, atomic groups and
+/// `update_failure_jump` make what follows a state depend on registers,
+/// counters or the failure stack, so states cannot be memoized in patterns
+/// using them. Neither can loops whose body may match nothing: the matcher
+/// leaves such a loop after an empty round, so what a state leads to
+/// depends on where the round containing it began, and the state can be
+/// reached again while it is still being explored.
+pub fn memoizable(buffer: &[u8]) -> bool {
+    let mut pos = 0;
+    while let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) {
+        match op {
+            CompiledOp::End => return true,
+            CompiledOp::MatchMemory
+            | CompiledOp::Call
+            | CompiledOp::RepeatStart
+            | CompiledOp::AtomicStart
+            | CompiledOp::UpdateFailureJump => return false,
+            CompiledOp::StarJump => match jump_target(buffer, pos) {
+                Some(fail) if min_len_from(buffer, fail + 3, Some(pos)) != Some(0) => {}
+                _ => return false,
+            },
+            _ => {}
+        }
+        match instruction_len(buffer, pos) {
+            Some(len) => pos += len,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Whether any `SyntaxSpec` or `NotSyntaxSpec` in the pattern tests `syntax`
+fn uses_syntax(buffer: &[u8], syntax: SyntaxType) -> bool {
+    let mut pos = 0;
+    while let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) {
+        match op {
+            CompiledOp::End => return false,
+            CompiledOp::SyntaxSpec | CompiledOp::NotSyntaxSpec
+                if buffer.get(pos + 1) == Some(&(syntax as u8)) =>
+            {
+                return true
+            }
+            _ => {}
+        }
+        match instruction_len(buffer, pos) {
+            Some(len) => pos += len,
+            None => return true,
+        }
+    }
+    true
+}
+
+/// Positions of the `star_jump`s that may update their loop's failure point
+/// instead of pushing a new one every round
+///
+/// This is re_optimize_star_jump: when the loop body is a straight run of
+/// simple instructions and nothing after the loop can start with a char the
+/// body starts with, giving back rounds of the loop never leads to a match,
+/// so only the failure point for the last round is worth keeping. Every
+/// other `star_jump` acts as a plain jump back to its `failure_jump`.
+pub fn update_star_jumps(buffer: &[u8]) -> BTreeSet<usize> {
+    let mut found = BTreeSet::new();
+    let mut pos = 0;
+    while let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) {
+        match op {
+            // What follows a loop inside a recursed group depends on the caller
+            CompiledOp::Call => return BTreeSet::new(),
+            CompiledOp::StarJump if star_jump_can_update(buffer, pos) => {
+                found.insert(pos);
+            }
+            _ => {}
+        }
+        match instruction_len(buffer, pos) {
+            Some(len) => pos += len,
+            None => return BTreeSet::new(),
+        }
+    }
+    found
+}
+
+/// Whether the `star_jump` at `star` can become an `update_failure_jump`
+fn star_jump_can_update(buffer: &[u8], star: usize) -> bool {
+    let Some(fail) = jump_target(buffer, star) else {
+        return false;
+    };
+    let exit = star + 3;
+    if buffer.get(fail).copied().and_then(CompiledOp::from_byte) != Some(CompiledOp::FailureJump)
+        || jump_target(buffer, fail) != Some(exit)
+    {
+        return false;
+    }
+
+    // The body must push no failure points of its own, so the one on top
+    // when it finishes is the loop's, and must consume a char every round
+    let mut first = None;
+    let mut pos = fail + 3;
+    while pos < star {
+        let Some(op) = CompiledOp::from_byte(buffer[pos]) else {
+            return false;
+        };
+        match op {
+            CompiledOp::Bol
+            | CompiledOp::Eol
+            | CompiledOp::BegBuf
+            | CompiledOp::EndBuf
+            | CompiledOp::SearchStart
+            | CompiledOp::WordBeg
+            | CompiledOp::WordEnd
+            | CompiledOp::WordBound
+            | CompiledOp::NotWordBound
+            | CompiledOp::StartMemory
+            | CompiledOp::EndMemory => {}
+            CompiledOp::Exact | CompiledOp::ExactString | CompiledOp::Set | CompiledOp::AnyChar => {
+                if first.is_none() {
+                    first = Some(pos);
+                }
+            }
+            _ => return false,
+        }
+        match instruction_len(buffer, pos) {
+            Some(len) => pos += len,
+            None => return false,
+        }
+    }
+    let Some(first) = first else {
+        return false;
+    };
+
+    // Rounds given back would leave the loop before one of the body's first
+    // chars, so what follows must not be able to take any of them. A
+    // follower that can match without consuming anything only counts when
+    // it reaches the end unconditionally, as then the last round matches too.
+    let mut follow = Vec::new();
+    if !collect_follow(buffer, exit, &mut follow, &mut HashSet::new()) {
+        return false;
+    }
+    let mut starts = Vec::new();
+    if collect_first(buffer, first, &mut starts, &mut HashSet::new()) {
+        !starts
+            .iter()
+            .any(|&(lo, hi)| follow.iter().any(|&(flo, fhi)| lo <= fhi && flo <= hi))
+    } else {
+        // `.` or a complemented set may take any char
+        follow.is_empty()
+    }
+}
+
+/// Add the ranges of chars that can be consumed first from `pos`, for
+/// [`star_jump_can_update`]
+///
+/// Unlike [`collect_first`], reaching the end is fine, but any instruction
+/// whose chars depend on the syntax table, the newline char or the position
+/// gives up, as does anything consuming more than a known set of chars.
+fn collect_follow(
+    buffer: &[u8],
+    pos: usize,
+    ranges: &mut Vec<(char, char)>,
+    visited: &mut HashSet<usize>,
+) -> bool {
+    if !visited.insert(pos) {
+        return true;
+    }
+    let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) else {
+        return false;
+    };
+    let Some(next) = instruction_len(buffer, pos).map(|len| pos + len) else {
+        return false;
+    };
+
+    match op {
+        CompiledOp::End => true,
+
+        CompiledOp::Exact | CompiledOp::ExactString | CompiledOp::Set => {
+            collect_first(buffer, pos, ranges, &mut HashSet::new())
+        }
+
+        CompiledOp::StartMemory | CompiledOp::EndMemory => {
+            collect_follow(buffer, next, ranges, visited)
+        }
+
+        CompiledOp::Jump | CompiledOp::StarJump => match jump_target(buffer, pos) {
+            Some(target) => collect_follow(buffer, target, ranges, visited),
+            None => false,
+        },
+
+        CompiledOp::FailureJump => match jump_target(buffer, pos) {
+            Some(target) => {
+                collect_follow(buffer, next, ranges, visited)
+                    && collect_follow(buffer, target, ranges, visited)
+            }
+            None => false,
+        },
+
+        // Skips the failure jump that follows it (see the matcher)
+        CompiledOp::DummyFailureJump => collect_follow(buffer, next + 3, ranges, visited),
+
+        _ => false,
+    }
+}
+
+/// Whether the pattern is exactly `.*`, so it matches at every position
+///
+/// Such a pattern needs no search: the first match starts at 0 and runs up
+/// to the first newline, or the whole text when `.` matches newlines.
+pub fn matches_any_prefix(buffer: &[u8]) -> bool {
+    let [fail, fail_lo, fail_hi, any, star, star_lo, star_hi, end] = buffer else {
+        return false;
+    };
+    let op = |byte: &u8| CompiledOp::from_byte(*byte);
+    op(fail) == Some(CompiledOp::FailureJump)
+        && op(any) == Some(CompiledOp::AnyChar)
+        && op(star) == Some(CompiledOp::StarJump)
+        && op(end) == Some(CompiledOp::End)
+        && i16::from_le_bytes([*fail_lo, *fail_hi]) == 4
+        && i16::from_le_bytes([*star_lo, *star_hi]) == -7
+}
+
+/// The chars a match can start with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirstSet {
+    /// Any char may start a match, or the pattern can match the empty string
+    Any,
+    /// A match starts with one of these chars
+    Chars(BTreeSet<char>),
+    /// A match starts with a char in one of these sorted, disjoint inclusive ranges
+    Ranges(Vec<(char, char)>),
+}
+
+impl FirstSet {
+    /// Whether a match can start with `ch`
+    pub fn contains(&self, ch: char) -> bool {
+        match self {
+            FirstSet::Any => true,
+            FirstSet::Chars(chars) => chars.contains(&ch),
+            FirstSet::Ranges(ranges) => ranges.iter().any(|&(lo, hi)| lo <= ch && ch <= hi),
+        }
+    }
+}
+
+/// The chars a match can start with, following every alternative
+///
+/// `fold_ascii_case` adds the uppercase form of every lowercase ASCII letter,
+/// for patterns whose input is case folded before matching. With
+/// `unicode_word`, `\w` matches more than the ASCII ranges collected for it,
+/// so patterns using it get no first set.
+pub fn first_set(buffer: &[u8], fold_ascii_case: bool, unicode_word: bool) -> FirstSet {
+    let mut ranges = Vec::new();
+    let mut visited = HashSet::new();
+    if !collect_first(buffer, 0, &mut ranges, &mut visited)
+        || (unicode_word && uses_syntax(buffer, SyntaxType::Word))
+    {
+        return FirstSet::Any;
+    }
+
+    if fold_ascii_case {
+        for (lo, hi) in ranges.clone() {
+            let (lo, hi) = (lo.max('a'), hi.min('z'));
+            if lo <= hi {
+                ranges.push((lo.to_ascii_uppercase(), hi.to_ascii_uppercase()));
+            }
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+    for (lo, hi) in ranges {
+        match merged.last_mut() {
+            Some(last) if lo as u32 <= last.1 as u32 + 1 => last.1 = last.1.max(hi),
+            _ => merged.push((lo, hi)),
+        }
+    }
+
+    if merged.iter().all(|&(lo, hi)| lo == hi) {
+        FirstSet::Chars(merged.into_iter().map(|(ch, _)| ch).collect())
+    } else {
+        FirstSet::Ranges(merged)
+    }
+}
+
+/// Add the ranges of chars that can be consumed first from `pos`
+///
+/// Returns false when any char can come first, or none need be consumed.
+/// Like re_do_compile_fastmap, a path that loops back to an instruction
+/// already visited is abandoned, as it leads nowhere new.
+fn collect_first(
+    buffer: &[u8],
+    pos: usize,
+    ranges: &mut Vec<(char, char)>,
+    visited: &mut HashSet<usize>,
+) -> bool {
+    if !visited.insert(pos) {
+        return true;
+    }
+    let Some(op) = buffer.get(pos).copied().and_then(CompiledOp::from_byte) else {
+        return false;
+    };
+    let Some(next) = instruction_len(buffer, pos).map(|len| pos + len) else {
+        return false;
+    };
+
+    match op {
+        CompiledOp::Exact | CompiledOp::ExactString => {
+            match std::str::from_utf8(&buffer[pos + 2..next])
+                .ok()
+                .and_then(|run| run.chars().next())
+            {
+                Some(ch) => {
+                    ranges.push((ch, ch));
+                    true
+                }
+                None => false,
+            }
+        }
+
+        CompiledOp::Set => match set_ranges(buffer, pos) {
+            Some((false, set)) => {
+                ranges.extend(set);
+                true
+            }
+            _ => false,
+        },
+
+        CompiledOp::SyntaxSpec => {
+            match buffer.get(pos + 1).copied().and_then(SyntaxType::from_byte) {
+                Some(SyntaxType::Word) => {
+                    ranges.extend([('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')]);
+                    true
+                }
+                Some(SyntaxType::Digit) => {
+                    ranges.push(('0', '9'));
+                    true
+                }
+                Some(SyntaxType::Space) => {
+                    ranges.extend([('\t', '\r'), (' ', ' ')]);
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        CompiledOp::Bol
+        | CompiledOp::Eol
+        | CompiledOp::BegBuf
+        | CompiledOp::EndBuf
+        | CompiledOp::SearchStart
+        | CompiledOp::WordBeg
+        | CompiledOp::WordEnd
+        | CompiledOp::WordBound
+        | CompiledOp::NotWordBound
+        | CompiledOp::StartMemory
+        | CompiledOp::EndMemory
+        | CompiledOp::RepeatPop
+        | CompiledOp::AtomicStart
+        | CompiledOp::AtomicEnd => collect_first(buffer, next, ranges, visited),
+
+        // The body comes first, unless it may be skipped altogether
+        CompiledOp::RepeatStart => match Repeat::read(buffer, pos) {
+            Some(repeat) => {
+                collect_first(buffer, next, ranges, visited)
+                    && (repeat.min > 0 || collect_first(buffer, repeat.exit, ranges, visited))
+            }
+            None => false,
+        },
+
+        // An empty body may loop back or leave the repetition
+        CompiledOp::RepeatEnd => {
+            let disp = i16::from_le_bytes([buffer[pos + 1], buffer[pos + 2]]);
+            match usize::try_from(pos as i32 + 3 + disp as i32) {
+                Ok(start) => {
+                    collect_first(buffer, start, ranges, visited)
+                        && collect_first(buffer, next, ranges, visited)
+                }
+                Err(_) => false,
+            }
+        }
+
+        CompiledOp::Jump | CompiledOp::StarJump | CompiledOp::UpdateFailureJump => {
+            match jump_target(buffer, pos) {
+                Some(target) => collect_first(buffer, target, ranges, visited),
+                None => false,
+            }
+        }
+
+        CompiledOp::FailureJump => match jump_target(buffer, pos) {
+            Some(target) => {
+                collect_first(buffer, next, ranges, visited)
+                    && collect_first(buffer, target, ranges, visited)
+            }
+            None => false,
+        },
+
+        // Skips the failure jump that follows it (see the matcher)
+        CompiledOp::DummyFailureJump => collect_first(buffer, next + 3, ranges, visited),
+
+        CompiledOp::End
+        | CompiledOp::AnyChar
+        | CompiledOp::NotSyntaxSpec
+        | CompiledOp::MatchMemory
+        | CompiledOp::Call => false,
+    }
+}
+
+/// Decode the complement flag and ranges of the `Set` instruction at `pos`
+fn set_ranges(buffer: &[u8], pos: usize) -> Option<(bool, Vec<(char, char)>)> {
+    let complement = *buffer.get(pos + 1)? != 0;
+    let num_ranges = *buffer.get(pos + 2)? as usize;
+
+    let mut offset = pos + 3;
+    let mut read_char = || {
+        let len = *buffer.get(offset)? as usize;
+        let ch = std::str::from_utf8(buffer.get(offset + 1..offset + 1 + len)?)
+            .ok()?
+            .chars()
+            .next()?;
+        offset += 1 + len;
+        Some(ch)
+    };
+
+    let mut ranges = Vec::with_capacity(num_ranges);
+    for _ in 0..num_ranges {
+        let lo = read_char()?;
+        let hi = read_char()?;
+        ranges.push((lo, hi));
+    }
+    Some((complement, ranges))
+}
+
+/// Target of the jump instruction at `pos`, if it is one
+pub fn jump_target(buffer: &[u8], pos: usize) -> Option<usize> {
+    match CompiledOp::from_byte(*buffer.get(pos)?)? {
+        CompiledOp::Jump
+        | CompiledOp::StarJump
+        | CompiledOp::FailureJump
+        | CompiledOp::UpdateFailureJump
+        | CompiledOp::DummyFailureJump => {
+            let low = *buffer.get(pos + 1)? as i16;
+            let high = *buffer.get(pos + 2)? as i16;
+            let disp = low | (high << 8);
+            usize::try_from(pos as i32 + 3 + disp as i32).ok()
+        }
+        _ => None,
+    }
+}
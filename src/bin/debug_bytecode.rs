@@ -8,14 +8,7 @@ fn main() {
         match Regex::new(pattern) {
             Ok(regex) => {
                 println!("  Bytecode length: {}", regex.buffer.len());
-                print!("  Bytecode: ");
-                for (i, byte) in regex.buffer.iter().enumerate() {
-                    print!("{byte:02x}");
-                    if i < regex.buffer.len() - 1 {
-                        print!(" ");
-                    }
-                }
-                println!();
+                print!("{}", regex.disassemble());
 
                 // Test some simple cases
                 let test_cases = ["", "a", "aa", "b"];
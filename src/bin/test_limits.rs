@@ -21,6 +21,7 @@ fn main() {
     let limits = ExecLimits {
         max_ticks: Some(100), // Very low limit
         max_failures: 50,     // Low limit
+        ..Default::default()
     };
 
     println!("\nTesting with limits (max_ticks: 100, max_failures: 50):");
@@ -39,6 +40,7 @@ fn main() {
     let test_limits = ExecLimits {
         max_ticks: Some(1000),
         max_failures: 100,
+        ..Default::default()
     };
 
     println!("\nTesting with test limits (max_ticks: 1000, max_failures: 100):");
@@ -4,16 +4,50 @@
 //! in regexpr.c:254-721
 
 use crate::{
-    error::{RegexError, Result},
-    opcodes::{CompiledOp, SyntaxOp},
+    analysis,
+    error::{CompileError, RegexError, Result},
+    opcodes::{instruction_len, CompiledOp, SyntaxOp, SyntaxType, REPEAT_UNBOUNDED},
     syntax::SyntaxFlags,
-    Regex, RE_NREGS,
+    Anchor, ExecLimits, Regex, RE_NREGS,
 };
 
 /// Maximum nesting level of operators
 const MAX_NESTING: usize = 100;
 /// Number of precedence levels
 const NUM_LEVELS: usize = 5;
+/// Most ranges a bracket expression can hold, its range count being a byte
+pub const MAX_SET_RANGES: usize = u8::MAX as usize;
+
+/// Translation table folding uppercase letters to lowercase
+///
+/// Covers ASCII and the accented letters of Latin-1 (`À` to `Þ`, less the
+/// multiplication sign), each of which lowercases to the char 0x20 above.
+fn case_fold_table() -> std::collections::BTreeMap<char, char> {
+    ('A'..='Z')
+        .chain(('À'..='Þ').filter(|&c| c != '×'))
+        .map(|c| (c, char::from_u32(c as u32 + 0x20).unwrap()))
+        .collect()
+}
+
+/// Combine a translate table with case folding
+///
+/// Chars are folded both before and after the lookup in `table`, so an
+/// uppercase char reaches the entry for its lowercase form and whatever
+/// `table` gives is folded too.
+fn with_case_folding(
+    table: &std::collections::BTreeMap<char, char>,
+) -> std::collections::BTreeMap<char, char> {
+    let fold = case_fold_table();
+    let folded = |ch: char| fold.get(&ch).copied().unwrap_or(ch);
+    fold.keys()
+        .chain(table.keys())
+        .filter_map(|&ch| {
+            let lower = folded(ch);
+            let mapped = folded(table.get(&lower).copied().unwrap_or(lower));
+            (mapped != ch).then_some((ch, mapped))
+        })
+        .collect()
+}
 
 /// Compiler state for translating regex patterns to bytecode
 struct Compiler {
@@ -26,7 +60,7 @@ struct Compiler {
     /// Syntax configuration
     syntax: SyntaxFlags,
     /// Translation table for case conversion (maps char to char)
-    translate: Option<std::collections::HashMap<char, char>>,
+    translate: Option<std::collections::BTreeMap<char, char>>,
     /// Operator precedence tables (now use char keys for Unicode support)
     plain_ops: std::collections::HashMap<char, SyntaxOp>,
     quoted_ops: std::collections::HashMap<char, SyntaxOp>,
@@ -40,10 +74,35 @@ struct Compiler {
     /// Register tracking
     next_register: u8,
     paren_depth: usize,
-    num_open_registers: usize,
-    open_registers: [u8; RE_NREGS],
+    /// What each open parenthesis emits when closed, innermost last
+    open_groups: Vec<OpenGroup>,
     /// Context state
     beginning_context: bool,
+    /// Highest group number targeted by a `\g<n>` recursion
+    max_recursed_group: u8,
+    /// Start of the literal instruction the next plain char may extend
+    literal_run: Option<usize>,
+    /// Name of each group by register number, `None` when unnamed
+    group_names: Vec<Option<String>>,
+    /// Name for the group the pending open parenthesis starts
+    pending_group_name: Option<String>,
+    /// Whether the pending open parenthesis starts an atomic group
+    pending_atomic: bool,
+    /// Errors collected so far, when compiling on past errors
+    diagnostics: Option<Vec<RegexError>>,
+    /// Most ranges a bracket expression may hold once merged
+    max_set_ranges: usize,
+}
+
+/// The kind of group an open parenthesis started
+#[derive(Debug, Clone, Copy)]
+enum OpenGroup {
+    /// Capturing group with its register
+    Capture(u8),
+    /// Atomic group `(?>...)`
+    Atomic,
+    /// Group past the last register, which groups but does not capture
+    Uncaptured,
 }
 
 impl Compiler {
@@ -52,16 +111,7 @@ impl Compiler {
         let pattern_chars: Vec<char> = pattern.chars().collect();
 
         // Set up case insensitive translation table if needed
-        let translate = if syntax.case_insensitive() {
-            let mut table = std::collections::HashMap::new();
-            // Map uppercase letters to lowercase for case insensitive matching
-            for c in 'A'..='Z' {
-                table.insert(c, c.to_ascii_lowercase());
-            }
-            Some(table)
-        } else {
-            None
-        };
+        let translate = syntax.case_insensitive().then(case_fold_table);
 
         let mut compiler = Compiler {
             pattern: pattern_chars,
@@ -79,9 +129,15 @@ impl Compiler {
             current_level: 0,
             next_register: 1,
             paren_depth: 0,
-            num_open_registers: 0,
-            open_registers: [0; RE_NREGS],
+            open_groups: Vec::new(),
             beginning_context: true,
+            max_recursed_group: 0,
+            literal_run: None,
+            group_names: vec![None],
+            pending_group_name: None,
+            pending_atomic: false,
+            diagnostics: None,
+            max_set_ranges: MAX_SET_RANGES,
         };
 
         compiler.initialize_tables();
@@ -145,12 +201,34 @@ impl Compiler {
         if !self.syntax.no_gnu_extensions() {
             self.quoted_ops.insert('w', SyntaxOp::WordChar);
             self.quoted_ops.insert('W', SyntaxOp::NotWordChar);
+            self.quoted_ops.insert('d', SyntaxOp::DigitChar);
+            self.quoted_ops.insert('D', SyntaxOp::NotDigitChar);
+            self.quoted_ops.insert('s', SyntaxOp::SpaceChar);
+            self.quoted_ops.insert('S', SyntaxOp::NotSpaceChar);
             self.quoted_ops.insert('<', SyntaxOp::WordBeg);
             self.quoted_ops.insert('>', SyntaxOp::WordEnd);
             self.quoted_ops.insert('b', SyntaxOp::WordBound);
             self.quoted_ops.insert('B', SyntaxOp::NotWordBound);
             self.quoted_ops.insert('`', SyntaxOp::BegBuf);
             self.quoted_ops.insert('\'', SyntaxOp::EndBuf);
+            self.quoted_ops.insert('A', SyntaxOp::BegBuf);
+            self.quoted_ops.insert('z', SyntaxOp::EndBuf);
+            self.quoted_ops.insert('=', SyntaxOp::Point);
+        }
+
+        // Counted repetition
+        if self.syntax.intervals() {
+            if self.syntax.needs_backslash_braces() {
+                self.quoted_ops.insert('{', SyntaxOp::OpenBrace);
+            } else {
+                self.plain_ops.insert('{', SyntaxOp::OpenBrace);
+            }
+        }
+
+        // Perl-style extensions
+        if self.syntax.perl_extensions() {
+            self.quoted_ops.insert('g', SyntaxOp::Recurse);
+            self.quoted_ops.insert('G', SyntaxOp::SearchStart);
         }
 
         // Extended memory
@@ -175,6 +253,46 @@ impl Compiler {
         self.precedences[SyntaxOp::End as usize] = 0;
     }
 
+    /// Apply the translation table and ASCII case folding to a pattern character
+    fn translate_char(&self, ch: char) -> char {
+        crate::translate_char(self.translate.as_ref(), self.syntax, ch)
+    }
+
+    /// The chars of `start..=end` once translated, as ranges
+    ///
+    /// Every member is translated, as regexpr.c does building its set char
+    /// by char through the translate table, not just the two ends: the chars
+    /// the table or ASCII case folding change leave the range, and each one's
+    /// translation is added on its own.
+    fn translate_range(&self, start: char, end: char) -> Vec<(char, char)> {
+        let mut changed: std::collections::BTreeSet<char> = self
+            .translate
+            .iter()
+            .flat_map(|table| table.range(start..=end).map(|(&ch, _)| ch))
+            .collect();
+        if self.syntax.case_insensitive_ascii() {
+            changed.extend(('A'..='Z').filter(|ch| (start..=end).contains(ch)));
+        }
+        changed.retain(|&ch| self.translate_char(ch) != ch);
+
+        let mut ranges = Vec::with_capacity(2 * changed.len() + 1);
+        let mut from = Some(start);
+        for ch in changed {
+            if let Some(lo) = from.filter(|&lo| lo < ch) {
+                // The surrogates lie between U+D7FF and U+E000
+                let hi = char::from_u32(ch as u32 - 1).unwrap_or('\u{D7FF}');
+                ranges.push((lo, hi));
+            }
+            let translated = self.translate_char(ch);
+            ranges.push((translated, translated));
+            from = (ch != char::MAX).then(|| char::from_u32(ch as u32 + 1).unwrap_or('\u{E000}'));
+        }
+        if let Some(lo) = from.filter(|&lo| lo <= end) {
+            ranges.push((lo, end));
+        }
+        ranges
+    }
+
     /// Get next character from pattern
     fn next_char(&mut self) -> Result<char> {
         if self.pos >= self.pattern.len() {
@@ -186,6 +304,23 @@ impl Compiler {
         Ok(ch)
     }
 
+    /// Look at the next pattern character without consuming it
+    fn peek_char(&self) -> Option<char> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    /// Consume `expected` as the next pattern character
+    fn expect_char(&mut self, expected: char) -> Result<()> {
+        if self.next_char()? != expected {
+            return Err(CompileError::ExpectedChar {
+                expected,
+                position: self.pos - 1,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
     /// Store a byte in the output buffer
     fn store(&mut self, byte: u8) {
         self.buffer.push(byte);
@@ -220,6 +355,53 @@ impl Compiler {
         }
     }
 
+    /// Store a plain literal char, extending the preceding literal if possible
+    ///
+    /// Consecutive literals are coalesced into a single `ExactString` so the
+    /// matcher compares the whole run in one dispatch.
+    fn store_literal(&mut self, ch: char) {
+        let mut bytes = [0; 4];
+        let utf8_bytes = ch.encode_utf8(&mut bytes).as_bytes();
+
+        if let Some(start) = self.literal_run {
+            let run_len = self.buffer[start + 1] as usize + utf8_bytes.len();
+            if run_len <= u8::MAX as usize {
+                self.buffer[start] = CompiledOp::ExactString.to_byte();
+                self.buffer[start + 1] = run_len as u8;
+                self.buffer.extend_from_slice(utf8_bytes);
+                return;
+            }
+        }
+
+        self.set_level_start();
+        self.literal_run = Some(self.buffer.len());
+        self.store_opcode_and_char(CompiledOp::Exact, ch);
+    }
+
+    /// Split the last char off a pending literal run so a following
+    /// quantifier applies to that char alone
+    fn split_literal_run(&mut self) {
+        let Some(start) = self.literal_run.take() else {
+            return;
+        };
+        if self.buffer[start] != CompiledOp::ExactString.to_byte() {
+            return;
+        }
+
+        let run = std::str::from_utf8(&self.buffer[start + 2..]).unwrap();
+        let last = run.chars().next_back().unwrap();
+        let single_char_left = run.chars().count() == 2;
+        let kept_len = run.len() - last.len_utf8();
+        self.buffer.truncate(start + 2 + kept_len);
+        if single_char_left {
+            self.buffer[start] = CompiledOp::Exact.to_byte();
+        }
+        self.buffer[start + 1] = kept_len as u8;
+
+        self.set_level_start();
+        self.store_opcode_and_char(CompiledOp::Exact, last);
+    }
+
     /// Get current buffer position for level start tracking
     fn current_level_start(&self) -> usize {
         self.starts[self.starts_base + self.current_level]
@@ -270,21 +452,157 @@ impl Compiler {
         }
     }
 
+    /// Insert raw bytes at given position, keeping pending jumps in place
+    fn insert_bytes(&mut self, pos: usize, bytes: &[u8]) {
+        self.buffer.splice(pos..pos, bytes.iter().copied());
+
+        for i in 0..self.num_jumps {
+            if self.future_jumps[i] >= pos {
+                self.future_jumps[i] += bytes.len();
+            }
+        }
+    }
+
+    /// Consume the `?` making the quantifier just read lazy, if there is one
+    ///
+    /// Only under `PERL_EXTENSIONS`; otherwise a following `?` applies to
+    /// the quantified expression as usual.
+    fn take_lazy_suffix(&mut self) -> bool {
+        let lazy = self.syntax.perl_extensions()
+            && self.peek_char() == Some('?')
+            && self.plain_ops.get(&'?') == Some(&SyntaxOp::Optional);
+        if lazy {
+            self.pos += 1;
+        }
+        lazy
+    }
+
+    /// Wrap the expression at the current level start in a counted repetition
+    ///
+    /// The body runs under a counter instead of being unrolled, so the
+    /// bytecode stays the same size whatever the counts:
+    ///   repeat_start min max lazy exit; body; repeat_end start; exit: repeat_pop
+    fn store_repeat(&mut self, min: u16, max: u16, lazy: bool) {
+        let start = self.current_level_start();
+        let body_len = self.buffer.len() - start;
+        let exit_disp = (body_len + 3) as u16;
+        let [min_lo, min_hi] = min.to_le_bytes();
+        let [max_lo, max_hi] = max.to_le_bytes();
+        let [exit_lo, exit_hi] = exit_disp.to_le_bytes();
+        self.insert_bytes(
+            start,
+            &[
+                CompiledOp::RepeatStart.to_byte(),
+                min_lo,
+                min_hi,
+                max_lo,
+                max_hi,
+                u8::from(lazy),
+                exit_lo,
+                exit_hi,
+            ],
+        );
+
+        let back_disp = start as i32 - (self.buffer.len() as i32 + 3);
+        self.store_opcode(CompiledOp::RepeatEnd);
+        self.store((back_disp & 0xff) as u8);
+        self.store(((back_disp >> 8) & 0xff) as u8);
+        self.store_opcode(CompiledOp::RepeatPop);
+    }
+
+    /// Parse the `n}`, `n,}` or `n,m}` following an interval's open brace
+    ///
+    /// Returns the minimum and maximum counts, the maximum being
+    /// `REPEAT_UNBOUNDED` when omitted.
+    fn parse_interval(&mut self) -> Result<(u16, u16)> {
+        let position = self.pos;
+        let invalid = CompileError::InvalidInterval { position };
+
+        let min = self.parse_count()?.ok_or(invalid.clone())?;
+        let max = if self.peek_char() == Some(',') {
+            self.pos += 1;
+            self.parse_count()?.unwrap_or(REPEAT_UNBOUNDED)
+        } else {
+            min
+        };
+
+        if self.syntax.needs_backslash_braces() && self.next_char()? != '\\' {
+            return Err(invalid.into());
+        }
+        if self.next_char()? != '}' || min > max || min == REPEAT_UNBOUNDED {
+            return Err(invalid.into());
+        }
+        Ok((min, max))
+    }
+
+    /// Parse an optional decimal repetition count
+    fn parse_count(&mut self) -> Result<Option<u16>> {
+        let mut count: Option<u16> = None;
+        while let Some(digit) = self.peek_char().and_then(|ch| ch.to_digit(10)) {
+            self.pos += 1;
+            count = count
+                .unwrap_or(0)
+                .checked_mul(10)
+                .and_then(|count| count.checked_add(digit as u16))
+                .filter(|&count| count < REPEAT_UNBOUNDED)
+                .map(Some)
+                .ok_or(CompileError::InvalidInterval {
+                    position: self.pos - 1,
+                })?;
+        }
+        Ok(count)
+    }
+
     /// Parse hexadecimal escape sequence
+    ///
+    /// `\xHH` gives a Latin-1 char, and `\x{H...}` any Unicode scalar value.
     fn get_hex(&mut self) -> Result<char> {
+        if self.peek_char() == Some('{') {
+            self.pos += 1;
+            let mut value: u32 = 0;
+            let mut digits = 0;
+            loop {
+                let ch = self.next_char()?;
+                if ch == '}' && digits > 0 {
+                    break;
+                }
+                let digit = hex_char_to_decimal(ch)?;
+                value = value
+                    .checked_mul(16)
+                    .and_then(|value| value.checked_add(digit.into()))
+                    .ok_or(RegexError::BadHexEscape)?;
+                digits += 1;
+            }
+            return char::from_u32(value).ok_or(RegexError::BadHexEscape);
+        }
+
         let ch1 = self.next_char()?;
         let val1 = hex_char_to_decimal(ch1)?;
 
         let ch2 = self.next_char()?;
         let val2 = hex_char_to_decimal(ch2)?;
 
-        let byte_val = val1 * 16 + val2;
-        // Convert byte value to char (only works for ASCII range)
-        if byte_val > 127 {
-            return Err(RegexError::BadHexEscape);
+        // Two digits name a byte, taken as the Latin-1 char of that value
+        Ok(char::from(val1 * 16 + val2))
+    }
+
+    /// Parse the four hex digits of a `\uXXXX` escape
+    fn get_unicode(&mut self) -> Result<char> {
+        let mut value: u32 = 0;
+        for _ in 0..4 {
+            value = value * 16 + u32::from(hex_char_to_decimal(self.next_char()?)?);
+        }
+        char::from_u32(value).ok_or(RegexError::BadHexEscape)
+    }
+
+    /// Parse the char following `\c`, giving the control char it names
+    fn get_control(&mut self) -> Result<char> {
+        let ch = self.next_char()?;
+        if !ch.is_ascii() || ch.is_ascii_control() {
+            return Err(CompileError::InvalidControlEscape(ch).into());
         }
 
-        Ok(byte_val as char)
+        Ok((ch.to_ascii_uppercase() as u8 ^ 0x40) as char)
     }
 
     /// Translate ANSI escape sequences
@@ -298,71 +616,244 @@ impl Compiler {
             't' | 'T' => '\t',   // tab
             'v' | 'V' => '\x0B', // vertical tab
             'x' | 'X' => return self.get_hex(),
+            'u' => return self.get_unicode(),
+            'c' => return self.get_control(), // control char (\cA is 0x01)
             _ => {
                 // Apply translation table if available
-                let Some(ref translate) = self.translate else {
-                    return Ok(ch);
-                };
-
-                translate.get(&ch).copied().unwrap_or(ch)
+                self.translate_char(ch)
             }
         };
         Ok(result)
     }
 
-    /// Main compilation loop
+    /// Compile the whole pattern, stopping at the first error
     fn compile(mut self) -> Result<Regex> {
-        self.set_level_start();
-        let mut op = SyntaxOp::Normal;
-
-        // Main parsing loop
-        while op != SyntaxOp::End {
-            let ch = if self.pos >= self.pattern.len() {
-                op = SyntaxOp::End;
-                '\0'
-            } else {
-                let mut ch = self.next_char()?;
-
-                // Apply translation if available
-                if let Some(ref translate) = self.translate {
-                    ch = translate.get(&ch).copied().unwrap_or(ch);
-                }
+        self.parse()?;
+        Ok(self.into_regex())
+    }
 
-                op = self.plain_ops.get(&ch).copied().unwrap_or(SyntaxOp::Normal);
+    /// Compile every token of the pattern into the buffer
+    fn parse(&mut self) -> Result<()> {
+        self.set_level_start();
 
-                if op == SyntaxOp::Quote {
-                    ch = self.next_char()?;
-                    op = self
-                        .quoted_ops
-                        .get(&ch)
-                        .copied()
-                        .unwrap_or(SyntaxOp::Normal);
+        // Main parsing loop. End of pattern is tracked by position alone so
+        // that a literal NUL in the pattern is an ordinary character.
+        while self.pos < self.pattern.len() {
+            self.pos = self.significant_pos();
+            if self.pos >= self.pattern.len() {
+                break;
+            }
 
-                    if op == SyntaxOp::Normal && self.syntax.ansi_sequences() {
-                        ch = self.ansi_translate(ch)?;
-                    }
-                }
-                ch
-            };
+            let token_start = self.pos;
+            if let Err(err) = self.compile_token() {
+                // Resume after the offending token, however much of it was read
+                self.report(err)?;
+                self.pos = self.pos.max(token_start + 1);
+                self.literal_run = None;
+            }
+        }
 
-            let level = self.precedences[op as usize];
-            self.handle_precedence(level)?;
-            self.process_operation(op, ch)?;
+        // End of pattern closes every open precedence level
+        self.handle_precedence(self.precedences[SyntaxOp::End as usize])?;
 
-            self.beginning_context = matches!(op, SyntaxOp::OpenPar | SyntaxOp::Or);
+        // Recursion may target groups defined later, so validate at the end
+        if self.max_recursed_group >= self.next_register {
+            self.report(RegexError::BadBackReference)?;
         }
+        Ok(())
+    }
 
+    /// Finish the bytecode once the whole pattern compiled
+    fn into_regex(mut self) -> Regex {
         // Note: Original C version doesn't check for unmatched parentheses
         // We maintain compatibility by allowing unclosed groups
 
         // Store end opcode
         self.store_opcode(CompiledOp::End);
 
-        Ok(Regex {
-            buffer: self.buffer,
-            translate: self.translate,
-            syntax: self.syntax,
-        })
+        log_event!(
+            debug,
+            "compiled {:?}: {} groups, {} bytecode bytes",
+            self.pattern.iter().collect::<String>(),
+            self.next_register - 1,
+            self.buffer.len()
+        );
+
+        finish(self.buffer, self.translate, self.syntax, self.group_names)
+    }
+
+    /// Fail with `err`, or when collecting diagnostics, record it and go on
+    fn report(&mut self, err: RegexError) -> Result<()> {
+        match self.diagnostics.as_mut() {
+            Some(diagnostics) => {
+                diagnostics.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    /// Compile the operator or literal starting at the current position
+    fn compile_token(&mut self) -> Result<()> {
+        let mut ch = self.next_char()?;
+
+        // Apply translation if available
+        ch = self.translate_char(ch);
+
+        let mut op = self.plain_ops.get(&ch).copied().unwrap_or(SyntaxOp::Normal);
+
+        if op == SyntaxOp::Quote {
+            if self.peek_char().is_none() {
+                return Err(RegexError::DanglingEscape {
+                    position: self.pos - 1,
+                });
+            }
+            ch = self.next_char()?;
+            op = self
+                .quoted_ops
+                .get(&ch)
+                .copied()
+                .unwrap_or(SyntaxOp::Normal);
+
+            if op == SyntaxOp::Normal && self.syntax.ansi_sequences() {
+                ch = self.ansi_translate(ch)?;
+            }
+        }
+
+        if op == SyntaxOp::OpenPar && self.syntax.perl_extensions() && self.peek_char() == Some('?')
+        {
+            match self.parse_group_extension()? {
+                Some(extension) => (op, ch) = extension,
+                // Inline flags emit no code and leave the context as is
+                None => return Ok(()),
+            }
+        }
+
+        let level = self.precedences[op as usize];
+        self.handle_precedence(level)?;
+        self.process_operation(op, ch)?;
+
+        self.beginning_context = matches!(op, SyntaxOp::OpenPar | SyntaxOp::Or);
+        if op != SyntaxOp::Normal {
+            self.literal_run = None;
+        }
+        Ok(())
+    }
+
+    /// Parse the `?...` following an open parenthesis under `PERL_EXTENSIONS`
+    ///
+    /// Returns the operation and character to dispatch in place of the
+    /// parenthesis, or `None` for inline flags, which are applied directly.
+    fn parse_group_extension(&mut self) -> Result<Option<(SyntaxOp, char)>> {
+        self.expect_char('?')?;
+
+        match self.next_char()? {
+            'R' => {
+                self.expect_char(')')?;
+                Ok(Some((SyntaxOp::Recurse, 'R')))
+            }
+            'P' => {
+                self.expect_char('<')?;
+                self.parse_group_name()?;
+                Ok(Some((SyntaxOp::OpenPar, '(')))
+            }
+            '<' => {
+                self.parse_group_name()?;
+                Ok(Some((SyntaxOp::OpenPar, '(')))
+            }
+            '>' => {
+                self.pending_atomic = true;
+                Ok(Some((SyntaxOp::OpenPar, '(')))
+            }
+            'i' | 's' | 'm' | 'x' => {
+                self.pos -= 1;
+                self.parse_inline_flags()?;
+                Ok(None)
+            }
+            other => Err(CompileError::UnknownGroupExtension(other).into()),
+        }
+    }
+
+    /// Parse the `name>` of a named group, keeping it for the next register
+    fn parse_group_name(&mut self) -> Result<()> {
+        let mut name = String::new();
+        loop {
+            match self.next_char()? {
+                '>' => break,
+                ch if ch.is_alphanumeric() || ch == '_' => name.push(ch),
+                ch => return Err(CompileError::InvalidGroupNameChar(ch).into()),
+            }
+        }
+
+        if name.is_empty() || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+            return Err(CompileError::InvalidGroupName(name).into());
+        }
+        if self
+            .group_names
+            .iter()
+            .flatten()
+            .any(|existing| *existing == name)
+        {
+            return Err(CompileError::DuplicateGroupName(name).into());
+        }
+
+        self.pending_group_name = Some(name);
+        Ok(())
+    }
+
+    /// Parse inline flags such as `(?ism)` up to the closing parenthesis
+    ///
+    /// Flags apply to the whole pattern, so they are only accepted before
+    /// anything has been compiled:
+    /// - `i` folds case as `CASE_INSENSITIVE` does
+    /// - `s` lets `.` match newline
+    /// - `x` ignores whitespace and `#` comments as `EXTENDED` does
+    /// - `m` is accepted for compatibility; `^` and `$` always match at
+    ///   line boundaries
+    fn parse_inline_flags(&mut self) -> Result<()> {
+        if !self.buffer.is_empty() {
+            return Err(CompileError::MisplacedInlineFlags.into());
+        }
+
+        loop {
+            match self.next_char()? {
+                'i' => {
+                    if !self.syntax.case_insensitive_ascii() && !self.syntax.case_insensitive() {
+                        self.syntax |= SyntaxFlags::CASE_INSENSITIVE;
+                        let table = self.translate.take().unwrap_or_default();
+                        self.translate = Some(with_case_folding(&table));
+                    }
+                }
+                's' => self.syntax |= SyntaxFlags::ANY_INCLUDES_NEWLINE,
+                'x' => self.syntax |= SyntaxFlags::EXTENDED,
+                'm' => {}
+                ')' => return Ok(()),
+                other => return Err(CompileError::UnknownInlineFlag(other).into()),
+            }
+        }
+    }
+
+    /// Parse the `<n>` group number following `\g`
+    fn parse_group_reference(&mut self) -> Result<u8> {
+        self.expect_char('<')?;
+
+        let mut group: usize = 0;
+        let mut digits = 0;
+        while let Some(ch) = self.peek_char().filter(char::is_ascii_digit) {
+            self.pos += 1;
+            group = group * 10 + (ch as usize - '0' as usize);
+            digits += 1;
+            if group >= RE_NREGS {
+                return Err(RegexError::BadBackReference);
+            }
+        }
+
+        self.expect_char('>')?;
+        if digits == 0 {
+            return Err(RegexError::BadBackReference);
+        }
+
+        Ok(group as u8)
     }
 
     /// Handle operator precedence and level management
@@ -393,10 +884,7 @@ impl Compiler {
         match op {
             SyntaxOp::End => {}
 
-            SyntaxOp::Normal => {
-                self.set_level_start();
-                self.store_opcode_and_char(CompiledOp::Exact, ch);
-            }
+            SyntaxOp::Normal => self.store_literal(ch),
 
             SyntaxOp::AnyChar => {
                 self.set_level_start();
@@ -440,9 +928,14 @@ impl Compiler {
                     return Ok(());
                 }
 
+                self.split_literal_run();
                 if self.current_level_start() == self.buffer.len() {
                     return Ok(()); // Ignore empty patterns for ?
                 }
+                if self.take_lazy_suffix() {
+                    self.store_repeat(0, 1, true);
+                    return Ok(());
+                }
 
                 self.insert_jump(
                     self.current_level_start(),
@@ -462,9 +955,14 @@ impl Compiler {
                     return Ok(());
                 }
 
+                self.split_literal_run();
                 if self.current_level_start() == self.buffer.len() {
                     return Ok(()); // Ignore empty patterns
                 }
+                if self.take_lazy_suffix() {
+                    self.store_repeat(0, REPEAT_UNBOUNDED, true);
+                    return Ok(());
+                }
 
                 self.insert_jump(
                     self.current_level_start(),
@@ -478,6 +976,26 @@ impl Compiler {
                 );
             }
 
+            SyntaxOp::OpenBrace => {
+                if self.beginning_context {
+                    if self.syntax.context_indep_ops() {
+                        return Err(RegexError::BadSpecialChar);
+                    }
+
+                    self.set_level_start();
+                    self.store_opcode_and_char(CompiledOp::Exact, '{');
+                    return Ok(());
+                }
+
+                let (min, max) = self.parse_interval()?;
+                self.split_literal_run();
+                if self.current_level_start() == self.buffer.len() {
+                    return Ok(()); // Ignore empty patterns
+                }
+                let lazy = self.take_lazy_suffix();
+                self.store_repeat(min, max, lazy);
+            }
+
             SyntaxOp::Plus => {
                 if self.beginning_context {
                     if self.syntax.context_indep_ops() {
@@ -489,9 +1007,14 @@ impl Compiler {
                     return Ok(());
                 }
 
+                self.split_literal_run();
                 if self.current_level_start() == self.buffer.len() {
                     return Ok(()); // Ignore empty patterns
                 }
+                if self.take_lazy_suffix() {
+                    self.store_repeat(1, REPEAT_UNBOUNDED, true);
+                    return Ok(());
+                }
 
                 // Follow the original regexpr.c algorithm exactly:
                 // 1. Insert failure_jump at start
@@ -538,11 +1061,17 @@ impl Compiler {
             SyntaxOp::OpenPar => {
                 self.set_level_start();
 
-                if self.next_register < RE_NREGS as u8 {
+                if std::mem::take(&mut self.pending_atomic) {
+                    self.store_opcode(CompiledOp::AtomicStart);
+                    self.open_groups.push(OpenGroup::Atomic);
+                } else if self.next_register < RE_NREGS as u8 {
+                    self.group_names.push(self.pending_group_name.take());
                     self.store_opcode_and_arg(CompiledOp::StartMemory, self.next_register);
-                    self.open_registers[self.num_open_registers] = self.next_register;
-                    self.num_open_registers += 1;
+                    self.open_groups
+                        .push(OpenGroup::Capture(self.next_register));
                     self.next_register += 1;
+                } else {
+                    self.open_groups.push(OpenGroup::Uncaptured);
                 }
 
                 self.paren_depth += 1;
@@ -560,12 +1089,12 @@ impl Compiler {
                     self.current_level = self.precedences[SyntaxOp::OpenPar as usize] as usize;
                     self.paren_depth -= 1;
 
-                    if self.paren_depth < self.num_open_registers {
-                        self.num_open_registers -= 1;
-                        self.store_opcode_and_arg(
-                            CompiledOp::EndMemory,
-                            self.open_registers[self.num_open_registers],
-                        );
+                    match self.open_groups.pop() {
+                        Some(OpenGroup::Capture(reg)) => {
+                            self.store_opcode_and_arg(CompiledOp::EndMemory, reg)
+                        }
+                        Some(OpenGroup::Atomic) => self.store_opcode(CompiledOp::AtomicEnd),
+                        _ => {}
                     }
                 } else {
                     // Treat as normal character when no matching open paren
@@ -579,7 +1108,12 @@ impl Compiler {
                     return Err(RegexError::BadBackReference);
                 }
 
+                // Only groups opened earlier in the pattern can be referenced
                 let reg_num = (ch as u8) - b'0';
+                if reg_num >= self.next_register {
+                    return Err(RegexError::BadBackReference);
+                }
+
                 self.set_level_start();
                 self.store_opcode_and_arg(CompiledOp::MatchMemory, reg_num);
             }
@@ -601,12 +1135,32 @@ impl Compiler {
 
             SyntaxOp::WordChar => {
                 self.set_level_start();
-                self.store_opcode_and_arg(CompiledOp::SyntaxSpec, 1); // Sword = 1
+                self.store_opcode_and_arg(CompiledOp::SyntaxSpec, SyntaxType::Word as u8);
             }
 
             SyntaxOp::NotWordChar => {
                 self.set_level_start();
-                self.store_opcode_and_arg(CompiledOp::NotSyntaxSpec, 1); // Sword = 1
+                self.store_opcode_and_arg(CompiledOp::NotSyntaxSpec, SyntaxType::Word as u8);
+            }
+
+            SyntaxOp::DigitChar => {
+                self.set_level_start();
+                self.store_opcode_and_arg(CompiledOp::SyntaxSpec, SyntaxType::Digit as u8);
+            }
+
+            SyntaxOp::NotDigitChar => {
+                self.set_level_start();
+                self.store_opcode_and_arg(CompiledOp::NotSyntaxSpec, SyntaxType::Digit as u8);
+            }
+
+            SyntaxOp::SpaceChar => {
+                self.set_level_start();
+                self.store_opcode_and_arg(CompiledOp::SyntaxSpec, SyntaxType::Space as u8);
+            }
+
+            SyntaxOp::NotSpaceChar => {
+                self.set_level_start();
+                self.store_opcode_and_arg(CompiledOp::NotSyntaxSpec, SyntaxType::Space as u8);
             }
 
             SyntaxOp::WordBeg => {
@@ -629,6 +1183,24 @@ impl Compiler {
                 self.store_opcode(CompiledOp::EndBuf);
             }
 
+            SyntaxOp::SearchStart => {
+                self.set_level_start();
+                self.store_opcode(CompiledOp::SearchStart);
+            }
+
+            SyntaxOp::Recurse => {
+                // (?R) recurses into the whole pattern, \g<n> into group n
+                let group = if ch == 'g' {
+                    self.parse_group_reference()?
+                } else {
+                    0
+                };
+                self.max_recursed_group = self.max_recursed_group.max(group);
+
+                self.set_level_start();
+                self.store_opcode_and_arg(CompiledOp::Call, group);
+            }
+
             SyntaxOp::ExtendedMemory => {
                 // \vNN for registers 10-99
                 let ch1 = self.next_char()?;
@@ -639,7 +1211,7 @@ impl Compiler {
                 }
 
                 let reg_num = ((ch1 as u8) - b'0') * 10 + ((ch2 as u8) - b'0');
-                if reg_num == 0 || reg_num >= RE_NREGS as u8 {
+                if reg_num == 0 || reg_num >= self.next_register {
                     return Err(RegexError::BadBackReference);
                 }
 
@@ -647,10 +1219,12 @@ impl Compiler {
                 self.store_opcode_and_arg(CompiledOp::MatchMemory, reg_num);
             }
 
+            SyntaxOp::Point => {
+                return Err(CompileError::UnsupportedPoint.into());
+            }
+
             _ => {
-                return Err(RegexError::CompileError(format!(
-                    "Unimplemented operation: {op:?}"
-                )));
+                return Err(CompileError::UnimplementedOperation(op).into());
             }
         }
 
@@ -659,10 +1233,102 @@ impl Compiler {
 
     /// Check if we're in a context where $ can appear
     fn is_eol_context(&self) -> bool {
-        // This is a simplified version - the original has more complex logic
-        self.pos >= self.pattern.len()
-            || self.pattern[self.pos] == '|'
-            || self.pattern[self.pos] == ')'
+        // As regexpr.c:473, the alternation and close group operators are
+        // recognised in the spelling the syntax uses; a newline acting as
+        // alternation under NEWLINE_OR does not count
+        let pos = self.significant_pos();
+        let operator = |ch: char, unquoted: bool| match self.pattern.get(pos..) {
+            Some([first, ..]) if unquoted => *first == ch,
+            Some(['\\', second, ..]) => !unquoted && *second == ch,
+            _ => false,
+        };
+        pos >= self.pattern.len()
+            || operator('|', !self.syntax.needs_backslash_vbar())
+            || operator(')', !self.syntax.needs_backslash_parens())
+    }
+
+    /// Position of the next pattern char that is not extended-mode filler
+    ///
+    /// Under `EXTENDED`, unescaped whitespace and `#` comments running to the
+    /// end of the line are skipped; otherwise this is the current position.
+    /// Sets are parsed separately, so whitespace inside `[...]` is kept.
+    fn significant_pos(&self) -> usize {
+        let mut pos = self.pos;
+        if !self.syntax.extended() {
+            return pos;
+        }
+
+        while let Some(&ch) = self.pattern.get(pos) {
+            if ch == '#' {
+                while self.pattern.get(pos).is_some_and(|&ch| ch != '\n') {
+                    pos += 1;
+                }
+            } else if ch.is_whitespace() {
+                pos += 1;
+            } else {
+                break;
+            }
+        }
+        pos
+    }
+
+    /// Parse the rest of a `[.c.]` or `[=c=]` inside a set, after its `[`
+    ///
+    /// Returns the single char named. Multi-char elements such as `[.ch.]`
+    /// would need locale collation data, so they are rejected.
+    fn parse_collating_element(&mut self) -> Result<char> {
+        let start = self.pos - 1;
+        let delimiter = self.next_char()?;
+        let mut name = String::new();
+        loop {
+            let ch = self.next_char()?;
+            if ch == delimiter && self.peek_char() == Some(']') && !name.is_empty() {
+                self.pos += 1;
+                break;
+            }
+            name.push(ch);
+        }
+
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Ok(ch),
+            _ => Err(CompileError::UnsupportedCollation(
+                self.pattern[start..self.pos].iter().collect(),
+            )
+            .into()),
+        }
+    }
+
+    /// Parse a POSIX class like `[:alpha:]` after its opening `[`
+    ///
+    /// Classes cover ASCII only, as in the C locale.
+    fn parse_char_class(&mut self) -> Result<&'static [(char, char)]> {
+        self.pos += 1;
+        let mut name = String::new();
+        loop {
+            let ch = self.next_char()?;
+            if ch == ':' && self.peek_char() == Some(']') {
+                self.pos += 1;
+                break;
+            }
+            name.push(ch);
+        }
+
+        Ok(match name.as_str() {
+            "alpha" => &[('A', 'Z'), ('a', 'z')],
+            "digit" => &[('0', '9')],
+            "alnum" => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            "upper" => &[('A', 'Z')],
+            "lower" => &[('a', 'z')],
+            "space" => &[('\t', '\r'), (' ', ' ')],
+            "blank" => &[('\t', '\t'), (' ', ' ')],
+            "punct" => &[('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+            "xdigit" => &[('0', '9'), ('A', 'F'), ('a', 'f')],
+            "cntrl" => &[('\0', '\x1f'), ('\x7f', '\x7f')],
+            "print" => &[(' ', '~')],
+            "graph" => &[('!', '~')],
+            _ => return Err(CompileError::UnknownCharClass(name).into()),
+        })
     }
 
     /// Compile character set [abc] or [^abc] or [a-z]
@@ -704,8 +1370,25 @@ impl Compiler {
             }
             first_char = false;
 
+            // POSIX classes add their ranges and cannot end a range
+            if ch == '[' && self.peek_char() == Some(':') {
+                if in_range {
+                    ranges.push(('-', '-'));
+                    in_range = false;
+                }
+                ranges.extend_from_slice(self.parse_char_class()?);
+                prev_char = None;
+                continue;
+            }
+
             let mut actual_char = ch;
 
+            // POSIX collating elements and equivalence classes stand for
+            // their one char, there being no locale to relate others to
+            if ch == '[' && matches!(self.peek_char(), Some('.' | '=')) {
+                actual_char = self.parse_collating_element()?;
+            }
+
             // Handle ANSI escape sequences if enabled
             if ch == '\\' && self.syntax.ansi_sequences() && self.pos < self.pattern.len() {
                 let escaped = self.pattern[self.pos];
@@ -713,14 +1396,18 @@ impl Compiler {
                 actual_char = self.ansi_translate(escaped)?;
             }
 
-            // Apply translation if available
-            if let Some(ref translate) = self.translate {
-                actual_char = translate.get(&actual_char).copied().unwrap_or(actual_char);
-            }
-
             if in_range {
                 // We're completing a range like a-z
                 if let Some(start_char) = prev_char {
+                    // Checked as written, before translation can reorder
+                    // the ends
+                    if start_char > actual_char {
+                        return Err(CompileError::InvalidRange {
+                            start: start_char,
+                            end: actual_char,
+                        }
+                        .into());
+                    }
                     ranges.push((start_char, actual_char));
                 }
                 in_range = false;
@@ -749,6 +1436,27 @@ impl Compiler {
             ranges.push(('-', '-'));
         }
 
+        // Translate, then sort and merge overlapping or adjacent ranges, so
+        // the matcher can stop scanning early
+        let mut ranges: Vec<(char, char)> = ranges
+            .into_iter()
+            .flat_map(|(start, end)| self.translate_range(start, end))
+            .collect();
+        ranges.sort_unstable();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if start as u32 <= *last_end as u32 + 1 => {
+                    *last_end = (*last_end).max(end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        let ranges = merged;
+        if ranges.len() > self.max_set_ranges.min(MAX_SET_RANGES) {
+            return Err(RegexError::TooComplex);
+        }
+
         // Store number of ranges
         self.buffer[num_ranges_pos] = ranges.len() as u8;
 
@@ -774,6 +1482,536 @@ fn hex_char_to_decimal(ch: char) -> Result<u8> {
 
 /// Main compilation entry point
 pub fn compile(pattern: &str, syntax: SyntaxFlags) -> Result<Regex> {
-    let compiler = Compiler::new(pattern, syntax)?;
+    compile_with_max_set_ranges(pattern, syntax, MAX_SET_RANGES)
+}
+
+/// Compile with `table` remapping chars in both the pattern and the text
+///
+/// Under `CASE_INSENSITIVE` the table is combined with case folding, so
+/// chars are folded before and after being looked up in it.
+pub(crate) fn compile_with_translate(
+    pattern: &str,
+    syntax: SyntaxFlags,
+    table: std::collections::HashMap<char, char>,
+) -> Result<Regex> {
+    let mut compiler = Compiler::new(pattern, syntax)?;
+    let table = table.into_iter().collect();
+    compiler.translate = Some(if syntax.case_insensitive() {
+        with_case_folding(&table)
+    } else {
+        table
+    });
+    compiler.compile()
+}
+
+/// Compile, rejecting bracket expressions of more than `max_set_ranges`
+/// ranges (after merging) as `TooComplex`
+pub(crate) fn compile_with_max_set_ranges(
+    pattern: &str,
+    syntax: SyntaxFlags,
+    max_set_ranges: usize,
+) -> Result<Regex> {
+    let mut compiler = Compiler::new(pattern, syntax)?;
+    compiler.max_set_ranges = max_set_ranges;
     compiler.compile()
 }
+
+/// Compile a pattern, carrying on past errors to report every one found
+///
+/// After an error, compilation resumes with the token following the one in
+/// error, so a single mistake may cause follow-on errors, and an error that
+/// consumes the rest of the pattern, like an unterminated set, hides any
+/// after it.
+pub fn compile_verbose(
+    pattern: &str,
+    syntax: SyntaxFlags,
+) -> std::result::Result<Regex, Vec<RegexError>> {
+    let mut compiler = Compiler::new(pattern, syntax).map_err(|err| vec![err])?;
+    compiler.diagnostics = Some(Vec::new());
+
+    let result = compiler.parse();
+    let mut diagnostics = compiler.diagnostics.take().unwrap_or_default();
+    diagnostics.extend(result.err());
+    if diagnostics.is_empty() {
+        Ok(compiler.into_regex())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Build a regex from finished bytecode, analysing it for search shortcuts
+fn finish(
+    buffer: Vec<u8>,
+    translate: Option<std::collections::BTreeMap<char, char>>,
+    syntax: SyntaxFlags,
+    group_names: Vec<Option<String>>,
+) -> Regex {
+    let anchor = analysis::start_anchor(&buffer);
+    let end_anchor = analysis::end_anchor(&buffer);
+    let min_len = analysis::min_match_len(&buffer);
+    let max_len = analysis::max_match_len(&buffer);
+    let both_anchored = matches!(anchor, Anchor::Line | Anchor::Buffer)
+        && matches!(end_anchor, Anchor::Line | Anchor::Buffer);
+    Regex {
+        anchor,
+        end_anchor,
+        min_len,
+        max_len,
+        exact_len: (both_anchored && max_len == Some(min_len)).then_some(min_len),
+        matches_any_prefix: analysis::matches_any_prefix(&buffer),
+        first_set: analysis::first_set(
+            &buffer,
+            syntax.case_insensitive() || syntax.case_insensitive_ascii(),
+            syntax.unicode_word(),
+        ),
+        // The input is translated before being compared with the pattern's
+        // chars and sets, so those are disjoint exactly when they look it
+        update_star_jumps: analysis::update_star_jumps(&buffer),
+        buffer,
+        translate,
+        syntax,
+        group_names: group_names.into(),
+        limits: ExecLimits::default(),
+    }
+}
+
+/// Combine two compiled regexes into one matching either, preferring `first`
+///
+/// The bytecode is laid out as the compiler lays out `first|second`:
+///   failure_jump second; first; jump end; second: second; end
+/// Displacements are relative, so both bodies move unchanged except that the
+/// registers of `second` are renumbered to follow those of `first`.
+pub fn alternate(first: &Regex, second: &Regex) -> Result<Regex> {
+    if first.syntax != second.syntax || first.translate != second.translate {
+        return Err(RegexError::InvalidSyntax(
+            "cannot alternate regexes compiled with different syntax",
+        ));
+    }
+
+    let first_groups = first.group_names.len() - 1;
+    let second_groups = second.group_names.len() - 1;
+    if first_groups + second_groups >= RE_NREGS {
+        return Err(RegexError::TooComplex);
+    }
+
+    let mut group_names = first.group_names.to_vec();
+    for name in second.group_names[1..].iter() {
+        if let Some(name) = name {
+            if group_names.contains(&Some(name.clone())) {
+                return Err(CompileError::DuplicateGroupName(name.clone()).into());
+            }
+        }
+        group_names.push(name.clone());
+    }
+
+    let first_body = strip_end(&first.buffer);
+    let second_body = strip_end(&second.buffer);
+    let skip = i16::try_from(first_body.len() + 3).map_err(|_| RegexError::TooComplex)?;
+    let over = i16::try_from(second_body.len()).map_err(|_| RegexError::TooComplex)?;
+
+    let mut buffer = Vec::with_capacity(first_body.len() + second_body.len() + 7);
+    buffer.push(CompiledOp::FailureJump.to_byte());
+    buffer.extend(skip.to_le_bytes());
+    buffer.extend_from_slice(first_body);
+    buffer.push(CompiledOp::Jump.to_byte());
+    buffer.extend(over.to_le_bytes());
+    let second_start = buffer.len();
+    buffer.extend_from_slice(second_body);
+    buffer.push(CompiledOp::End.to_byte());
+
+    renumber_registers(&mut buffer, second_start, first_groups as u8)?;
+
+    Ok(Regex {
+        limits: first.limits,
+        ..finish(buffer, first.translate.clone(), first.syntax, group_names)
+    })
+}
+
+/// Copy of `regex` with every top-level alternative anchored by `Bol`
+///
+/// Top-level alternation is laid out as a chain of
+///   failure_jump next; branch; jump end; next: ...
+/// ending in the last branch and `End`. Each branch is moved whole, so its
+/// internal displacements stay valid; only the chain is rebuilt around it.
+pub fn anchor_all_alternatives(regex: &Regex) -> Result<Regex> {
+    let body = strip_end(&regex.buffer);
+    let end = body.len();
+
+    // Only bytes that start an instruction may be read as one; an operand
+    // byte can equal an opcode
+    let mut starts = std::collections::BTreeSet::new();
+    let mut pos = 0;
+    while pos < end {
+        starts.insert(pos);
+        pos += instruction_len(body, pos).ok_or(RegexError::ExecutionError)?;
+    }
+
+    let mut branches = Vec::new();
+    let mut pos = 0;
+    loop {
+        let next = match read_jump(body, pos, CompiledOp::FailureJump) {
+            Some(next)
+                if next > pos + 3
+                    && starts.contains(&(next - 3))
+                    && read_jump(body, next - 3, CompiledOp::Jump) == Some(end) =>
+            {
+                next
+            }
+            _ => break,
+        };
+        branches.push(&body[pos + 3..next - 3]);
+        pos = next;
+    }
+    branches.push(&body[pos..]);
+
+    let bol = CompiledOp::Bol.to_byte();
+    let anchored_len = |branch: &[u8]| branch.len() + usize::from(branch.first() != Some(&bol));
+    let mut buffer = Vec::with_capacity(body.len() + 2 * branches.len());
+    let mut exits = Vec::new();
+    for (index, branch) in branches.iter().enumerate() {
+        let last = index == branches.len() - 1;
+        if !last {
+            let skip =
+                i16::try_from(anchored_len(branch) + 3).map_err(|_| RegexError::TooComplex)?;
+            buffer.push(CompiledOp::FailureJump.to_byte());
+            buffer.extend(skip.to_le_bytes());
+        }
+        if branch.first() != Some(&bol) {
+            buffer.push(bol);
+        }
+        buffer.extend_from_slice(branch);
+        if !last {
+            buffer.extend([CompiledOp::Jump.to_byte(), 0, 0]);
+            exits.push(buffer.len());
+        }
+    }
+    for exit in exits {
+        let disp = i16::try_from(buffer.len() - exit).map_err(|_| RegexError::TooComplex)?;
+        buffer[exit - 2..exit].copy_from_slice(&disp.to_le_bytes());
+    }
+    buffer.push(CompiledOp::End.to_byte());
+
+    Ok(Regex {
+        limits: regex.limits,
+        ..finish(
+            buffer,
+            regex.translate.clone(),
+            regex.syntax,
+            regex.group_names.to_vec(),
+        )
+    })
+}
+
+/// Copy of `regex` that must be followed by `literal`
+///
+/// The literal is stored as `ExactString` runs in place of the final `End`.
+/// Jumps that exit the pattern target that `End`, so they now land on the
+/// literal, and no displacement inside the old body changes.
+pub fn append_literal(regex: &Regex, literal: &str) -> Result<Regex> {
+    let body = strip_end(&regex.buffer);
+    let mut pos = 0;
+    while pos < body.len() {
+        if CompiledOp::from_byte(body[pos]) == Some(CompiledOp::Call) && body[pos + 1] == 0 {
+            return Err(CompileError::UnimplementedOperation(SyntaxOp::Recurse).into());
+        }
+        pos += instruction_len(body, pos).ok_or(RegexError::ExecutionError)?;
+    }
+
+    let mut buffer = Vec::with_capacity(body.len() + literal.len() + 3);
+    buffer.extend_from_slice(body);
+    let mut run: Option<usize> = None;
+    for ch in literal.chars() {
+        let mut bytes = [0; 4];
+        let utf8_bytes = regex.translate_char(ch).encode_utf8(&mut bytes).as_bytes();
+        match run {
+            Some(start) if buffer[start + 1] as usize + utf8_bytes.len() <= u8::MAX as usize => {
+                buffer[start] = CompiledOp::ExactString.to_byte();
+                buffer[start + 1] += utf8_bytes.len() as u8;
+            }
+            _ => {
+                run = Some(buffer.len());
+                buffer.push(CompiledOp::Exact.to_byte());
+                buffer.push(utf8_bytes.len() as u8);
+            }
+        }
+        buffer.extend_from_slice(utf8_bytes);
+    }
+    buffer.push(CompiledOp::End.to_byte());
+
+    Ok(Regex {
+        limits: regex.limits,
+        ..finish(
+            buffer,
+            regex.translate.clone(),
+            regex.syntax,
+            regex.group_names.to_vec(),
+        )
+    })
+}
+
+/// Target of the `op` jump at `pos`, if that is what is there
+fn read_jump(buffer: &[u8], pos: usize, op: CompiledOp) -> Option<usize> {
+    if CompiledOp::from_byte(*buffer.get(pos)?)? != op {
+        return None;
+    }
+    let disp = i16::from_le_bytes([*buffer.get(pos + 1)?, *buffer.get(pos + 2)?]);
+    usize::try_from(pos as isize + 3 + disp as isize).ok()
+}
+
+/// Bytecode without its final `End`
+fn strip_end(buffer: &[u8]) -> &[u8] {
+    buffer
+        .strip_suffix(&[CompiledOp::End.to_byte()])
+        .unwrap_or(buffer)
+}
+
+/// Shift the register operands of instructions from `from` onwards by `shift`
+///
+/// Whole pattern recursion cannot be spliced, as it would recurse into the
+/// combined pattern instead.
+fn renumber_registers(buffer: &mut [u8], from: usize, shift: u8) -> Result<()> {
+    let mut pos = 0;
+    while let Some(op) = CompiledOp::from_byte(buffer[pos]) {
+        match op {
+            CompiledOp::End => break,
+            CompiledOp::Call if buffer[pos + 1] == 0 => {
+                return Err(CompileError::UnimplementedOperation(SyntaxOp::Recurse).into());
+            }
+            CompiledOp::StartMemory
+            | CompiledOp::EndMemory
+            | CompiledOp::MatchMemory
+            | CompiledOp::Call
+                if pos >= from =>
+            {
+                buffer[pos + 1] += shift
+            }
+            _ => {}
+        }
+        pos += instruction_len(buffer, pos).ok_or(RegexError::ExecutionError)?;
+    }
+    Ok(())
+}
+
+/// Leading bytes of a serialized regex
+const BYTES_MAGIC: &[u8; 6] = b"tature";
+/// Version of the serialized layout, bumped whenever it or the bytecode changes
+const BYTES_VERSION: u8 = 1;
+
+/// Serialize the compiled form of `regex`
+///
+/// Layout, with integers little-endian:
+///   magic; version; syntax u32; translate count u32, then (from, to) u32 pairs;
+///   group count u32, then per group a 0 or 1 byte and, for 1, a u32 length
+///   and the UTF-8 name; bytecode length u32 and the bytecode
+pub fn to_bytes(regex: &Regex) -> Vec<u8> {
+    let mut out = Vec::with_capacity(regex.buffer.len() + 32);
+    let push_u32 = |out: &mut Vec<u8>, value: usize| out.extend((value as u32).to_le_bytes());
+
+    out.extend_from_slice(BYTES_MAGIC);
+    out.push(BYTES_VERSION);
+    push_u32(&mut out, regex.syntax.bits() as usize);
+
+    let translate = regex.translate.as_ref();
+    push_u32(&mut out, translate.map_or(0, |table| table.len()));
+    for (&from, &to) in translate.into_iter().flatten() {
+        push_u32(&mut out, from as usize);
+        push_u32(&mut out, to as usize);
+    }
+
+    push_u32(&mut out, regex.group_names.len());
+    for name in regex.group_names.iter() {
+        match name {
+            Some(name) => {
+                out.push(1);
+                push_u32(&mut out, name.len());
+                out.extend_from_slice(name.as_bytes());
+            }
+            None => out.push(0),
+        }
+    }
+
+    push_u32(&mut out, regex.buffer.len());
+    out.extend_from_slice(&regex.buffer);
+    out
+}
+
+/// Rebuild a regex serialized by `to_bytes`, checking the bytecode is sound
+pub fn from_bytes(bytes: &[u8]) -> Result<Regex> {
+    let mut reader = ByteReader { bytes, pos: 0 };
+
+    if reader.take(BYTES_MAGIC.len())? != BYTES_MAGIC {
+        return Err(RegexError::InvalidBytecode("missing header"));
+    }
+    if reader.take(1)?[0] != BYTES_VERSION {
+        return Err(RegexError::InvalidBytecode("unsupported format version"));
+    }
+
+    let syntax = SyntaxFlags::from_bits_truncate(reader.u32()?);
+    syntax.validate().map_err(RegexError::InvalidSyntax)?;
+
+    let translate_len = reader.u32()? as usize;
+    let translate = if translate_len == 0 {
+        None
+    } else {
+        let mut table = std::collections::BTreeMap::new();
+        for _ in 0..translate_len {
+            table.insert(reader.char()?, reader.char()?);
+        }
+        Some(table)
+    };
+
+    let group_count = reader.u32()? as usize;
+    if group_count == 0 || group_count > RE_NREGS {
+        return Err(RegexError::InvalidBytecode("bad group count"));
+    }
+    let mut group_names = Vec::with_capacity(group_count);
+    for _ in 0..group_count {
+        group_names.push(match reader.take(1)?[0] {
+            0 => None,
+            1 => {
+                let len = reader.u32()? as usize;
+                let name = std::str::from_utf8(reader.take(len)?)
+                    .map_err(|_| RegexError::InvalidBytecode("group name is not UTF-8"))?;
+                Some(name.to_string())
+            }
+            _ => return Err(RegexError::InvalidBytecode("bad group name tag")),
+        });
+    }
+
+    let buffer_len = reader.u32()? as usize;
+    let buffer = reader.take(buffer_len)?.to_vec();
+    if reader.pos != bytes.len() {
+        return Err(RegexError::InvalidBytecode("trailing bytes"));
+    }
+    validate_bytecode(&buffer, group_count)?;
+
+    Ok(finish(buffer, translate, syntax, group_names))
+}
+
+/// Cursor over serialized regex bytes
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let taken = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or(RegexError::InvalidBytecode("truncated"))?;
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn char(&mut self) -> Result<char> {
+        char::from_u32(self.u32()?).ok_or(RegexError::InvalidBytecode("bad char"))
+    }
+}
+
+/// Check that `buffer` decodes into whole instructions ending in `End`, with
+/// valid chars, registers below `group_count` and jumps landing on
+/// instructions, so the matcher can run it without further checks
+///
+/// Jumps must also have the shapes the compiler gives them, as the matcher
+/// relies on those to terminate: every jump goes forward except a
+/// `star_jump`, which goes back to the `failure_jump` leaving its loop just
+/// after it, and a `repeat_end`. A cycle of jumps that skips both would
+/// spin forever without consuming input.
+fn validate_bytecode(buffer: &[u8], group_count: usize) -> Result<()> {
+    let invalid = RegexError::InvalidBytecode;
+    let one_char = |bytes: &[u8]| {
+        std::str::from_utf8(bytes)
+            .ok()
+            .is_some_and(|text| text.chars().count() == 1)
+    };
+
+    let mut starts = std::collections::BTreeSet::new();
+    let mut targets = Vec::new();
+    let mut pos = 0;
+    let mut last = None;
+    while pos < buffer.len() {
+        let op = CompiledOp::from_byte(buffer[pos]).ok_or(invalid("unknown opcode"))?;
+        let len = instruction_len(buffer, pos).ok_or(invalid("truncated instruction"))?;
+        starts.insert(pos);
+        let next = pos + len;
+
+        match op {
+            CompiledOp::Exact if !one_char(&buffer[pos + 2..next]) => {
+                return Err(invalid("bad literal"));
+            }
+            CompiledOp::ExactString if std::str::from_utf8(&buffer[pos + 2..next]).is_err() => {
+                return Err(invalid("bad literal"));
+            }
+            CompiledOp::Set => {
+                let mut bound = pos + 3;
+                while bound < next {
+                    let bound_len = buffer[bound] as usize;
+                    if !one_char(&buffer[bound + 1..bound + 1 + bound_len]) {
+                        return Err(invalid("bad set"));
+                    }
+                    bound += 1 + bound_len;
+                }
+            }
+            CompiledOp::StartMemory
+            | CompiledOp::EndMemory
+            | CompiledOp::MatchMemory
+            | CompiledOp::Call
+                if buffer[pos + 1] as usize >= group_count =>
+            {
+                return Err(invalid("bad register"));
+            }
+            CompiledOp::Jump | CompiledOp::FailureJump | CompiledOp::DummyFailureJump => {
+                let target = analysis::jump_target(buffer, pos).ok_or(invalid("bad jump"))?;
+                if target <= pos {
+                    return Err(invalid("backward jump"));
+                }
+                targets.push(target);
+            }
+            CompiledOp::StarJump => {
+                let target = analysis::jump_target(buffer, pos).ok_or(invalid("bad jump"))?;
+                let loops = target < pos
+                    && CompiledOp::from_byte(buffer[target]) == Some(CompiledOp::FailureJump)
+                    && analysis::jump_target(buffer, target) == Some(next);
+                if !loops {
+                    return Err(invalid("bad loop"));
+                }
+                targets.push(target);
+            }
+            // Only the matcher's own conversion of star_jump produces one
+            CompiledOp::UpdateFailureJump => return Err(invalid("bad jump")),
+            CompiledOp::RepeatStart => {
+                let exit = next + u16::from_le_bytes([buffer[pos + 6], buffer[pos + 7]]) as usize;
+                targets.push(exit);
+            }
+            CompiledOp::RepeatEnd => {
+                let disp = i16::from_le_bytes([buffer[pos + 1], buffer[pos + 2]]);
+                let start = usize::try_from(next as isize + disp as isize)
+                    .map_err(|_| invalid("bad jump"))?;
+                if CompiledOp::from_byte(*buffer.get(start).unwrap_or(&0))
+                    != Some(CompiledOp::RepeatStart)
+                {
+                    return Err(invalid("bad jump"));
+                }
+                targets.push(start);
+            }
+            _ => {}
+        }
+
+        last = Some(op);
+        pos = next;
+    }
+
+    if last != Some(CompiledOp::End) {
+        return Err(invalid("missing end"));
+    }
+    if targets.iter().any(|target| !starts.contains(target)) {
+        return Err(invalid("jump into the middle of an instruction"));
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Readable listings of compiled bytecode
+//!
+//! Each instruction is printed on its own line as its position, the
+//! regexpr.c style mnemonic and its operands, with jump displacements
+//! resolved to absolute positions:
+//!
+//! ```text
+//!    0: exact "a"
+//!    3: failure_jump -> 12
+//!    6: exact "b"
+//!    9: star_jump -> 3
+//!   12: exact "c"
+//!   15: end
+//! ```
+
+use std::fmt::Write;
+
+use crate::opcodes::{instruction_len, CompiledOp, REPEAT_UNBOUNDED};
+
+/// List the instructions of `buffer`, one per line
+///
+/// Listing stops with an `invalid` line at a byte that does not start a
+/// whole instruction.
+pub fn disassemble(buffer: &[u8]) -> String {
+    let mut out = String::new();
+    let mut pos = 0;
+    while pos < buffer.len() {
+        let (Some(op), Some(len)) = (
+            CompiledOp::from_byte(buffer[pos]),
+            instruction_len(buffer, pos),
+        ) else {
+            let _ = writeln!(out, "{pos:4}: invalid {:#04x}", buffer[pos]);
+            break;
+        };
+        let _ = write!(out, "{pos:4}: {}", op.mnemonic());
+        write_operands(&mut out, op, &buffer[pos..pos + len], pos);
+        out.push('\n');
+        pos += len;
+    }
+    out
+}
+
+/// Append the operands of the instruction `bytes`, found at `pos`
+fn write_operands(out: &mut String, op: CompiledOp, bytes: &[u8], pos: usize) {
+    let word = |at: usize| i16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let target = |disp: i16| pos as isize + bytes.len() as isize + disp as isize;
+
+    let _ = match op {
+        CompiledOp::Exact | CompiledOp::ExactString => {
+            write!(out, " {:?}", String::from_utf8_lossy(&bytes[2..]))
+        }
+        CompiledOp::Set => {
+            out.push_str(if bytes[1] != 0 { " [^" } else { " [" });
+            let mut at = 3;
+            let mut bound = || {
+                let len = bytes[at] as usize;
+                let text = String::from_utf8_lossy(&bytes[at + 1..at + 1 + len]).into_owned();
+                at += 1 + len;
+                text
+            };
+            for _ in 0..bytes[2] {
+                let (lo, hi) = (bound(), bound());
+                out.push_str(&lo);
+                if hi != lo {
+                    out.push('-');
+                    out.push_str(&hi);
+                }
+            }
+            write!(out, "]")
+        }
+        CompiledOp::StartMemory
+        | CompiledOp::EndMemory
+        | CompiledOp::MatchMemory
+        | CompiledOp::SyntaxSpec
+        | CompiledOp::NotSyntaxSpec
+        | CompiledOp::Call => write!(out, " {}", bytes[1]),
+        CompiledOp::Jump
+        | CompiledOp::StarJump
+        | CompiledOp::FailureJump
+        | CompiledOp::UpdateFailureJump
+        | CompiledOp::DummyFailureJump
+        | CompiledOp::RepeatEnd => write!(out, " -> {}", target(word(1))),
+        CompiledOp::RepeatStart => {
+            let min = u16::from_le_bytes([bytes[1], bytes[2]]);
+            let max = u16::from_le_bytes([bytes[3], bytes[4]]);
+            let exit = pos + bytes.len() + u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+            let _ = write!(out, " {min},");
+            if max != REPEAT_UNBOUNDED {
+                let _ = write!(out, "{max}");
+            }
+            if bytes[5] != 0 {
+                out.push_str(" lazy");
+            }
+            write!(out, " -> {exit}")
+        }
+        _ => Ok(()),
+    };
+}
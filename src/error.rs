@@ -2,6 +2,8 @@
 
 use std::fmt;
 
+use crate::opcodes::SyntaxOp;
+
 /// Result type for regex operations
 pub type Result<T> = std::result::Result<T, RegexError>;
 
@@ -9,7 +11,7 @@ pub type Result<T> = std::result::Result<T, RegexError>;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RegexError {
     /// Pattern compilation failed
-    CompileError(String),
+    CompileError(CompileError),
     /// Pattern is too complex (too many nested operators)
     TooComplex,
     /// Unmatched parentheses
@@ -22,6 +24,11 @@ pub enum RegexError {
     BadSpecialChar,
     /// Pattern ends prematurely
     PrematureEnd,
+    /// Pattern ends with a backslash that has nothing to quote
+    DanglingEscape {
+        /// Char position of the trailing backslash
+        position: usize,
+    },
     /// Out of memory during compilation
     OutOfMemory,
     /// Execution timed out (exceeded tick limit)
@@ -30,6 +37,15 @@ pub enum RegexError {
     ExecutionError,
     /// Invalid UTF-8 in input
     InvalidUtf8,
+    /// Input is longer than `ExecLimits::max_input_chars`
+    InputTooLong {
+        /// The configured maximum length in chars
+        limit: usize,
+    },
+    /// Unsupported combination of syntax flags
+    InvalidSyntax(&'static str),
+    /// Serialized regex that is corrupt or from an incompatible version
+    InvalidBytecode(&'static str),
 }
 
 impl fmt::Display for RegexError {
@@ -42,12 +58,122 @@ impl fmt::Display for RegexError {
             RegexError::BadBackReference => write!(f, "Bad match register number"),
             RegexError::BadSpecialChar => write!(f, "Badly placed special character"),
             RegexError::PrematureEnd => write!(f, "Regular expression ends prematurely"),
+            RegexError::DanglingEscape { position } => {
+                write!(f, "Trailing backslash at position {position}")
+            }
             RegexError::OutOfMemory => write!(f, "Out of memory"),
             RegexError::Timeout => write!(f, "Regex execution timed out"),
             RegexError::ExecutionError => write!(f, "Regex execution error"),
             RegexError::InvalidUtf8 => write!(f, "Invalid UTF-8 in input"),
+            RegexError::InputTooLong { limit } => {
+                write!(f, "Input exceeds the maximum of {limit} chars")
+            }
+            RegexError::InvalidSyntax(reason) => write!(f, "Invalid syntax flags: {reason}"),
+            RegexError::InvalidBytecode(reason) => write!(f, "Invalid compiled regex: {reason}"),
         }
     }
 }
 
 impl std::error::Error for RegexError {}
+
+/// Compiler failures that have no dedicated regexpr.c error code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// A specific character was required at a position
+    ExpectedChar {
+        /// The character that was required
+        expected: char,
+        /// Char position where it was required
+        position: usize,
+    },
+    /// `(?` followed by an unsupported character
+    UnknownGroupExtension(char),
+    /// A character not allowed in a group name
+    InvalidGroupNameChar(char),
+    /// A group name that is empty or starts with a digit
+    InvalidGroupName(String),
+    /// A group name used more than once
+    DuplicateGroupName(String),
+    /// Inline flags after the start of the pattern
+    MisplacedInlineFlags,
+    /// An unsupported inline flag letter
+    UnknownInlineFlag(char),
+    /// A `\c` escape not followed by a printable ASCII char
+    InvalidControlEscape(char),
+    /// A malformed or out of range `{n,m}` interval
+    InvalidInterval {
+        /// Char position where the interval could not be parsed
+        position: usize,
+    },
+    /// Emacs `\=`, which matches at point; there is no point to match at
+    UnsupportedPoint,
+    /// A `[.name.]` or `[=name=]` naming more than one char, which needs
+    /// locale collation data (the bracketed form is kept)
+    UnsupportedCollation(String),
+    /// A `[:name:]` class that POSIX does not define (the name is kept)
+    UnknownCharClass(String),
+    /// A bracket range whose end comes before its start, such as `z-a`
+    InvalidRange {
+        /// The first char of the range as written
+        start: char,
+        /// The last char of the range as written
+        end: char,
+    },
+    /// An operation the compiler has no translation for
+    UnimplementedOperation(SyntaxOp),
+    /// A null pattern pointer passed through the C interface
+    NullPattern,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::ExpectedChar { expected, position } => {
+                write!(f, "Expected '{expected}' at position {position}")
+            }
+            CompileError::UnknownGroupExtension(ch) => {
+                write!(f, "Unknown group extension '(?{ch}'")
+            }
+            CompileError::InvalidGroupNameChar(ch) => {
+                write!(f, "Invalid character '{ch}' in group name")
+            }
+            CompileError::InvalidGroupName(name) => write!(f, "Invalid group name '{name}'"),
+            CompileError::DuplicateGroupName(name) => write!(f, "Duplicate group name '{name}'"),
+            CompileError::MisplacedInlineFlags => {
+                write!(f, "Inline flags must appear at the start of the pattern")
+            }
+            CompileError::UnknownInlineFlag(ch) => write!(f, "Unknown inline flag '{ch}'"),
+            CompileError::InvalidControlEscape(ch) => {
+                write!(f, "Invalid control escape \\c{}", ch.escape_debug())
+            }
+            CompileError::InvalidInterval { position } => {
+                write!(f, "Invalid interval at position {position}")
+            }
+            CompileError::UnsupportedPoint => {
+                write!(f, "\\= (match at point) is not supported")
+            }
+            CompileError::UnsupportedCollation(element) => {
+                write!(f, "Collating element {element} is not supported")
+            }
+            CompileError::UnknownCharClass(name) => {
+                write!(f, "Unknown character class [:{name}:]")
+            }
+            CompileError::InvalidRange { start, end } => write!(
+                f,
+                "Invalid range {}-{} in bracket expression",
+                start.escape_debug(),
+                end.escape_debug()
+            ),
+            CompileError::UnimplementedOperation(op) => {
+                write!(f, "Unimplemented operation: {op:?}")
+            }
+            CompileError::NullPattern => write!(f, "null pattern"),
+        }
+    }
+}
+
+impl From<CompileError> for RegexError {
+    fn from(error: CompileError) -> Self {
+        RegexError::CompileError(error)
+    }
+}
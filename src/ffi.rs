@@ -0,0 +1,175 @@
+//! C-compatible interface mirroring re_compile_pattern/re_search
+//!
+//! Enabled with the `ffi` feature. Text offsets crossing this boundary are
+//! byte offsets into the UTF-8 input, as a C caller would expect, rather than
+//! the char positions used by the Rust API. Build with
+//! `--crate-type staticlib` or `cdylib` to link from C.
+
+use std::ffi::{c_char, c_int, CStr};
+
+use crate::{byte_offsets, CompileError, Regex, RegexError, Result, SyntaxFlags, RE_NREGS};
+
+/// Match registers, laid out like `struct re_registers` in regexpr.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TatureRegisters {
+    /// Start byte offset of each group, or -1 if it did not participate
+    pub start: [c_int; RE_NREGS],
+    /// End byte offset of each group, or -1 if it did not participate
+    pub end: [c_int; RE_NREGS],
+}
+
+/// Error code reported through `tature_compile`'s `err` argument on success
+pub const TATURE_OK: c_int = 0;
+
+/// Map an error to the code reported through `tature_compile`'s `err` argument
+fn error_code(error: &RegexError) -> c_int {
+    match error {
+        RegexError::CompileError(_) => 1,
+        RegexError::TooComplex => 2,
+        RegexError::UnmatchedParenthesis => 3,
+        RegexError::BadHexEscape => 4,
+        RegexError::BadBackReference => 5,
+        RegexError::BadSpecialChar => 6,
+        RegexError::PrematureEnd => 7,
+        RegexError::OutOfMemory => 8,
+        RegexError::Timeout => 9,
+        RegexError::ExecutionError => 10,
+        RegexError::InvalidUtf8 => 11,
+        RegexError::InvalidSyntax(_) => 12,
+        RegexError::DanglingEscape { .. } => 13,
+        RegexError::InputTooLong { .. } => 14,
+        RegexError::InvalidBytecode(_) => 15,
+    }
+}
+
+/// Compile a NUL-terminated UTF-8 pattern with the given syntax bits
+///
+/// Returns a heap-allocated regex to be released with [`tature_free`], or
+/// null on failure. When `err` is non-null it receives [`TATURE_OK`] or a
+/// nonzero error code.
+///
+/// # Safety
+///
+/// `pattern` must be null or point to a NUL-terminated string, and `err`
+/// must be null or point to writable memory for one `c_int`.
+#[no_mangle]
+pub unsafe extern "C" fn tature_compile(
+    pattern: *const c_char,
+    flags: u32,
+    err: *mut c_int,
+) -> *mut Regex {
+    let result = if pattern.is_null() {
+        Err(CompileError::NullPattern.into())
+    } else {
+        Regex::try_from_utf8_bytes(
+            CStr::from_ptr(pattern).to_bytes(),
+            SyntaxFlags::from_bits_truncate(flags),
+        )
+    };
+
+    into_raw(result, err)
+}
+
+/// Compile a pattern of `len` Latin-1 bytes with the given syntax bits
+///
+/// Like `re_compile_pattern`, the pattern is not NUL-terminated and may
+/// contain NULs. Returns and reports errors as [`tature_compile`] does.
+///
+/// # Safety
+///
+/// `pattern` must be null or point to at least `len` readable bytes, and
+/// `err` must be null or point to writable memory for one `c_int`.
+#[no_mangle]
+pub unsafe extern "C" fn tature_compile_len(
+    pattern: *const c_char,
+    len: c_int,
+    flags: u32,
+    err: *mut c_int,
+) -> *mut Regex {
+    let result = if pattern.is_null() || len < 0 {
+        Err(CompileError::NullPattern.into())
+    } else {
+        let bytes = std::slice::from_raw_parts(pattern as *const u8, len as usize);
+        Regex::from_bytes_with_len(bytes, SyntaxFlags::from_bits_truncate(flags))
+    };
+
+    into_raw(result, err)
+}
+
+/// Hand a compiled regex to C, storing the error code through `err` if non-null
+unsafe fn into_raw(result: Result<Regex>, err: *mut c_int) -> *mut Regex {
+    let (regex, code) = match result {
+        Ok(regex) => (Box::into_raw(Box::new(regex)), TATURE_OK),
+        Err(error) => (std::ptr::null_mut(), error_code(&error)),
+    };
+
+    if !err.is_null() {
+        *err = code;
+    }
+    regex
+}
+
+/// Search `len` bytes of UTF-8 `text` for the first match
+///
+/// Returns the byte offset where the match starts, -1 if there is no match,
+/// or -2 on invalid arguments or input that is not UTF-8. When `regs` is
+/// non-null it is filled in with byte offsets for every group.
+///
+/// # Safety
+///
+/// `re` must be null or a pointer returned by [`tature_compile`] that has not
+/// been freed, `text` must point to at least `len` readable bytes, and `regs`
+/// must be null or point to a writable [`TatureRegisters`].
+#[no_mangle]
+pub unsafe extern "C" fn tature_search(
+    re: *const Regex,
+    text: *const c_char,
+    len: c_int,
+    regs: *mut TatureRegisters,
+) -> c_int {
+    if re.is_null() || text.is_null() || len < 0 {
+        return -2;
+    }
+
+    let bytes = std::slice::from_raw_parts(text as *const u8, len as usize);
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return -2;
+    };
+
+    let regex = &*re;
+    let captures = regex.captures(text);
+
+    if !regs.is_null() {
+        let regs = &mut *regs;
+        regs.start = [-1; RE_NREGS];
+        regs.end = [-1; RE_NREGS];
+
+        if let Some(ref captures) = captures {
+            let offsets = byte_offsets(text);
+            for index in 0..RE_NREGS {
+                if let Some((start, end)) = captures.get(index) {
+                    regs.start[index] = offsets[start] as c_int;
+                    regs.end[index] = offsets[end] as c_int;
+                }
+            }
+        }
+    }
+
+    captures
+        .and_then(|captures| captures.get(0))
+        .map_or(-1, |(start, _)| byte_offsets(text)[start] as c_int)
+}
+
+/// Release a regex returned by [`tature_compile`]
+///
+/// # Safety
+///
+/// `re` must be null or a pointer returned by [`tature_compile`] that has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn tature_free(re: *mut Regex) {
+    if !re.is_null() {
+        drop(Box::from_raw(re));
+    }
+}
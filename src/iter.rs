@@ -0,0 +1,374 @@
+//! Iterators over successive matches in a text
+//!
+//! Each iterator decodes its text into chars once, when created, and
+//! searches that for every step rather than decoding the text again. Text
+//! over `ExecLimits::max_input_chars` is never decoded and yields nothing.
+
+use std::marker::PhantomData;
+
+use crate::{
+    byte_offsets,
+    matcher::{self, Window},
+    Captures, Regex,
+};
+
+/// The text as chars with the position to search from, or an empty text
+/// already searched past when the text is too long for `regex`
+fn decode(regex: &Regex, text: &str) -> (Vec<char>, usize) {
+    match matcher::check_input_len(text, regex.limits) {
+        Ok(()) => (text.chars().collect(), 0),
+        Err(_) => (Vec::new(), 1),
+    }
+}
+
+/// Iterator over non-overlapping matches, yielding `(start, end)` char positions
+///
+/// Created by [`Regex::find_iter`].
+#[derive(Debug)]
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    /// The text as chars
+    chars: Vec<char>,
+    /// Char position where the next search begins
+    pos: usize,
+    text: PhantomData<&'t str>,
+}
+
+impl<'r, 't> Matches<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str) -> Self {
+        let (chars, pos) = decode(regex, text);
+        Self {
+            regex,
+            chars,
+            pos,
+            text: PhantomData,
+        }
+    }
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos > self.chars.len() {
+            return None;
+        }
+
+        let (start, end) = self
+            .regex
+            .search_chars(&self.chars, self.pos, self.regex.limits, Window::default())?
+            .get(0)?;
+
+        // Empty matches advance by one char so iteration terminates
+        self.pos = if end == start { end + 1 } else { end };
+        Some((start, end))
+    }
+}
+
+/// Iterator over non-overlapping matches with touching matches merged,
+/// yielding `(start, end)` char positions
+///
+/// A match starting exactly where the previous one ended extends it, so
+/// `[0-9]` over `"12 3"` yields `(0, 2)` and `(3, 4)`. Created by
+/// [`Regex::find_iter_merged`].
+#[derive(Debug)]
+pub struct MergedMatches<'r, 't> {
+    matches: std::iter::Peekable<Matches<'r, 't>>,
+}
+
+impl<'r, 't> MergedMatches<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str) -> Self {
+        Self {
+            matches: Matches::new(regex, text).peekable(),
+        }
+    }
+}
+
+impl Iterator for MergedMatches<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (start, mut end) = self.matches.next()?;
+        while let Some((_, next_end)) = self.matches.next_if(|&(next_start, _)| next_start == end) {
+            end = next_end;
+        }
+        Some((start, end))
+    }
+}
+
+/// Iterator over possibly overlapping matches, yielding `(start, end)` char positions
+///
+/// After each match the next search begins `step` chars past the previous
+/// match start. Created by [`Regex::find_iter_overlapping`] and
+/// [`Regex::find_iter_overlapping_step`].
+#[derive(Debug)]
+pub struct OverlappingMatches<'r, 't> {
+    regex: &'r Regex,
+    /// The text as chars
+    chars: Vec<char>,
+    /// Char position where the next search begins
+    pos: usize,
+    text: PhantomData<&'t str>,
+    /// Distance between successive search starts
+    step: usize,
+}
+
+impl<'r, 't> OverlappingMatches<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str, step: usize) -> Self {
+        let (chars, pos) = decode(regex, text);
+        Self {
+            regex,
+            chars,
+            pos,
+            text: PhantomData,
+            step: step.max(1),
+        }
+    }
+}
+
+impl Iterator for OverlappingMatches<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos > self.chars.len() {
+            return None;
+        }
+
+        let (start, end) = self
+            .regex
+            .search_chars(&self.chars, self.pos, self.regex.limits, Window::default())?
+            .get(0)?;
+
+        self.pos = start + self.step;
+        Some((start, end))
+    }
+}
+
+/// Iterator over the captures of non-overlapping matches
+///
+/// Created by [`Regex::captures_iter`], [`Regex::captures_iter_limited`],
+/// which stops after a fixed number of matches, and
+/// [`Regex::captures_iter_contiguous`], which stops at the first gap.
+#[derive(Debug)]
+pub struct CaptureMatches<'r, 't> {
+    regex: &'r Regex,
+    /// The text as chars
+    chars: Vec<char>,
+    /// Char position where the next search begins
+    pos: usize,
+    text: PhantomData<&'t str>,
+    /// Matches still allowed, or `None` when uncapped
+    remaining: Option<usize>,
+    /// Whether each match must start where the previous one ended
+    contiguous: bool,
+}
+
+impl<'r, 't> CaptureMatches<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str, max_matches: Option<usize>) -> Self {
+        let (chars, pos) = decode(regex, text);
+        Self {
+            regex,
+            chars,
+            pos,
+            text: PhantomData,
+            remaining: max_matches,
+            contiguous: false,
+        }
+    }
+
+    /// Only match where the previous match ended, as if the pattern began with `\G`
+    pub(crate) fn contiguous(self) -> Self {
+        Self {
+            contiguous: true,
+            ..self
+        }
+    }
+}
+
+impl Iterator for CaptureMatches<'_, '_> {
+    type Item = Captures;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos > self.chars.len() || self.remaining == Some(0) {
+            return None;
+        }
+
+        let captures = if self.contiguous {
+            self.regex
+                .try_search_chars(
+                    &self.chars,
+                    self.pos,
+                    Some(0),
+                    self.regex.limits,
+                    Window::default(),
+                )
+                .ok()
+                .flatten()
+        } else {
+            self.regex
+                .search_chars(&self.chars, self.pos, self.regex.limits, Window::default())
+        }?;
+        let (start, end) = captures.get(0)?;
+
+        // Empty matches advance by one char so iteration terminates; when
+        // contiguous, that would leave a gap, so iteration ends instead
+        self.pos = if end != start {
+            end
+        } else if self.contiguous {
+            self.chars.len() + 1
+        } else {
+            end + 1
+        };
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        Some(captures)
+    }
+}
+
+/// A piece of text produced by [`Regex::split_captures`]
+#[derive(Debug)]
+pub enum SplitItem<'t> {
+    /// Text between two separators, or before the first or after the last
+    Field(&'t str),
+    /// The captures of a separator match, boxed as they are large
+    Separator(Box<Captures>),
+}
+
+/// Iterator splitting a text on matches, yielding fields and separators in turn
+///
+/// Like Python's `re.split` with a capturing pattern, every separator sits
+/// between two fields, so the first and last items are always fields.
+/// Created by [`Regex::split_captures`].
+#[derive(Debug)]
+pub struct SplitCaptures<'r, 't> {
+    matches: CaptureMatches<'r, 't>,
+    text: &'t str,
+    /// Byte offset of every char index in the text
+    offsets: Vec<usize>,
+    /// Char position where the next field begins, or `None` once exhausted
+    field_start: Option<usize>,
+    /// Separator to yield after the current field
+    pending: Option<Box<Captures>>,
+}
+
+impl<'r, 't> SplitCaptures<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str) -> Self {
+        let matches = CaptureMatches::new(regex, text, None);
+        // Too long to search, so not even the whole text is a field
+        let exhausted = matches.pos > matches.chars.len();
+        Self {
+            matches,
+            text,
+            offsets: if exhausted {
+                Vec::new()
+            } else {
+                byte_offsets(text)
+            },
+            field_start: (!exhausted).then_some(0),
+            pending: None,
+        }
+    }
+}
+
+impl<'t> Iterator for SplitCaptures<'_, 't> {
+    type Item = SplitItem<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(separator) = self.pending.take() {
+            return Some(SplitItem::Separator(separator));
+        }
+
+        let start = self.field_start?;
+        let end = match self.matches.next() {
+            Some(separator) => {
+                let (sep_start, sep_end) = separator.get(0)?;
+                self.field_start = Some(sep_end);
+                self.pending = Some(Box::new(separator));
+                sep_start
+            }
+            None => {
+                self.field_start = None;
+                self.offsets.len() - 1
+            }
+        };
+        Some(SplitItem::Field(
+            &self.text[self.offsets[start]..self.offsets[end]],
+        ))
+    }
+}
+
+/// Iterator pairing each match's captures with the unmatched text before it
+///
+/// A final item carries the text after the last match, with no captures, so
+/// the gaps and matches together cover the whole text. Created by
+/// [`Regex::captures_iter_with_gaps`].
+#[derive(Debug)]
+pub struct CapturesWithGaps<'r, 't> {
+    inner: SplitCaptures<'r, 't>,
+}
+
+impl<'r, 't> CapturesWithGaps<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str) -> Self {
+        Self {
+            inner: SplitCaptures::new(regex, text),
+        }
+    }
+}
+
+impl<'t> Iterator for CapturesWithGaps<'_, 't> {
+    type Item = (&'t str, Option<Captures>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Split items alternate, starting and ending with a field
+        let SplitItem::Field(gap) = self.inner.next()? else {
+            return None;
+        };
+        match self.inner.next() {
+            Some(SplitItem::Separator(captures)) => Some((gap, Some(*captures))),
+            _ => Some((gap, None)),
+        }
+    }
+}
+
+/// Iterator over the fields of a text split on matches
+///
+/// Created by [`Regex::split`], which yields every field, and
+/// [`Regex::split_trimmed`], which drops an empty first or last field.
+#[derive(Debug)]
+pub struct Split<'r, 't> {
+    inner: SplitCaptures<'r, 't>,
+    /// Whether to drop an empty field at either end
+    trim: bool,
+    /// Whether no field has been yielded or dropped yet
+    first: bool,
+}
+
+impl<'r, 't> Split<'r, 't> {
+    pub(crate) fn new(regex: &'r Regex, text: &'t str, trim: bool) -> Self {
+        Self {
+            inner: SplitCaptures::new(regex, text),
+            trim,
+            first: true,
+        }
+    }
+}
+
+impl<'t> Iterator for Split<'_, 't> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let field = match self.inner.next()? {
+                SplitItem::Field(field) => field,
+                SplitItem::Separator(_) => continue,
+            };
+            let first = std::mem::replace(&mut self.first, false);
+            let last = self.inner.field_start.is_none();
+            if self.trim && field.is_empty() && (first || last) {
+                continue;
+            }
+            return Some(field);
+        }
+    }
+}
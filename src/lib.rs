@@ -5,16 +5,46 @@
 //! UTF-8 support and tick-based execution limits.
 //!
 //! Both patterns and input text are processed as UTF-8 characters.
+//!
+//! With the `log` feature enabled, compilation and matching emit `log`
+//! events under the `tature` target.
+
+/// Emit a `log` event when the `log` feature is enabled
+///
+/// Expands to nothing otherwise, so the arguments are never evaluated.
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "log")]
+        log::$level!(target: "tature", $($arg)+);
+    };
+}
 
+mod analysis;
 pub mod compiler;
+pub mod disasm;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod iter;
 pub mod matcher;
 pub mod opcodes;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod set;
 pub mod syntax;
 
-pub use error::{RegexError, Result};
+pub use analysis::FirstSet;
+pub use error::{CompileError, RegexError, Result};
+pub use iter::{
+    CaptureMatches, CapturesWithGaps, Matches, MergedMatches, OverlappingMatches, Split,
+    SplitCaptures, SplitItem,
+};
+pub use matcher::{Boundaries, MatchContext, SearchStats};
+pub use set::RegexSet;
 pub use syntax::SyntaxFlags;
 
+use std::{borrow::Cow, sync::Arc};
+
 /// Maximum number of capture groups supported
 pub const RE_NREGS: usize = 100;
 
@@ -24,26 +54,129 @@ pub struct Regex {
     /// Compiled bytecode buffer
     pub buffer: Vec<u8>,
     /// Translation table for case-insensitive matching
-    translate: Option<std::collections::HashMap<char, char>>,
+    ///
+    /// Kept ordered so anything derived from it (debug output, serialized
+    /// forms) is identical for identical inputs.
+    translate: Option<std::collections::BTreeMap<char, char>>,
     /// Syntax flags used during compilation
-    #[allow(dead_code)]
     syntax: SyntaxFlags,
+    /// Where the pattern is anchored, restricting the positions search tries
+    anchor: Anchor,
+    /// Where a match must end, if the pattern finishes with `$` or `` \' ``
+    end_anchor: Anchor,
+    /// The fewest chars a match can consume
+    min_len: usize,
+    /// The most chars a match can consume, when bounded
+    max_len: Option<usize>,
+    /// The length of every match, when the pattern is anchored at both ends
+    /// and fixed-length, so text of any other length is rejected up front
+    exact_len: Option<usize>,
+    /// Whether the pattern is `.*`, matching at every position
+    matches_any_prefix: bool,
+    /// The chars a match can start with
+    first_set: FirstSet,
+    /// Positions of the `star_jump`s that update their loop's failure point
+    /// rather than jumping back to push another
+    update_star_jumps: std::collections::BTreeSet<usize>,
+    /// Name of each group by number (index 0 is the whole match), shared
+    /// with the captures of patterns that name any group
+    group_names: Arc<[Option<String>]>,
+    /// Limits used by the methods that take no `ExecLimits`
+    limits: ExecLimits,
+}
+
+/// Start anchoring of a compiled pattern (the `anchor` field of regexpr.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    /// Pattern may match anywhere
+    None,
+    /// Pattern is anchored by `^` or `$` to a line boundary
+    Line,
+    /// Pattern is anchored by `` \` `` or `` \' `` to the buffer boundary
+    Buffer,
+    /// Pattern is anchored by `\G` to where the search starts
+    Search,
 }
 
 /// Match result with capture group positions
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Captures {
     /// Start and end positions of capture groups
     groups: [(Option<usize>, Option<usize>); RE_NREGS],
+    /// Number of groups in the pattern, including group 0
+    group_count: usize,
+    /// Group names by number, when the pattern names any group
+    names: Option<Arc<[Option<String>]>>,
+}
+
+/// A match in the shape LambdaMOO's `match()` and `rmatch()` report it
+///
+/// Positions are 1-based and `end` is inclusive, so an empty match at the
+/// start of the subject is `start == 1, end == 0`. `replacements` holds
+/// groups 1 to 9 (the `%1`..`%9` registers), with `(0, -1)` for groups that
+/// did not participate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MooMatch {
+    /// 1-based position of the first matched char
+    pub start: i64,
+    /// 1-based position of the last matched char
+    pub end: i64,
+    /// `(start, end)` of groups 1 to 9, in the same convention
+    pub replacements: [(i64, i64); 9],
 }
 
+impl From<&Captures> for MooMatch {
+    fn from(captures: &Captures) -> Self {
+        let span = |group| {
+            captures
+                .get(group)
+                .map_or((0, -1), |(start, end)| (start as i64 + 1, end as i64))
+        };
+        let (start, end) = span(0);
+        Self {
+            start,
+            end,
+            replacements: std::array::from_fn(|index| span(index + 1)),
+        }
+    }
+}
+
+/// A group's name (if any) paired with its span in a match
+pub type NamedSpan = (Option<String>, Option<(usize, usize)>);
+
 /// Configuration for regex execution limits
+///
+/// One exception: `is_match` and `find` answer a pattern that is just `.*`
+/// (see [`Regex::matches_everything`]) in a single pass over the text without
+/// running the matcher, so of these limits only `max_input_chars` applies to
+/// them there. Every other method, and every other pattern, honours them all.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct ExecLimits {
     /// Maximum number of execution steps before timeout
     pub max_ticks: Option<usize>,
     /// Maximum failure stack depth
     pub max_failures: usize,
+    /// Maximum input length in chars, checked before the input is decoded
+    pub max_input_chars: Option<usize>,
+    /// Skip backtracking into `(position, text position)` states that have
+    /// already failed
+    ///
+    /// Bounds the work of patterns such as `(a+a+)+b` by the pattern size
+    /// times the text length, at the cost of a set of visited states. Has no
+    /// effect on patterns with back-references, recursion, counted
+    /// repetitions, atomic groups or loops whose body can match nothing,
+    /// such as `(a*)*`; matches and captures never change.
+    pub memoize: bool,
+    /// Maximum wall-clock time for one search or match call
+    ///
+    /// The clock is read only every few thousand ticks, so a call may run
+    /// slightly past it before failing with `RegexError::Timeout`.
+    pub timeout: Option<std::time::Duration>,
 }
 
 impl Default for ExecLimits {
@@ -51,10 +184,120 @@ impl Default for ExecLimits {
         Self {
             max_ticks: None,
             max_failures: 100_000,
+            max_input_chars: None,
+            memoize: false,
+            timeout: None,
         }
     }
 }
 
+/// Builds a `Regex` from a pattern and options set one at a time
+///
+/// Limits set here are kept on the `Regex` and used by every method that
+/// takes no `ExecLimits` of its own, such as `is_match` and `find_iter`.
+///
+/// ```
+/// use tature::RegexBuilder;
+///
+/// let regex = RegexBuilder::new("hello")
+///     .case_insensitive(true)
+///     .max_ticks(10_000)
+///     .build()
+///     .unwrap();
+/// assert!(regex.is_match("Hello, world"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexBuilder {
+    pattern: String,
+    syntax: SyntaxFlags,
+    case_insensitive: Option<bool>,
+    limits: ExecLimits,
+    max_set_ranges: usize,
+}
+
+impl RegexBuilder {
+    /// Start building a regex for `pattern` with default syntax and limits
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            syntax: SyntaxFlags::default(),
+            case_insensitive: None,
+            limits: ExecLimits::default(),
+            max_set_ranges: compiler::MAX_SET_RANGES,
+        }
+    }
+
+    /// Set the syntax flags, replacing any set before
+    pub fn syntax(&mut self, syntax: SyntaxFlags) -> &mut Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Turn `CASE_INSENSITIVE` on or off, whatever the syntax flags say
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = Some(yes);
+        self
+    }
+
+    /// Limit the execution steps of each match attempt
+    pub fn max_ticks(&mut self, max_ticks: usize) -> &mut Self {
+        self.limits.max_ticks = Some(max_ticks);
+        self
+    }
+
+    /// Limit the depth of the failure stack
+    pub fn max_failures(&mut self, max_failures: usize) -> &mut Self {
+        self.limits.max_failures = max_failures;
+        self
+    }
+
+    /// Skip backtracking into states that have already failed, see
+    /// `ExecLimits::memoize`
+    pub fn memoize(&mut self, yes: bool) -> &mut Self {
+        self.limits.memoize = yes;
+        self
+    }
+
+    /// Limit the wall-clock time of each search, see `ExecLimits::timeout`
+    pub fn timeout(&mut self, timeout: std::time::Duration) -> &mut Self {
+        self.limits.timeout = Some(timeout);
+        self
+    }
+
+    /// Limit the length of the input, in chars
+    ///
+    /// Longer input is never searched: searches find no match, iterators
+    /// yield nothing and replacements return the text unchanged.
+    pub fn max_input_chars(&mut self, max_input_chars: usize) -> &mut Self {
+        self.limits.max_input_chars = Some(max_input_chars);
+        self
+    }
+
+    /// Limit the ranges a bracket expression may hold once overlapping and
+    /// adjacent ranges are merged
+    ///
+    /// Larger sets fail to compile with `TooComplex`. The limit cannot be
+    /// raised past `compiler::MAX_SET_RANGES`.
+    pub fn max_set_ranges(&mut self, max_set_ranges: usize) -> &mut Self {
+        self.max_set_ranges = max_set_ranges;
+        self
+    }
+
+    /// Compile the pattern
+    pub fn build(&self) -> Result<Regex> {
+        let syntax = match self.case_insensitive {
+            Some(true) => self.syntax | SyntaxFlags::CASE_INSENSITIVE,
+            Some(false) => self.syntax - SyntaxFlags::CASE_INSENSITIVE,
+            None => self.syntax,
+        };
+        syntax.validate().map_err(RegexError::InvalidSyntax)?;
+        let mut regex =
+            compiler::compile_with_max_set_ranges(&self.pattern, syntax, self.max_set_ranges)?;
+        regex.limits = self.limits;
+        Ok(regex)
+    }
+}
+
 impl Regex {
     /// Compile a regex pattern with default syntax
     pub fn new(pattern: &str) -> Result<Self> {
@@ -63,41 +306,358 @@ impl Regex {
 
     /// Compile a regex pattern with specific syntax flags
     pub fn with_syntax(pattern: &str, syntax: SyntaxFlags) -> Result<Self> {
+        syntax.validate().map_err(RegexError::InvalidSyntax)?;
+
         // Pattern is already validated as UTF-8 by Rust's &str type
         let regex = compiler::compile(pattern, syntax)?;
         Ok(regex)
     }
 
+    /// Compile a pattern that treats chars as equivalent according to `table`
+    ///
+    /// Each char of the pattern and of the text is replaced by its entry in
+    /// `table`, if it has one, before being compared, e.g. mapping `é` to `e`
+    /// lets `café` match `cafe` and the other way round.
+    pub fn with_translate(
+        pattern: &str,
+        syntax: SyntaxFlags,
+        table: std::collections::HashMap<char, char>,
+    ) -> Result<Self> {
+        syntax.validate().map_err(RegexError::InvalidSyntax)?;
+        compiler::compile_with_translate(pattern, syntax, table)
+    }
+
+    /// Serialize the compiled regex, to be restored with `from_bytes`
+    ///
+    /// The bytes hold the bytecode, syntax flags, translate table and group
+    /// names behind a versioned header. Limits set with `RegexBuilder` are
+    /// not included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        compiler::to_bytes(self)
+    }
+
+    /// Restore a regex serialized by `to_bytes` without recompiling it
+    ///
+    /// Fails with `RegexError::InvalidBytecode` for bytes from another format
+    /// version or whose bytecode does not decode into valid instructions,
+    /// including jumps the compiler never emits, such as one to itself, that
+    /// could keep the matcher looping without consuming input.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        compiler::from_bytes(bytes)
+    }
+
+    /// Compile a pattern, reporting every error found instead of only the first
+    ///
+    /// For editors and linters. After an error, compilation resumes with the
+    /// next token, so one mistake can cause follow-on errors, and an error
+    /// that consumes the rest of the pattern, like an unterminated set,
+    /// hides any after it.
+    pub fn compile_verbose(
+        pattern: &str,
+        syntax: SyntaxFlags,
+    ) -> std::result::Result<Self, Vec<RegexError>> {
+        syntax
+            .validate()
+            .map_err(|err| vec![RegexError::InvalidSyntax(err)])?;
+        compiler::compile_verbose(pattern, syntax)
+    }
+
+    /// Compile a pattern given as bytes, decoding each byte as a Latin-1 char
+    ///
+    /// Mirrors `re_compile_pattern`, which takes a pointer and a length: the
+    /// whole slice is the pattern, embedded NULs included, and it need not be
+    /// valid UTF-8.
+    pub fn from_bytes_with_len(bytes: &[u8], syntax: SyntaxFlags) -> Result<Self> {
+        let pattern: String = bytes.iter().map(|&byte| byte as char).collect();
+        Self::with_syntax(&pattern, syntax)
+    }
+
+    /// Compile a pattern given as bytes that must be valid UTF-8
+    ///
+    /// The strict counterpart of [`Regex::from_bytes_with_len`]: bytes that
+    /// are not UTF-8 are rejected with `RegexError::InvalidUtf8` rather than
+    /// read as Latin-1.
+    pub fn try_from_utf8_bytes(bytes: &[u8], syntax: SyntaxFlags) -> Result<Self> {
+        let pattern = std::str::from_utf8(bytes).map_err(|_| RegexError::InvalidUtf8)?;
+        Self::with_syntax(pattern, syntax)
+    }
+
     /// Test if the pattern matches anywhere in the text
     pub fn is_match(&self, text: &str) -> bool {
-        self.is_match_with_limits(text, ExecLimits::default())
+        self.is_match_with_limits(text, self.limits)
     }
 
     /// Test if pattern matches with execution limits
     pub fn is_match_with_limits(&self, text: &str, limits: ExecLimits) -> bool {
+        if self.matches_any_prefix {
+            return matcher::check_input_len(text, limits).is_ok();
+        }
         self.find_with_limits(text, limits).is_some()
     }
 
     /// Find the first match in the text
     pub fn find(&self, text: &str) -> Option<(usize, usize)> {
-        self.find_with_limits(text, ExecLimits::default())
+        self.find_with_limits(text, self.limits)
     }
 
     /// Find first match with execution limits
     pub fn find_with_limits(&self, text: &str, limits: ExecLimits) -> Option<(usize, usize)> {
+        if self.matches_any_prefix {
+            // `.*` matches at 0, up to the first newline `.` cannot cross
+            matcher::check_input_len(text, limits).ok()?;
+            let mut chars = text.chars();
+            let end = if self.syntax.any_includes_newline() {
+                chars.count()
+            } else {
+                chars
+                    .position(|ch| ch == '\n')
+                    .unwrap_or(text.chars().count())
+            };
+            return Some((0, end));
+        }
         self.captures_with_limits(text, limits)
             .and_then(|captures| captures.get(0))
     }
 
     /// Get all capture groups from the first match
     pub fn captures(&self, text: &str) -> Option<Captures> {
-        self.captures_with_limits(text, ExecLimits::default())
+        self.captures_with_limits(text, self.limits)
     }
 
     /// Get captures with execution limits
     pub fn captures_with_limits(&self, text: &str, limits: ExecLimits) -> Option<Captures> {
-        // Text is already validated as UTF-8 by Rust's &str type
-        matcher::search(self, text, 0, text.chars().count() as i32, limits)
+        self.search_captures(text, 0, limits)
+    }
+
+    /// Get captures with execution limits, reporting why matching stopped
+    ///
+    /// Unlike `captures_with_limits`, a search cut short by a limit is an
+    /// error rather than no match: `RegexError::InputTooLong` for input over
+    /// `max_input_chars`, `RegexError::Timeout` past `max_ticks`, and
+    /// `RegexError::ExecutionError` past `max_failures`. `Ok(None)` always
+    /// means the pattern cannot match.
+    pub fn try_captures_with_limits(
+        &self,
+        text: &str,
+        limits: ExecLimits,
+    ) -> Result<Option<Captures>> {
+        matcher::check_input_len(text, limits)?;
+        let chars: Vec<char> = text.chars().collect();
+        self.try_search_chars(&chars, 0, None, limits, matcher::Window::default())
+    }
+
+    /// Test if the pattern matches, with the word chars and line separator
+    /// of `context` in place of the defaults
+    pub fn is_match_with_context(
+        &self,
+        text: &str,
+        limits: ExecLimits,
+        context: MatchContext,
+    ) -> bool {
+        self.captures_with_context(text, limits, context).is_some()
+    }
+
+    /// Find the first match, with the word chars and line separator of
+    /// `context` in place of the defaults
+    pub fn find_with_context(
+        &self,
+        text: &str,
+        limits: ExecLimits,
+        context: MatchContext,
+    ) -> Option<(usize, usize)> {
+        self.captures_with_context(text, limits, context)
+            .and_then(|captures| captures.get(0))
+    }
+
+    /// Get captures for the first match, with the word chars and line
+    /// separator of `context` in place of the defaults
+    ///
+    /// The compiled pattern is shared, so one `Regex` can serve inputs with
+    /// different tokenization rules.
+    pub fn captures_with_context(
+        &self,
+        text: &str,
+        limits: ExecLimits,
+        context: MatchContext,
+    ) -> Option<Captures> {
+        let window = matcher::Window {
+            context: Some(context),
+            ..Default::default()
+        };
+        self.search_captures_in_window(text, 0, limits, window)
+    }
+
+    /// Get captures for the first match, along with how much work it took
+    ///
+    /// The stats count every start position tried, including the work of a
+    /// search cut short by a limit, which gives `None` as usual. Useful for
+    /// tuning `ExecLimits` and logging expensive patterns.
+    pub fn captures_with_report(
+        &self,
+        text: &str,
+        limits: ExecLimits,
+    ) -> (Option<Captures>, SearchStats) {
+        let mut stats = SearchStats::default();
+        let captures = matcher::check_input_len(text, limits).ok().and_then(|()| {
+            let chars: Vec<char> = text.chars().collect();
+            self.try_search_chars_with_stats(
+                &chars,
+                0,
+                None,
+                limits,
+                matcher::Window::default(),
+                &mut stats,
+            )
+            .ok()
+            .flatten()
+        });
+        (captures, stats)
+    }
+
+    /// Get captures for the first match starting at or after char position `start`
+    ///
+    /// With `window`, only starts up to `window` chars past `start` are tried,
+    /// bounding the work of each call when the caller knows how close the
+    /// next match must be; the match itself may extend further. This is the
+    /// `range` argument of `re_search`.
+    pub fn captures_at(&self, text: &str, start: usize, window: Option<usize>) -> Option<Captures> {
+        matcher::check_input_len(text, self.limits).ok()?;
+        let chars: Vec<char> = text.chars().collect();
+        self.try_search_chars(
+            &chars,
+            start,
+            window,
+            self.limits,
+            matcher::Window::default(),
+        )
+        .ok()
+        .flatten()
+    }
+
+    /// Find the first match starting at or after char position `start`
+    ///
+    /// For resuming a scan by hand where `find_iter` does not fit. The text
+    /// before `start` still counts for `^` and word boundaries. `None` when
+    /// `start` is past the end of `text`; at the end, only a pattern that
+    /// can match the empty string matches.
+    pub fn find_from(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        if start > text.chars().count() {
+            return None;
+        }
+        self.captures_at(text, start, None)
+            .and_then(|captures| captures.get(0))
+    }
+
+    /// Find the match that starts furthest right in the text
+    ///
+    /// The span of [`Regex::rcaptures`]: starts are tried from the end of the
+    /// text back towards 0 and the first that matches wins, so this is the
+    /// match with the largest start. It ends where a match from that
+    /// start ends for [`Regex::find`], so `a+` on "aaa" gives `(2, 3)`, not
+    /// the whole run. A pattern that can match the empty string matches at
+    /// the end of the text.
+    pub fn rfind(&self, text: &str) -> Option<(usize, usize)> {
+        self.rcaptures(text).and_then(|captures| captures.get(0))
+    }
+
+    /// Whether the pattern matches starting exactly at char position `pos`
+    ///
+    /// No other start is tried, so a tokenizer can test the token it expects
+    /// where it is. False when `pos` is past the end of `text`.
+    pub fn is_match_at(&self, text: &str, pos: usize) -> bool {
+        self.captures_anchored_at(text, pos).is_some()
+    }
+
+    /// Get captures for a match starting exactly at char position `pos`
+    ///
+    /// Like `captures_at` with a window of 0, but runs the matcher at `pos`
+    /// directly. `None` when `pos` is past the end of `text`.
+    pub fn captures_anchored_at(&self, text: &str, pos: usize) -> Option<Captures> {
+        if pos > text.chars().count() {
+            return None;
+        }
+        matcher::match_at(self, text, pos, self.limits)
+            .ok()
+            .flatten()
+    }
+
+    /// Whether `text` is a prefix of some string the pattern matches whole
+    ///
+    /// True when matching from the start of `text` either consumes all of it
+    /// or runs out of text part way through the pattern, so `abcdef` accepts
+    /// "abc" but not "abx". For input validation as the user types. Limits
+    /// being exceeded counts as false.
+    pub fn could_match_prefix(&self, text: &str) -> bool {
+        matcher::could_match_prefix(self, text, self.limits).unwrap_or(false)
+    }
+
+    /// Whether the pattern matches all of `text`, from the first char to
+    /// the last
+    ///
+    /// Unlike checking where [`Regex::find`] ends, shorter alternatives give
+    /// way to ones reaching the end, so `a|ab` fully matches "ab". No `^` or
+    /// `$` is needed in the pattern. Limits being exceeded counts as false.
+    pub fn is_full_match(&self, text: &str) -> bool {
+        matcher::full_match(self, text, self.limits)
+            .ok()
+            .flatten()
+            .is_some()
+    }
+
+    /// Get captures for the first match along with the alternation branches
+    /// it took
+    ///
+    /// The path holds, in the order they were entered, the 0-based index of
+    /// the branch chosen at each alternation the match passed through, so
+    /// `(a|b(c|d))` matching "bd" gives `[1, 1]`. Alternations left by
+    /// backtracking are not included. Meant for debugging patterns: finding
+    /// the path costs an extra pass over the bytecode and the match.
+    pub fn captures_with_branch_path(&self, text: &str) -> Option<(Captures, Vec<usize>)> {
+        let (start, _) = self.captures(text)?.get(0)?;
+        let chars: Vec<char> = text.chars().collect();
+        matcher::match_at_with_branch_path(self, &chars, start, self.limits)
+            .ok()
+            .flatten()
+    }
+
+    /// Get captures for the first match lying entirely within `range`
+    ///
+    /// `range` is in chars. Only positions inside it are tried and matches may
+    /// not extend past its end, but `^`, `$` and word boundaries still see
+    /// the surrounding text, so the range edges are not treated as line or
+    /// word edges.
+    pub fn captures_in_range(&self, text: &str, range: std::ops::Range<usize>) -> Option<Captures> {
+        let window = matcher::Window {
+            end: Some(range.end),
+            ..Default::default()
+        };
+        self.search_captures_in_window(text, range.start, self.limits, window)
+    }
+
+    /// Get captures for the first match in a chunk of a larger input
+    ///
+    /// `boundaries` says whether the chunk's edges are real edges of the
+    /// input; see [`Boundaries`].
+    pub fn captures_with_boundaries(&self, text: &str, boundaries: Boundaries) -> Option<Captures> {
+        let window = matcher::Window {
+            boundaries,
+            ..Default::default()
+        };
+        self.search_captures_in_window(text, 0, self.limits, window)
+    }
+
+    /// Get all capture groups from the rightmost match
+    ///
+    /// Searches backward from the end of the text, like `re_search` with a
+    /// negative range, then matches forward from the start it finds.
+    pub fn rcaptures(&self, text: &str) -> Option<Captures> {
+        self.rcaptures_with_limits(text, self.limits)
+    }
+
+    /// Get captures from the rightmost match with execution limits
+    pub fn rcaptures_with_limits(&self, text: &str, limits: ExecLimits) -> Option<Captures> {
+        let text_len = text.chars().count();
+        matcher::search(self, text, text_len, -(text_len as i32), limits)
             .ok()
             .filter(|&pos| pos >= 0)
             .and_then(|pos| {
@@ -106,6 +666,563 @@ impl Regex {
                     .flatten()
             })
     }
+
+    /// The leftmost match, reported the way LambdaMOO's `match()` does
+    ///
+    /// The server runs this same engine, so the match chosen is the usual
+    /// first-alternative one; only the register conventions differ. Compile
+    /// with `SyntaxFlags::MOO` (plus `CASE_INSENSITIVE` unless the MOO code
+    /// passes `case-matters`) after translating `%` escapes to `\`.
+    pub fn moo_match(&self, text: &str) -> Option<MooMatch> {
+        self.captures(text).as_ref().map(MooMatch::from)
+    }
+
+    /// The rightmost match, reported the way LambdaMOO's `rmatch()` does
+    pub fn moo_rmatch(&self, text: &str) -> Option<MooMatch> {
+        self.rcaptures(text).as_ref().map(MooMatch::from)
+    }
+
+    /// Get the first match as `(name, span)` pairs for every group
+    ///
+    /// Entry `n` describes group `n`, with entry 0 for the whole match. The
+    /// name is `None` for unnamed groups and the span is `None` for groups
+    /// that did not participate.
+    pub fn captures_named(&self, text: &str) -> Option<Vec<NamedSpan>> {
+        let captures = self.captures(text)?;
+        Some(
+            self.group_names
+                .iter()
+                .enumerate()
+                .map(|(group, name)| (name.clone(), captures.get(group)))
+                .collect(),
+        )
+    }
+
+    /// Number of capturing groups, not counting the whole match
+    pub fn group_count(&self) -> usize {
+        self.group_names.len() - 1
+    }
+
+    /// Get the name of a group, if it was given one with `(?P<name>...)`
+    pub fn group_name(&self, group: usize) -> Option<&str> {
+        self.group_names.get(group)?.as_deref()
+    }
+
+    /// The name of every group by number, `None` for unnamed groups
+    ///
+    /// The first item is group 0, the whole match, which never has a name.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.group_names.iter().map(Option::as_deref)
+    }
+
+    /// Whether any group was given a name
+    pub(crate) fn has_named_groups(&self) -> bool {
+        self.group_names.iter().any(Option::is_some)
+    }
+
+    /// Group names to hand to captures, when there are any
+    pub(crate) fn shared_group_names(&self) -> Option<Arc<[Option<String>]>> {
+        self.has_named_groups()
+            .then(|| Arc::clone(&self.group_names))
+    }
+
+    /// Write capture spans for the first match into a caller-provided slice
+    ///
+    /// Follows the `re_registers` convention from regexpr.h: each entry is a
+    /// `(start, end)` char position pair, with `(-1, -1)` for groups that did
+    /// not participate. Entries beyond the available groups are also set to
+    /// `(-1, -1)`. Returns whether a match occurred; on no match every entry
+    /// is `(-1, -1)`.
+    pub fn captures_into_slice(&self, text: &str, out: &mut [(i32, i32)]) -> bool {
+        let captures = self.captures(text);
+
+        for (index, slot) in out.iter_mut().enumerate() {
+            *slot = captures
+                .as_ref()
+                .and_then(|captures| captures.get(index))
+                .map_or((-1, -1), |(start, end)| (start as i32, end as i32));
+        }
+
+        captures.is_some()
+    }
+
+    /// A readable listing of the compiled bytecode, one instruction per line
+    ///
+    /// See [`disasm`] for the format. Meant for debugging; the listing is
+    /// not stable across versions.
+    pub fn disassemble(&self) -> String {
+        disasm::disassemble(&self.buffer)
+    }
+
+    /// Whether the pattern can only match at the start of a line or buffer
+    ///
+    /// True when the compiled pattern begins with `^`, `` \` `` or `\G`;
+    /// searches then only try the positions where that anchor can succeed.
+    pub fn is_anchored_start(&self) -> bool {
+        self.anchor != Anchor::None
+    }
+
+    /// Whether the pattern can only match up to the end of a line or buffer
+    ///
+    /// True when the compiled pattern ends with `$` or `` \' `` on every
+    /// path; searches then skip starts too far from a line or buffer end for
+    /// the longest possible match to reach it.
+    pub fn is_anchored_end(&self) -> bool {
+        self.end_anchor != Anchor::None
+    }
+
+    /// The fewest and, when bounded, the most chars a match can span
+    ///
+    /// Computed from the bytecode. Unbounded loops, back-references and
+    /// recursion leave the maximum `None`; back-references and recursion
+    /// count as empty towards the minimum.
+    pub fn match_len_bounds(&self) -> (usize, Option<usize>) {
+        (self.min_len, self.max_len)
+    }
+
+    /// Approximate heap memory held by this regex, in bytes
+    ///
+    /// Counts the capacity of the bytecode buffer and other owned buffers.
+    /// Tree-based tables are counted per entry with an allowance for node
+    /// overhead, so treat the figure as an estimate for cache budgeting,
+    /// not an exact allocation count.
+    pub fn heap_size(&self) -> usize {
+        /// Per-entry estimate of B-tree node bookkeeping
+        const TREE_ENTRY_OVERHEAD: usize = std::mem::size_of::<usize>();
+
+        let translate = self.translate.as_ref().map_or(0, |table| {
+            table.len() * (2 * std::mem::size_of::<char>() + TREE_ENTRY_OVERHEAD)
+        });
+        let group_names = self.group_names.len() * std::mem::size_of::<Option<String>>()
+            + self
+                .group_names
+                .iter()
+                .flatten()
+                .map(String::capacity)
+                .sum::<usize>();
+        let first_set = match &self.first_set {
+            FirstSet::Any => 0,
+            FirstSet::Chars(chars) => {
+                chars.len() * (std::mem::size_of::<char>() + TREE_ENTRY_OVERHEAD)
+            }
+            FirstSet::Ranges(ranges) => ranges.capacity() * std::mem::size_of::<(char, char)>(),
+        };
+
+        self.buffer.capacity() + translate + group_names + first_set
+    }
+
+    /// The limits used by the methods that take no `ExecLimits`
+    ///
+    /// `ExecLimits::default()` unless set through `RegexBuilder`.
+    pub fn limits(&self) -> ExecLimits {
+        self.limits
+    }
+
+    /// True when the pattern is `.*`, which matches at every position
+    ///
+    /// `is_match` and `find` answer such patterns directly instead of
+    /// running the matcher, checking only `ExecLimits::max_input_chars`.
+    pub fn matches_everything(&self) -> bool {
+        self.matches_any_prefix
+    }
+
+    /// The set of chars a match can start with
+    ///
+    /// Computed from the leading instructions of every alternative. Patterns
+    /// that can match the empty string, or start with `.`, report
+    /// [`FirstSet::Any`]. Searches skip positions whose char is not in it.
+    pub fn first_chars(&self) -> &FirstSet {
+        &self.first_set
+    }
+
+    pub(crate) fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub(crate) fn end_anchor(&self) -> Anchor {
+        self.end_anchor
+    }
+
+    pub(crate) fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    pub(crate) fn exact_len(&self) -> Option<usize> {
+        self.exact_len
+    }
+
+    pub(crate) fn star_jump_updates(&self, pos: usize) -> bool {
+        self.update_star_jumps.contains(&pos)
+    }
+
+    /// Combine this regex with `other` into one matching either pattern
+    ///
+    /// The compiled bytecode is spliced under a new alternation without
+    /// re-parsing, this pattern taking precedence. Groups of `other` are
+    /// renumbered to follow this pattern's, keeping their names. Both regexes
+    /// must share syntax flags and translation table.
+    pub fn alternate(&self, other: &Regex) -> Result<Regex> {
+        compiler::alternate(self, other)
+    }
+
+    /// Copy of this regex that must be followed by the literal text `literal`
+    ///
+    /// The literal is appended to the compiled bytecode without re-parsing,
+    /// and follows the whole pattern: `foo\|bar` plus `"!"` behaves like
+    /// `\(foo\|bar\)!` without adding a group. Patterns that recurse into
+    /// themselves with `(?R)` cannot be extended.
+    pub fn append_literal(&self, literal: &str) -> Result<Regex> {
+        compiler::append_literal(self, literal)
+    }
+
+    /// Copy of this regex with each top-level alternative anchored at line starts
+    ///
+    /// `^` binds tighter than `|`, so in `^foo|bar` only `foo` is anchored.
+    /// The copy behaves as if every alternative began with `^`, like
+    /// `^\(foo\|bar\)` but without adding a group.
+    pub fn anchor_all_alternatives(&self) -> Result<Regex> {
+        compiler::anchor_all_alternatives(self)
+    }
+
+    /// Iterate over all non-overlapping matches in the text
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        Matches::new(self, text)
+    }
+
+    /// Iterate over non-overlapping matches, merging those that touch
+    ///
+    /// For highlighting: a match that starts where the previous one ended
+    /// extends it instead of being yielded separately.
+    pub fn find_iter_merged<'r, 't>(&'r self, text: &'t str) -> MergedMatches<'r, 't> {
+        MergedMatches::new(self, text)
+    }
+
+    /// Iterate over the captures of all non-overlapping matches
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches::new(self, text, None)
+    }
+
+    /// Iterate over the captures of matches that follow on without gaps
+    ///
+    /// The first match must start at the beginning of the text and each
+    /// later one where the previous ended, as if the pattern began with
+    /// `\G`; iteration stops at the first position where no match starts,
+    /// or after an empty match. For strict tokenizing, where text no token
+    /// matches is an error: compare the last match end with the text length.
+    pub fn captures_iter_contiguous<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches::new(self, text, None).contiguous()
+    }
+
+    /// A shared regex matching runs of ASCII whitespace
+    ///
+    /// Compiled on first use. Pair it with [`Regex::split_trimmed`] to split
+    /// like `str::split_whitespace`.
+    pub fn whitespace_splitter() -> &'static Regex {
+        static WHITESPACE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+        WHITESPACE.get_or_init(|| {
+            Regex::with_syntax("[ \t\n\r\x0B\x0C]+", SyntaxFlags::AWK)
+                .expect("whitespace pattern compiles")
+        })
+    }
+
+    /// Split the text on non-overlapping matches, yielding the fields between
+    ///
+    /// A match at either end of the text yields an empty field there.
+    pub fn split<'r, 't>(&'r self, text: &'t str) -> Split<'r, 't> {
+        Split::new(self, text, false)
+    }
+
+    /// Split the text on non-overlapping matches, dropping an empty first or
+    /// last field
+    ///
+    /// With [`Regex::whitespace_splitter`] this yields no empty fields, so
+    /// leading and trailing whitespace is ignored.
+    pub fn split_trimmed<'r, 't>(&'r self, text: &'t str) -> Split<'r, 't> {
+        Split::new(self, text, true)
+    }
+
+    /// Split the text on non-overlapping matches, keeping the separators
+    ///
+    /// Yields a field, then alternately a separator's captures and the field
+    /// after it, so delimiter details are not lost.
+    pub fn split_captures<'r, 't>(&'r self, text: &'t str) -> SplitCaptures<'r, 't> {
+        SplitCaptures::new(self, text)
+    }
+
+    /// Iterate over matches together with the unmatched text preceding each
+    ///
+    /// Yields `(gap, Some(captures))` per match, then `(tail, None)` for the
+    /// text after the last match, so output can be rebuilt in one pass.
+    pub fn captures_iter_with_gaps<'r, 't>(&'r self, text: &'t str) -> CapturesWithGaps<'r, 't> {
+        CapturesWithGaps::new(self, text)
+    }
+
+    /// Iterate over the captures of at most `max_matches` non-overlapping matches
+    ///
+    /// A built-in cap for scanning untrusted input, where a pathological text
+    /// could otherwise yield millions of tiny matches.
+    pub fn captures_iter_limited<'r, 't>(
+        &'r self,
+        text: &'t str,
+        max_matches: usize,
+    ) -> CaptureMatches<'r, 't> {
+        CaptureMatches::new(self, text, Some(max_matches))
+    }
+
+    /// Iterate over all matches, including overlapping ones
+    ///
+    /// Each search resumes one char after the previous match start.
+    pub fn find_iter_overlapping<'r, 't>(&'r self, text: &'t str) -> OverlappingMatches<'r, 't> {
+        self.find_iter_overlapping_step(text, 1)
+    }
+
+    /// Iterate over overlapping matches whose starts are at least `step` chars apart
+    ///
+    /// Each search resumes `step` chars after the previous match start, which
+    /// bounds the number of matches on pathological inputs. A `step` of 0 is
+    /// treated as 1.
+    pub fn find_iter_overlapping_step<'r, 't>(
+        &'r self,
+        text: &'t str,
+        step: usize,
+    ) -> OverlappingMatches<'r, 't> {
+        OverlappingMatches::new(self, text, step)
+    }
+
+    /// Replace all non-overlapping matches with `replacement`
+    ///
+    /// In `replacement`, `$0` to `$9` stand for the text of that group,
+    /// `${n}` for any group number and `${name}` for a named group; `$$` is a
+    /// literal `$`. Groups that did not take part in a match expand to
+    /// nothing, and a `$` starting none of these is kept as is.
+    ///
+    /// Returns `Cow::Borrowed(text)` when nothing matched, so the common
+    /// "no change" case does not allocate. Text between matches is copied as
+    /// byte slices of the input, never re-encoded, so it comes back verbatim.
+    pub fn replace_all<'t>(&self, text: &'t str, replacement: &str) -> Cow<'t, str> {
+        self.replace_each(text, |captures, text, offsets, dst| {
+            self.expand(captures, text, offsets, replacement, dst)
+        })
+    }
+
+    /// Replace all non-overlapping matches with what `f` returns for them
+    ///
+    /// `f` gets each match's captures and the original text; capture
+    /// positions are char indices into that text. Unmatched text is copied
+    /// verbatim, and an empty match moves the search on by one char.
+    pub fn replace_all_with<F>(&self, text: &str, mut f: F) -> String
+    where
+        F: FnMut(&Captures, &str) -> String,
+    {
+        self.replace_each(text, |captures, text, _, dst| {
+            dst.push_str(&f(captures, text))
+        })
+        .into_owned()
+    }
+
+    /// Rebuild `text`, letting `replace` append the text for each match
+    fn replace_each<'t>(
+        &self,
+        text: &'t str,
+        mut replace: impl FnMut(&Captures, &str, &[usize], &mut String),
+    ) -> Cow<'t, str> {
+        let mut matches = self.captures_iter(text).peekable();
+        if matches.peek().is_none() {
+            return Cow::Borrowed(text);
+        }
+
+        let offsets = byte_offsets(text);
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for captures in matches {
+            let Some((start, end)) = captures.get(0) else {
+                continue;
+            };
+            result.push_str(&text[last..offsets[start]]);
+            replace(&captures, text, &offsets, &mut result);
+            last = offsets[end];
+        }
+        result.push_str(&text[last..]);
+
+        Cow::Owned(result)
+    }
+
+    /// Append `replacement` to `dst`, substituting group references
+    fn expand(
+        &self,
+        captures: &Captures,
+        text: &str,
+        offsets: &[usize],
+        replacement: &str,
+        dst: &mut String,
+    ) {
+        let mut rest = replacement;
+        while let Some(dollar) = rest.find('$') {
+            dst.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+
+            let (group, len) = match rest.chars().next() {
+                Some('$') => {
+                    dst.push('$');
+                    rest = &rest[1..];
+                    continue;
+                }
+                Some(digit @ '0'..='9') => (digit.to_digit(10).map(|n| n as usize), 1),
+                Some('{') => match rest.find('}') {
+                    Some(close) => {
+                        let reference = &rest[1..close];
+                        let group = reference.parse().ok().or_else(|| {
+                            self.group_names
+                                .iter()
+                                .position(|name| name.as_deref() == Some(reference))
+                        });
+                        (group, close + 1)
+                    }
+                    None => {
+                        dst.push('$');
+                        continue;
+                    }
+                },
+                _ => {
+                    dst.push('$');
+                    continue;
+                }
+            };
+
+            if let Some((start, end)) = group.and_then(|group| captures.get(group)) {
+                dst.push_str(&text[offsets[start]..offsets[end]]);
+            }
+            rest = &rest[len..];
+        }
+        dst.push_str(rest);
+    }
+
+    /// Search for the first match starting at char position `start`
+    pub(crate) fn search_captures(
+        &self,
+        text: &str,
+        start: usize,
+        limits: ExecLimits,
+    ) -> Option<Captures> {
+        self.search_captures_in_window(text, start, limits, matcher::Window::default())
+    }
+
+    /// Search for the first match starting at char position `start` within `window`
+    fn search_captures_in_window(
+        &self,
+        text: &str,
+        start: usize,
+        limits: ExecLimits,
+        window: matcher::Window,
+    ) -> Option<Captures> {
+        // Text is already validated as UTF-8 by Rust's &str type
+        matcher::check_input_len(text, limits).ok()?;
+        let chars: Vec<char> = text.chars().collect();
+        self.search_chars(&chars, start, limits, window)
+    }
+
+    /// Search text already split into chars for the first match starting at
+    /// char position `start` within `window`
+    pub(crate) fn search_chars(
+        &self,
+        chars: &[char],
+        start: usize,
+        limits: ExecLimits,
+        window: matcher::Window,
+    ) -> Option<Captures> {
+        self.try_search_chars(chars, start, None, limits, window)
+            .ok()
+            .flatten()
+    }
+
+    /// Like `search_chars`, but reporting a search cut short by a limit
+    ///
+    /// `scan` caps how many chars past `start` a match may begin.
+    fn try_search_chars(
+        &self,
+        chars: &[char],
+        start: usize,
+        scan: Option<usize>,
+        limits: ExecLimits,
+        window: matcher::Window,
+    ) -> Result<Option<Captures>> {
+        let mut stats = SearchStats::default();
+        self.try_search_chars_with_stats(chars, start, scan, limits, window, &mut stats)
+    }
+
+    /// Like `try_search_chars`, recording the work done into `stats`
+    fn try_search_chars_with_stats(
+        &self,
+        chars: &[char],
+        start: usize,
+        scan: Option<usize>,
+        limits: ExecLimits,
+        window: matcher::Window,
+        stats: &mut SearchStats,
+    ) -> Result<Option<Captures>> {
+        let end = window.end.map_or(chars.len(), |end| end.min(chars.len()));
+        let range = end.saturating_sub(start);
+        let range = scan.map_or(range, |scan| scan.min(range)) as i32;
+        let pos = matcher::search_in_window(self, chars, start, range, limits, stats, window)?;
+        if pos < 0 {
+            return Ok(None);
+        }
+        matcher::match_at_in_window(self, chars, pos as usize, start, limits, window, stats)
+    }
+}
+
+impl Regex {
+    /// Apply the translation table and ASCII case folding to a character
+    pub(crate) fn translate_char(&self, ch: char) -> char {
+        translate_char(self.translate.as_ref(), self.syntax, ch)
+    }
+}
+
+/// Compile `pattern` with default syntax and test whether it matches `text`
+pub fn is_match(pattern: &str, text: &str) -> Result<bool> {
+    Ok(Regex::new(pattern)?.is_match(text))
+}
+
+/// Compile `pattern` with default syntax and find its first match in `text`
+pub fn find(pattern: &str, text: &str) -> Result<Option<(usize, usize)>> {
+    Ok(Regex::new(pattern)?.find(text))
+}
+
+/// Compile each of `patterns` with `syntax`, one result per pattern in order
+///
+/// A failing pattern does not stop the rest from compiling, so every broken
+/// pattern in a batch can be reported at once.
+pub fn compile_all(patterns: &[&str], syntax: SyntaxFlags) -> Vec<Result<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| Regex::with_syntax(pattern, syntax))
+        .collect()
+}
+
+/// Byte offset of every char index in `text`, plus one entry for the end
+pub(crate) fn byte_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+/// Map a character through an optional translation table, then fold ASCII
+/// letters when `CASE_INSENSITIVE_ASCII` is set
+pub(crate) fn translate_char(
+    translate: Option<&std::collections::BTreeMap<char, char>>,
+    syntax: SyntaxFlags,
+    ch: char,
+) -> char {
+    let ch = translate
+        .and_then(|translate| translate.get(&ch).copied())
+        .unwrap_or(ch);
+
+    if syntax.case_insensitive_ascii() {
+        ch.to_ascii_lowercase()
+    } else {
+        ch
+    }
 }
 
 impl Captures {
@@ -116,6 +1233,25 @@ impl Captures {
             .and_then(|(start, end)| start.zip(end))
     }
 
+    /// The text of a capture group, sliced out of `text`
+    ///
+    /// `text` must be the string that was matched: the stored char positions
+    /// are converted to byte offsets against it, so any other string gives
+    /// a meaningless slice, or `None` when it is too short.
+    pub fn matched_str<'t>(&self, index: usize, text: &'t str) -> Option<&'t str> {
+        let (start, end) = self.get(index)?;
+        let mut offsets = text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(text.len()));
+        let start_byte = offsets.nth(start)?;
+        let end_byte = match end - start {
+            0 => start_byte,
+            len => offsets.nth(len - 1)?,
+        };
+        Some(&text[start_byte..end_byte])
+    }
+
     /// Get number of capture groups (including group 0)
     pub fn len(&self) -> usize {
         self.groups
@@ -128,4 +1264,41 @@ impl Captures {
     pub fn is_empty(&self) -> bool {
         self.groups[0].0.is_none()
     }
+
+    /// The bounds of every group in the pattern, group 0 first
+    ///
+    /// Groups that did not take part in the match are `None`, so a whole
+    /// match can be checked with one comparison.
+    pub fn to_vec(&self) -> Vec<Option<(usize, usize)>> {
+        (0..self.group_count).map(|index| self.get(index)).collect()
+    }
+
+    /// The text of the group called `name`, sliced out of `text`
+    ///
+    /// `None` when no group has that name or the group did not participate.
+    /// As with `matched_str`, `text` must be the string that was matched.
+    pub fn name<'t>(&self, name: &str, text: &'t str) -> Option<&'t str> {
+        let index = self
+            .names
+            .as_ref()?
+            .iter()
+            .position(|group| group.as_deref() == Some(name))?;
+        self.matched_str(index, text)
+    }
+}
+
+/// Lists only the groups that matched, e.g. `Captures { 0: (0, 9), 2: (6, 9) }`
+impl std::fmt::Debug for Captures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Captures {{")?;
+        let mut first = true;
+        for index in 0..RE_NREGS {
+            if let Some(span) = self.get(index) {
+                let separator = if first { " " } else { ", " };
+                write!(f, "{separator}{index}: {span:?}")?;
+                first = false;
+            }
+        }
+        write!(f, "{}}}", if first { "" } else { " " })
+    }
 }
@@ -3,15 +3,26 @@
 //! This module ports the matching logic from re_match_2 and re_search_2
 //! in regexpr.c:880-1464
 
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, HashSet},
+    rc::Rc,
+    time::Instant,
+};
+
 use crate::{
+    analysis,
     error::{RegexError, Result},
-    opcodes::CompiledOp,
-    Captures, ExecLimits, Regex, RE_NREGS,
+    opcodes::{instruction_len, CompiledOp, SyntaxType, REPEAT_UNBOUNDED},
+    Anchor, Captures, ExecLimits, FirstSet, Regex, SyntaxFlags, RE_NREGS,
 };
 
 /// Initial size of failure stack
 const INITIAL_FAILURES: usize = 128;
 
+/// Ticks between reads of the clock when `ExecLimits::timeout` is set
+const CLOCK_CHECK_TICKS: usize = 4096;
+
 /// A failure point for backtracking
 #[derive(Debug, Clone)]
 struct FailurePoint {
@@ -19,6 +30,157 @@ struct FailurePoint {
     text_pos: usize,
     /// Position in bytecode to resume from
     code_pos: usize,
+    /// Active recursive calls at the time the failure point was pushed
+    calls: Option<Rc<CallFrame>>,
+    /// Length of the register trail at the time, undone back to on backtrack
+    trail_len: usize,
+    /// Alternation branch entered by resuming here, when recording branches
+    branch: Option<usize>,
+    /// Pushed by `dummy_failure_jump` for a loop's first round, so it only
+    /// fails further until an `update_failure_jump` makes it real
+    dummy: bool,
+}
+
+/// Which register array a trail entry refers to
+#[derive(Debug, Clone, Copy)]
+enum RegisterKind {
+    Start,
+    End,
+    Maybe,
+}
+
+/// A state change recorded so backtracking can undo it
+///
+/// Without this, captures set by an abandoned alternative would survive
+/// into the result of the alternative that finally matches, and counted
+/// repetitions would resume with the wrong iteration count.
+#[derive(Debug, Clone, Copy)]
+enum TrailEntry {
+    /// A register overwrite and the value it replaced
+    Register {
+        kind: RegisterKind,
+        reg: usize,
+        old: Option<usize>,
+    },
+    /// A counter pushed by `RepeatStart`
+    CounterPush,
+    /// A counter popped by `RepeatPop`
+    CounterPop(Counter),
+    /// An update to the innermost counter and the value it replaced
+    CounterSet(Counter),
+    /// A failure stack mark pushed by `AtomicStart`
+    MarkPush,
+    /// A failure stack mark popped by `AtomicEnd`
+    MarkPop(usize),
+    /// An alternation branch appended to the branch path
+    BranchPush,
+}
+
+/// Progress through an active counted repetition
+#[derive(Debug, Clone, Copy)]
+struct Counter {
+    /// Iterations of the body completed
+    count: u32,
+    /// Text position where the current iteration began
+    iter_start: usize,
+}
+
+/// An active recursive call made by the `Call` opcode
+///
+/// Frames form a persistent list so failure points can snapshot the call
+/// stack by cloning a pointer.
+#[derive(Debug)]
+struct CallFrame {
+    /// Group being recursed into, 0 for the whole pattern
+    group: u8,
+    /// Bytecode position to resume at when the call returns
+    return_pos: usize,
+    /// Text position where the call was made
+    entry_text_pos: usize,
+    /// Registers at the time of the call, restored on return
+    saved_registers: Box<Registers>,
+    /// Enclosing call, if any
+    parent: Option<Rc<CallFrame>>,
+}
+
+/// Snapshot of the capture registers
+#[derive(Debug)]
+struct Registers {
+    start: [Option<usize>; RE_NREGS],
+    end: [Option<usize>; RE_NREGS],
+    maybe: [Option<usize>; RE_NREGS],
+}
+
+/// How the ends of the text relate to the input it was taken from
+///
+/// When text is matched in chunks, a chunk edge is not a real edge of the
+/// input. Clearing a flag stops `^`/`$` matching at that edge, and makes
+/// `\b`, `\<` and `\>` assume the unseen neighbouring char continues a
+/// word, so no word boundary is reported in the middle of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Boundaries {
+    /// The start of the text is a real line and buffer start
+    pub bol_at_start: bool,
+    /// The end of the text is a real line and buffer end
+    pub eol_at_end: bool,
+}
+
+impl Default for Boundaries {
+    fn default() -> Self {
+        Self {
+            bol_at_start: true,
+            eol_at_end: true,
+        }
+    }
+}
+
+/// Char classes that can be chosen per call instead of fixed at compile time
+///
+/// `word_char` decides what `\w`, `\W`, `\b`, `\B`, `\<` and `\>` treat as
+/// part of a word, and `newline` is the char `^` and `$` match around and
+/// `.` does not match. The default is ASCII word chars and `\n`; without a
+/// context, patterns compiled with `SyntaxFlags::UNICODE_WORD` count Unicode
+/// letters and digits as word chars instead.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchContext {
+    /// Whether a char is a word char
+    pub word_char: fn(char) -> bool,
+    /// The line separator
+    pub newline: char,
+}
+
+impl Default for MatchContext {
+    fn default() -> Self {
+        Self {
+            word_char: is_word_char,
+            newline: '\n',
+        }
+    }
+}
+
+impl MatchContext {
+    /// The context a pattern compiled with `syntax` uses by default
+    fn for_syntax(syntax: SyntaxFlags) -> Self {
+        Self {
+            word_char: if syntax.unicode_word() {
+                is_unicode_word_char
+            } else {
+                is_word_char
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// The part of the text a match may consume, and how its edges behave
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Window {
+    /// Char position matches may not extend past, if before the text end
+    pub(crate) end: Option<usize>,
+    /// Whether the text edges are real input edges
+    pub(crate) boundaries: Boundaries,
+    /// Char classes chosen by the caller, if not the defaults
+    pub(crate) context: Option<MatchContext>,
 }
 
 /// Execution state for the regex virtual machine
@@ -26,7 +188,19 @@ struct MatchState<'a> {
     /// The compiled regex
     regex: &'a Regex,
     /// Input text as chars
-    text_chars: Vec<char>,
+    text_chars: &'a [char],
+    /// Char position matches may not consume past
+    text_end: usize,
+    /// Whether the text edges are real input edges
+    boundaries: Boundaries,
+    /// Word chars and line separator in effect
+    context: MatchContext,
+    /// Most failure points stacked at once, over every attempt
+    max_failure_depth: usize,
+    /// When `ExecLimits::timeout` runs out, counted from state creation
+    deadline: Option<Instant>,
+    /// Ticks left before the clock is next read, over every attempt
+    ticks_to_clock_check: usize,
     /// Current position in text (char index)
     text_pos: usize,
     /// Position in bytecode
@@ -43,14 +217,49 @@ struct MatchState<'a> {
     limits: ExecLimits,
     /// Current tick count
     ticks: usize,
+    /// Innermost active recursive call
+    calls: Option<Rc<CallFrame>>,
+    /// Register and counter changes since matching began, for undoing on backtrack
+    trail: Vec<TrailEntry>,
+    /// Counters of the active counted repetitions, innermost last
+    counters: Vec<Counter>,
+    /// Failure stack heights at the entry of the active atomic groups
+    atomic_marks: Vec<usize>,
+    /// Set when backtracking found no failure point left, i.e. no match
+    exhausted: bool,
+    /// Branch index of each alternation `failure_jump`, when recording branches
+    branch_points: Option<&'a BTreeMap<usize, usize>>,
+    /// Alternation branches taken so far, in order
+    branch_path: Vec<usize>,
+    /// Whether only matches reaching the end of the text count, and running
+    /// out of text counts as a possible match
+    prefix_mode: bool,
+    /// Whether only matches reaching the end of the text count
+    whole_text: bool,
+    /// Set when a prefix-mode match needed a char past the end of the text
+    hit_end: bool,
+    /// Char position the search started from, where `\G` matches
+    search_start: usize,
+    /// `(failure_jump, text position)` states already entered, when memoizing
+    visited: Option<HashSet<(usize, usize)>>,
 }
 
 impl<'a> MatchState<'a> {
     /// Create new match state
-    fn new(regex: &'a Regex, text: &str, limits: ExecLimits) -> Self {
+    fn new(regex: &'a Regex, text_chars: &'a [char], limits: ExecLimits, window: Window) -> Self {
         Self {
             regex,
-            text_chars: text.chars().collect(),
+            text_end: window
+                .end
+                .map_or(text_chars.len(), |end| end.min(text_chars.len())),
+            boundaries: window.boundaries,
+            max_failure_depth: 0,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            ticks_to_clock_check: CLOCK_CHECK_TICKS,
+            context: window
+                .context
+                .unwrap_or_else(|| MatchContext::for_syntax(regex.syntax)),
+            text_chars,
             text_pos: 0,
             code_pos: 0,
             failure_stack: Vec::with_capacity(INITIAL_FAILURES),
@@ -59,21 +268,61 @@ impl<'a> MatchState<'a> {
             reg_maybe_pos: [None; RE_NREGS],
             limits,
             ticks: 0,
+            calls: None,
+            trail: Vec::new(),
+            counters: Vec::new(),
+            atomic_marks: Vec::new(),
+            exhausted: false,
+            branch_points: None,
+            branch_path: Vec::new(),
+            prefix_mode: false,
+            whole_text: false,
+            hit_end: false,
+            search_start: 0,
+            visited: (limits.memoize && analysis::memoizable(&regex.buffer)).then(HashSet::new),
         }
     }
 
+    /// Clear everything a match attempt leaves behind, keeping the allocations
+    ///
+    /// Lets a search reuse one state for every start position.
+    fn reset(&mut self) {
+        self.failure_stack.clear();
+        self.reg_start_pos = [None; RE_NREGS];
+        self.reg_end_pos = [None; RE_NREGS];
+        self.reg_maybe_pos = [None; RE_NREGS];
+        self.ticks = 0;
+        self.calls = None;
+        self.trail.clear();
+        self.counters.clear();
+        self.atomic_marks.clear();
+        self.exhausted = false;
+        self.branch_path.clear();
+        self.hit_end = false;
+        // Visited states are kept: a state that failed from one start
+        // position fails from every other
+    }
+
     /// Check if we've exceeded execution limits
     fn check_limits(&mut self) -> Result<()> {
         self.ticks += 1;
 
         if let Some(max_ticks) = self.limits.max_ticks {
             if self.ticks >= max_ticks {
+                log_event!(warn, "tick limit of {max_ticks} exceeded");
                 return Err(RegexError::Timeout);
             }
         }
 
-        if self.failure_stack.len() >= self.limits.max_failures {
-            return Err(RegexError::ExecutionError);
+        if let Some(deadline) = self.deadline {
+            self.ticks_to_clock_check -= 1;
+            if self.ticks_to_clock_check == 0 {
+                self.ticks_to_clock_check = CLOCK_CHECK_TICKS;
+                if Instant::now() >= deadline {
+                    log_event!(warn, "timeout of {:?} exceeded", self.limits.timeout);
+                    return Err(RegexError::Timeout);
+                }
+            }
         }
 
         Ok(())
@@ -87,19 +336,18 @@ impl<'a> MatchState<'a> {
     }
 
     /// Get current character without advancing
-    fn current_char(&self) -> Result<char> {
-        if self.text_pos >= self.text_chars.len() {
+    fn current_char(&mut self) -> Result<char> {
+        if self.text_pos >= self.text_end {
+            if self.prefix_mode && self.text_pos == self.text_chars.len() {
+                self.hit_end = true;
+            }
             return Err(RegexError::ExecutionError);
         }
 
         let ch = self.text_chars[self.text_pos];
 
-        // Apply translation if available
-        let Some(ref translate) = self.regex.translate else {
-            return Ok(ch);
-        };
-
-        Ok(translate.get(&ch).copied().unwrap_or(ch))
+        // Apply translation and case folding if enabled
+        Ok(self.regex.translate_char(ch))
     }
 
     /// Advance text position
@@ -107,9 +355,57 @@ impl<'a> MatchState<'a> {
         self.text_pos += 1;
     }
 
-    /// Check if we're at end of text
-    fn at_end(&self) -> bool {
-        self.text_pos >= self.text_chars.len()
+    /// Check if `^` can match at the current position
+    fn at_line_start(&self) -> bool {
+        match self.text_pos {
+            0 => self.boundaries.bol_at_start,
+            pos => self.text_chars[pos - 1] == self.context.newline,
+        }
+    }
+
+    /// Check if `$` can match at the current position
+    fn at_line_end(&self) -> bool {
+        match self.text_chars.get(self.text_pos) {
+            None => self.boundaries.eol_at_end,
+            Some(&ch) => ch == self.context.newline,
+        }
+    }
+
+    /// Whether the char before the current position is a word char
+    ///
+    /// `None` at the real start of the input; an unseen char before a chunk
+    /// edge counts as a word char.
+    fn prev_is_word(&self) -> Option<bool> {
+        match self.text_pos {
+            0 if self.boundaries.bol_at_start => None,
+            0 => Some(true),
+            pos => Some((self.context.word_char)(self.text_chars[pos - 1])),
+        }
+    }
+
+    /// Whether the char at the current position is a word char
+    ///
+    /// `None` at the real end of the input; an unseen char after a chunk
+    /// edge counts as a word char. Chars past a match window are still
+    /// visible here, as they are real context.
+    fn next_is_word(&self) -> Option<bool> {
+        match self.text_chars.get(self.text_pos) {
+            None if self.boundaries.eol_at_end => None,
+            None => Some(true),
+            Some(&ch) => Some((self.context.word_char)(ch)),
+        }
+    }
+
+    /// Whether `ch` has the syntax class `syntax_code`
+    ///
+    /// `None` for codes no class is defined for, which match nothing either way.
+    fn has_syntax(&self, syntax_code: u8, ch: char) -> Option<bool> {
+        match SyntaxType::from_byte(syntax_code)? {
+            SyntaxType::Normal => None,
+            SyntaxType::Word => Some((self.context.word_char)(ch)),
+            SyntaxType::Digit => Some(ch.is_ascii_digit()),
+            SyntaxType::Space => Some(matches!(ch, '\t'..='\r' | ' ')),
+        }
     }
 
     /// Get absolute position in text
@@ -118,27 +414,251 @@ impl<'a> MatchState<'a> {
     }
 
     /// Push failure point onto stack
+    ///
+    /// Fails with `ExecutionError` once `max_failures` points are stacked, so
+    /// with a limit of 0 only patterns that never backtrack can match.
     fn push_failure(&mut self, code_pos: usize) -> Result<()> {
+        self.push_failure_point(code_pos, false)
+    }
+
+    /// Push a failure point resuming at `code_pos`, or a dummy one
+    fn push_failure_point(&mut self, code_pos: usize, dummy: bool) -> Result<()> {
         if self.failure_stack.len() >= self.limits.max_failures {
+            log_event!(
+                warn,
+                "failure stack limit of {} exceeded",
+                self.limits.max_failures
+            );
             return Err(RegexError::ExecutionError);
         }
 
         self.failure_stack.push(FailurePoint {
             text_pos: self.text_pos,
             code_pos,
+            calls: self.calls.clone(),
+            trail_len: self.trail.len(),
+            branch: None,
+            dummy,
         });
+        self.max_failure_depth = self.max_failure_depth.max(self.failure_stack.len());
         Ok(())
     }
 
+    /// Add the work of the last attempt to `stats`
+    fn add_stats(&self, stats: &mut SearchStats) {
+        stats.ticks_used += self.ticks;
+        stats.max_failure_depth = stats.max_failure_depth.max(self.max_failure_depth);
+    }
+
     /// Pop failure point and backtrack
     fn pop_failure(&mut self) -> bool {
-        if let Some(failure) = self.failure_stack.pop() {
+        while let Some(failure) = self.failure_stack.pop() {
+            if failure.dummy {
+                continue;
+            }
             self.text_pos = failure.text_pos;
             self.code_pos = failure.code_pos;
-            true
+            self.calls = failure.calls;
+            self.undo_trail(failure.trail_len);
+            if let Some(branch) = failure.branch {
+                self.take_branch(branch);
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Move the topmost failure point's resume state to the current one
+    ///
+    /// Used by loops: backtracking out of a later iteration keeps the text
+    /// position and captures of the iterations already completed.
+    fn update_top_failure(&mut self) {
+        let trail_len = self.trail.len();
+        if let Some(top) = self.failure_stack.last_mut() {
+            top.text_pos = self.text_pos;
+            top.trail_len = trail_len;
+            top.dummy = false;
+        }
+    }
+
+    /// Index of the failure point leaving the loop that exits at `exit`,
+    /// pushed when its current round began
+    ///
+    /// Failure points pushed since belong to the body, which lies before
+    /// `exit`, so none of them resume there. Leftovers from rounds of the
+    /// same loop run inside a recursive call have a different call stack.
+    fn loop_failure(&self, exit: usize) -> Option<usize> {
+        self.failure_stack.iter().rposition(|failure| {
+            failure.code_pos == exit
+                && match (&failure.calls, &self.calls) {
+                    (Some(theirs), Some(ours)) => Rc::ptr_eq(theirs, ours),
+                    (theirs, ours) => theirs.is_none() && ours.is_none(),
+                }
+        })
+    }
+
+    /// Set a register, recording its old value on the trail
+    fn set_register(&mut self, kind: RegisterKind, reg: usize, value: Option<usize>) {
+        let slot = match kind {
+            RegisterKind::Start => &mut self.reg_start_pos[reg],
+            RegisterKind::End => &mut self.reg_end_pos[reg],
+            RegisterKind::Maybe => &mut self.reg_maybe_pos[reg],
+        };
+        if *slot == value {
+            return;
+        }
+
+        let old = std::mem::replace(slot, value);
+        self.record(TrailEntry::Register { kind, reg, old });
+    }
+
+    /// Record a state change on the trail
+    fn record(&mut self, entry: TrailEntry) {
+        // Nothing can backtrack to before the first failure point
+        if !self.failure_stack.is_empty() {
+            self.trail.push(entry);
+        }
+    }
+
+    /// Undo register and counter changes made since the trail was `len` entries long
+    fn undo_trail(&mut self, len: usize) {
+        while self.trail.len() > len {
+            match self.trail.pop().unwrap() {
+                TrailEntry::Register { kind, reg, old } => {
+                    let slot = match kind {
+                        RegisterKind::Start => &mut self.reg_start_pos[reg],
+                        RegisterKind::End => &mut self.reg_end_pos[reg],
+                        RegisterKind::Maybe => &mut self.reg_maybe_pos[reg],
+                    };
+                    *slot = old;
+                }
+                TrailEntry::CounterPush => {
+                    self.counters.pop();
+                }
+                TrailEntry::CounterPop(counter) => self.counters.push(counter),
+                TrailEntry::CounterSet(counter) => {
+                    if let Some(top) = self.counters.last_mut() {
+                        *top = counter;
+                    }
+                }
+                TrailEntry::MarkPush => {
+                    self.atomic_marks.pop();
+                }
+                TrailEntry::MarkPop(mark) => self.atomic_marks.push(mark),
+                TrailEntry::BranchPush => {
+                    self.branch_path.pop();
+                }
+            }
+        }
+    }
+
+    /// Append an alternation branch to the branch path
+    fn take_branch(&mut self, branch: usize) {
+        self.branch_path.push(branch);
+        self.record(TrailEntry::BranchPush);
+    }
+
+    /// Replace the innermost counter, recording its old value on the trail
+    fn set_counter(&mut self, counter: Counter) -> Result<()> {
+        let top = self.counters.last_mut().ok_or(RegexError::ExecutionError)?;
+        let old = std::mem::replace(top, counter);
+        self.record(TrailEntry::CounterSet(old));
+        Ok(())
+    }
+
+    /// Choose between another iteration and leaving the repetition at `start`
+    ///
+    /// Below the minimum the body must run again and at the maximum the
+    /// repetition must end; in between, the preferred choice is taken and
+    /// the other pushed as a failure point.
+    fn repeat_step(&mut self, start: usize) -> Result<()> {
+        let buffer = &self.regex.buffer;
+        let min = u16::from_le_bytes([buffer[start + 1], buffer[start + 2]]) as u32;
+        let max = u16::from_le_bytes([buffer[start + 3], buffer[start + 4]]);
+        let lazy = buffer[start + 5] != 0;
+        let body = start + 8;
+        let exit = body + u16::from_le_bytes([buffer[start + 6], buffer[start + 7]]) as usize;
+
+        let count = self
+            .counters
+            .last()
+            .ok_or(RegexError::ExecutionError)?
+            .count;
+        if max != REPEAT_UNBOUNDED && count >= max as u32 {
+            self.code_pos = exit;
+            return Ok(());
+        }
+
+        self.set_counter(Counter {
+            count,
+            iter_start: self.text_pos,
+        })?;
+        if count < min {
+            self.code_pos = body;
+        } else if lazy {
+            self.push_failure(body)?;
+            self.code_pos = exit;
         } else {
-            false
+            self.push_failure(exit)?;
+            self.code_pos = body;
         }
+        Ok(())
+    }
+
+    /// Enter a recursive call into `group` (0 for the whole pattern)
+    ///
+    /// Returns false if the call cannot proceed: the group does not exist,
+    /// or the same group is already active at this text position, which
+    /// would recurse forever without consuming input.
+    fn enter_call(&mut self, group: u8) -> bool {
+        let mut frame = self.calls.as_deref();
+        while let Some(active) = frame {
+            if active.group == group && active.entry_text_pos == self.text_pos {
+                return false;
+            }
+            frame = active.parent.as_deref();
+        }
+
+        let target = if group == 0 {
+            0
+        } else {
+            match find_group_start(&self.regex.buffer, group) {
+                Some(target) => target,
+                None => return false,
+            }
+        };
+
+        self.calls = Some(Rc::new(CallFrame {
+            group,
+            return_pos: self.code_pos,
+            entry_text_pos: self.text_pos,
+            saved_registers: Box::new(Registers {
+                start: self.reg_start_pos,
+                end: self.reg_end_pos,
+                maybe: self.reg_maybe_pos,
+            }),
+            parent: self.calls.clone(),
+        }));
+        self.code_pos = target;
+        true
+    }
+
+    /// Return from the innermost call if it recursed into `group`
+    ///
+    /// Captures made inside the call are discarded, as in PCRE.
+    fn return_from_call(&mut self, group: u8) -> bool {
+        let Some(frame) = self.calls.clone().filter(|frame| frame.group == group) else {
+            return false;
+        };
+
+        for reg in 0..=self.regex.group_count() {
+            self.set_register(RegisterKind::Start, reg, frame.saved_registers.start[reg]);
+            self.set_register(RegisterKind::End, reg, frame.saved_registers.end[reg]);
+            self.set_register(RegisterKind::Maybe, reg, frame.saved_registers.maybe[reg]);
+        }
+        self.code_pos = frame.return_pos;
+        self.calls = frame.parent.clone();
+        true
     }
 
     /// Read 16-bit displacement from bytecode
@@ -149,8 +669,19 @@ impl<'a> MatchState<'a> {
         low | (high << 8)
     }
 
-    /// Main matching loop
+    /// Match from `start_pos`, returning the end of the match
+    ///
+    /// `Ok(None)` means every alternative failed; errors mean matching was
+    /// cut short, by a limit or malformed bytecode, so the outcome is unknown.
     fn execute(&mut self, start_pos: usize) -> Result<Option<usize>> {
+        match self.run(start_pos) {
+            Err(_) if self.exhausted => Ok(None),
+            result => result,
+        }
+    }
+
+    /// Main matching loop
+    fn run(&mut self, start_pos: usize) -> Result<Option<usize>> {
         // Set up initial position
         self.text_pos = start_pos;
         self.code_pos = 0;
@@ -158,6 +689,11 @@ impl<'a> MatchState<'a> {
         loop {
             self.check_limits()?;
 
+            // More text could continue the match the pattern was in
+            if self.hit_end {
+                return Ok(Some(self.text_pos));
+            }
+
             if self.code_pos >= self.regex.buffer.len() {
                 return Err(RegexError::ExecutionError);
             }
@@ -168,97 +704,93 @@ impl<'a> MatchState<'a> {
 
             match opcode {
                 CompiledOp::End => {
+                    // End of a (?R) recursion resumes after the call
+                    if self.return_from_call(0) {
+                        continue;
+                    }
+
+                    // A prefix or whole-text match must account for all of the text
+                    if (self.prefix_mode || self.whole_text)
+                        && self.text_pos != self.text_chars.len()
+                    {
+                        self.backtrack()?;
+                        continue;
+                    }
+
                     // Match successful
                     return Ok(Some(self.absolute_pos()));
                 }
 
                 CompiledOp::Bol => {
-                    // Beginning of line
-                    if self.text_pos == 0 {
-                        // At very beginning
-                        continue;
+                    // Beginning of line: start of text or after a newline
+                    if !self.at_line_start() {
+                        self.backtrack()?;
                     }
+                }
 
-                    // Check if previous character was newline
-                    let prev_char = if self.text_pos > 0 {
-                        Some(self.text_chars[self.text_pos - 1])
-                    } else {
-                        None
-                    };
-
-                    if prev_char == Some('\n') {
-                        continue;
+                CompiledOp::Eol => {
+                    // End of line: end of text or before a newline
+                    if !self.at_line_end() {
+                        self.backtrack()?;
                     }
-
-                    self.backtrack()?;
                 }
 
-                CompiledOp::Eol => {
-                    // End of line
-                    if self.at_end() {
-                        continue;
+                CompiledOp::BegBuf => {
+                    // Beginning of buffer: only the real start, newlines aside
+                    if self.text_pos != 0 || !self.boundaries.bol_at_start {
+                        self.backtrack()?;
                     }
+                }
 
-                    if self.current_char()? == '\n' {
-                        continue;
+                CompiledOp::EndBuf => {
+                    // End of buffer: only the real end, not before a final newline
+                    if self.text_pos != self.text_chars.len() || !self.boundaries.eol_at_end {
+                        self.backtrack()?;
                     }
+                }
 
-                    self.backtrack()?;
+                CompiledOp::SearchStart => {
+                    // Only where the search began, so matches chain up
+                    if self.text_pos != self.search_start {
+                        self.backtrack()?;
+                    }
                 }
 
                 CompiledOp::Set => {
                     // Character set with Unicode ranges
+                    let set_pos = self.code_pos - 1;
+                    let set_len = instruction_len(&self.regex.buffer, set_pos)
+                        .ok_or(RegexError::ExecutionError)?;
                     match self.next_char() {
                         Ok(ch) => {
-                            // Read complement flag
                             let complement = self.regex.buffer[self.code_pos] != 0;
-                            self.code_pos += 1;
-
-                            // Read number of ranges
-                            let num_ranges = self.regex.buffer[self.code_pos] as usize;
-                            self.code_pos += 1;
+                            let num_ranges = self.regex.buffer[self.code_pos + 1] as usize;
+
+                            let mut pos = self.code_pos + 2;
+                            let mut read_char = || {
+                                let len = self.regex.buffer[pos] as usize;
+                                let bytes = &self.regex.buffer[pos + 1..pos + 1 + len];
+                                pos += 1 + len;
+                                std::str::from_utf8(bytes).unwrap().chars().next().unwrap()
+                            };
 
+                            // Ranges are sorted and disjoint, so the scan can
+                            // stop at the first range starting past `ch`
                             let mut matched = false;
-                            let mut pos = self.code_pos;
-
-                            // Check each range
                             for _ in 0..num_ranges {
-                                // Read start char
-                                let start_len = self.regex.buffer[pos] as usize;
-                                pos += 1;
-                                let start_bytes = &self.regex.buffer[pos..pos + start_len];
-                                let start_char = std::str::from_utf8(start_bytes)
-                                    .unwrap()
-                                    .chars()
-                                    .next()
-                                    .unwrap();
-                                pos += start_len;
-
-                                // Read end char
-                                let end_len = self.regex.buffer[pos] as usize;
-                                pos += 1;
-                                let end_bytes = &self.regex.buffer[pos..pos + end_len];
-                                let end_char = std::str::from_utf8(end_bytes)
-                                    .unwrap()
-                                    .chars()
-                                    .next()
-                                    .unwrap();
-                                pos += end_len;
-
-                                // Check if character is in this range (but don't break early)
-                                if ch >= start_char && ch <= end_char {
+                                let start_char = read_char();
+                                let end_char = read_char();
+                                if ch < start_char {
+                                    break;
+                                }
+                                if ch <= end_char {
                                     matched = true;
-                                    // Don't break - continue reading all ranges to advance pos correctly
+                                    break;
                                 }
                             }
 
-                            // Apply complement if needed
-                            if complement {
-                                matched = !matched;
-                            }
-
-                            if matched {
-                                self.code_pos = pos; // Skip to end of character set data
+                            if matched != complement {
+                                self.code_pos = set_pos + set_len;
                                 continue;
                             }
 
@@ -297,11 +829,30 @@ impl<'a> MatchState<'a> {
                     }
                 }
 
+                CompiledOp::ExactString => {
+                    // Literal run - compare the length-prefixed UTF-8 string char by char
+                    let regex = self.regex;
+                    let run_len = regex.buffer[self.code_pos] as usize;
+                    self.code_pos += 1;
+                    let run = &regex.buffer[self.code_pos..self.code_pos + run_len];
+                    self.code_pos += run_len;
+
+                    let matched = std::str::from_utf8(run)
+                        .unwrap()
+                        .chars()
+                        .all(|expected| self.next_char().is_ok_and(|ch| ch == expected));
+                    if !matched {
+                        self.backtrack()?;
+                    }
+                }
+
                 CompiledOp::AnyChar => {
-                    // Any character except newline
+                    // Any character except newline, unless newline is included
                     match self.next_char() {
                         Ok(ch) => {
-                            if ch != '\n' {
+                            if ch != self.context.newline
+                                || self.regex.syntax.any_includes_newline()
+                            {
                                 continue;
                             }
                             self.backtrack()?;
@@ -319,7 +870,7 @@ impl<'a> MatchState<'a> {
                     self.code_pos += 1;
 
                     if reg < RE_NREGS {
-                        self.reg_maybe_pos[reg] = Some(self.absolute_pos());
+                        self.set_register(RegisterKind::Maybe, reg, Some(self.absolute_pos()));
                     }
                 }
 
@@ -329,8 +880,21 @@ impl<'a> MatchState<'a> {
                     self.code_pos += 1;
 
                     if reg < RE_NREGS {
-                        self.reg_start_pos[reg] = self.reg_maybe_pos[reg];
-                        self.reg_end_pos[reg] = Some(self.absolute_pos());
+                        self.set_register(RegisterKind::Start, reg, self.reg_maybe_pos[reg]);
+                        self.set_register(RegisterKind::End, reg, Some(self.absolute_pos()));
+                    }
+
+                    // End of a \g<n> recursion resumes after the call
+                    self.return_from_call(reg as u8);
+                }
+
+                CompiledOp::Call => {
+                    // Recursive call into a group or the whole pattern
+                    let group = self.regex.buffer[self.code_pos];
+                    self.code_pos += 1;
+
+                    if !self.enter_call(group) {
+                        self.backtrack()?;
                     }
                 }
 
@@ -339,14 +903,9 @@ impl<'a> MatchState<'a> {
                     let reg = self.regex.buffer[self.code_pos] as usize;
                     self.code_pos += 1;
 
-                    if reg >= RE_NREGS || self.reg_end_pos[reg].is_none() {
+                    if !self.match_memory(reg) {
                         self.backtrack()?;
-                        continue;
                     }
-
-                    // This would need implementation to match the captured text
-                    // For now, simplified version
-                    self.backtrack()?;
                 }
 
                 CompiledOp::Jump => {
@@ -360,194 +919,193 @@ impl<'a> MatchState<'a> {
                     let _disp = self.read_displacement();
 
                     // The next instruction should be a FailureJump
-                    if self.code_pos < self.regex.buffer.len()
-                        && CompiledOp::from_byte(self.regex.buffer[self.code_pos])
-                            == Some(CompiledOp::FailureJump)
-                    {
-                        // Skip the FailureJump instruction entirely on first iteration
-                        // This forces the pattern to match at least once before allowing failure
-                        self.code_pos += 3; // Skip FailureJump opcode + 2 displacement bytes
-                    } else {
+                    let next = self.regex.buffer.get(self.code_pos).copied();
+                    let exit = analysis::jump_target(&self.regex.buffer, self.code_pos);
+                    let (Some(CompiledOp::FailureJump), Some(exit)) =
+                        (next.and_then(CompiledOp::from_byte), exit)
+                    else {
                         // Should not happen in well-formed bytecode
                         return Err(RegexError::ExecutionError);
-                    }
+                    };
+
+                    // Skip the FailureJump on the first round, forcing the
+                    // body to match once, but leave a failure point for
+                    // the loop's star_jump to find: as in regexpr.c it only
+                    // fails further until an update makes it real
+                    self.push_failure_point(exit, true)?;
+                    self.code_pos += 3; // Skip FailureJump opcode + 2 displacement bytes
                 }
 
                 CompiledOp::FailureJump => {
-                    // Regular failure jump
-                    if self.failure_stack.len() >= self.limits.max_failures {
-                        return Err(RegexError::ExecutionError);
+                    // Regular failure jump. Reaching one again at the same
+                    // position either repeats a state that already failed
+                    // or is a loop that matched nothing; both fail.
+                    if let Some(visited) = &mut self.visited {
+                        if !visited.insert((self.code_pos - 1, self.text_pos)) {
+                            self.backtrack()?;
+                            continue;
+                        }
                     }
-
                     let disp = self.read_displacement();
                     let target = (self.code_pos as i32 + disp as i32) as usize;
                     self.push_failure(target)?;
+
+                    let branch_points = self.branch_points;
+                    if let Some(&branch) =
+                        branch_points.and_then(|points| points.get(&(self.code_pos - 3)))
+                    {
+                        // The last branch has no failure_jump to record it, so
+                        // the one before records it on backtracking
+                        if !branch_points.is_some_and(|points| points.contains_key(&target)) {
+                            if let Some(top) = self.failure_stack.last_mut() {
+                                top.branch = Some(branch + 1);
+                            }
+                        }
+                        self.take_branch(branch);
+                    }
                 }
 
                 CompiledOp::StarJump => {
-                    // Star jump - this should be converted to UpdateFailureJump during execution
+                    // Acts as regexpr.c's conversion of it: either an
+                    // UpdateFailureJump into the body or a Jump back to
+                    // the loop's FailureJump
+                    let star = self.code_pos - 1;
                     let disp = self.read_displacement();
                     let target = (self.code_pos as i32 + disp as i32) as usize;
-
-                    // For now, treat as UpdateFailureJump
-                    if !self.failure_stack.is_empty() {
-                        let last_idx = self.failure_stack.len() - 1;
-                        self.failure_stack[last_idx].text_pos = self.text_pos;
+                    let exit = self.code_pos;
+
+                    // A round that matched nothing, such as that of `(a*)*`
+                    // or `(|a)*`, would repeat forever; leave the loop
+                    // instead, dropping its failure point if it is on top
+                    if let Some(index) = self.loop_failure(exit) {
+                        if self.failure_stack[index].text_pos == self.text_pos {
+                            if index + 1 == self.failure_stack.len() {
+                                self.failure_stack.pop();
+                            }
+                            continue;
+                        }
                     }
 
-                    self.code_pos = target;
+                    if self.regex.star_jump_updates(star) {
+                        self.update_top_failure();
+                        self.code_pos = target + 3;
+                    } else {
+                        self.code_pos = target;
+                    }
                 }
 
                 CompiledOp::UpdateFailureJump => {
                     // Update failure point and jump
-                    if !self.failure_stack.is_empty() {
-                        let last_idx = self.failure_stack.len() - 1;
-                        self.failure_stack[last_idx].text_pos = self.text_pos;
-                    }
+                    self.update_top_failure();
 
                     let disp = self.read_displacement();
                     self.code_pos = (self.code_pos as i32 + disp as i32) as usize;
                 }
 
-                CompiledOp::WordBeg => {
-                    // Beginning of word (\<)
-                    // Must be at word boundary where next char is word char and prev is not
-                    if self.at_end() {
-                        self.backtrack()?;
-                        continue;
-                    }
-
-                    let next_char = self.current_char()?;
-                    let next_is_word = is_word_char(next_char);
-
-                    if !next_is_word {
-                        self.backtrack()?;
-                        continue;
-                    }
+                CompiledOp::RepeatStart => {
+                    // Enter a counted repetition with a fresh counter
+                    let start = self.code_pos - 1;
+                    self.counters.push(Counter {
+                        count: 0,
+                        iter_start: self.text_pos,
+                    });
+                    self.record(TrailEntry::CounterPush);
+                    self.repeat_step(start)?;
+                }
 
-                    // Check previous character
-                    let prev_is_word = if self.absolute_pos() == 0 {
-                        false // Beginning of text
+                CompiledOp::RepeatEnd => {
+                    // Body matched once more; loop back or leave
+                    let disp = self.read_displacement();
+                    let start = (self.code_pos as i32 + disp as i32) as usize;
+                    let counter = *self.counters.last().ok_or(RegexError::ExecutionError)?;
+                    let count = counter.count + 1;
+                    self.set_counter(Counter { count, ..counter })?;
+
+                    let min = u16::from_le_bytes([
+                        self.regex.buffer[start + 1],
+                        self.regex.buffer[start + 2],
+                    ]) as u32;
+                    if count >= min && self.text_pos == counter.iter_start {
+                        // An empty iteration would only repeat itself
+                        self.code_pos = start
+                            + 8
+                            + u16::from_le_bytes([
+                                self.regex.buffer[start + 6],
+                                self.regex.buffer[start + 7],
+                            ]) as usize;
                     } else {
-                        is_word_char(self.text_chars[self.text_pos - 1])
-                    };
-
-                    if prev_is_word {
-                        self.backtrack()?;
+                        self.repeat_step(start)?;
                     }
                 }
 
-                CompiledOp::WordEnd => {
-                    // End of word (\>)
-                    // Must be at word boundary where prev char is word char and next is not
-                    if self.absolute_pos() == 0 {
-                        self.backtrack()?;
-                        continue;
-                    }
+                CompiledOp::RepeatPop => {
+                    // Leave a counted repetition
+                    let counter = self.counters.pop().ok_or(RegexError::ExecutionError)?;
+                    self.record(TrailEntry::CounterPop(counter));
+                }
 
-                    // Check previous character
-                    let prev_is_word = if self.absolute_pos() == 0 {
-                        false
-                    } else {
-                        is_word_char(self.text_chars[self.text_pos - 1])
-                    };
+                CompiledOp::AtomicStart => {
+                    // Remember which failure points predate the group
+                    self.atomic_marks.push(self.failure_stack.len());
+                    self.record(TrailEntry::MarkPush);
+                }
 
-                    if !prev_is_word {
+                CompiledOp::AtomicEnd => {
+                    // The group matched; forget its alternatives so they are
+                    // never retried
+                    let mark = self.atomic_marks.pop().ok_or(RegexError::ExecutionError)?;
+                    self.record(TrailEntry::MarkPop(mark));
+                    self.failure_stack.truncate(mark);
+                }
+
+                CompiledOp::WordBeg => {
+                    // Beginning of word (\<): next char is a word char, previous is not
+                    if self.next_is_word() != Some(true) || self.prev_is_word() == Some(true) {
                         self.backtrack()?;
-                        continue;
                     }
+                }
 
-                    // Check next character
-                    let next_is_word = if self.at_end() {
-                        false // End of text
-                    } else {
-                        let next_char = self.current_char()?;
-                        is_word_char(next_char)
-                    };
-
-                    if next_is_word {
+                CompiledOp::WordEnd => {
+                    // End of word (\>): previous char is a word char, next is not
+                    if self.prev_is_word() != Some(true) || self.next_is_word() == Some(true) {
                         self.backtrack()?;
                     }
                 }
 
                 CompiledOp::WordBound => {
-                    // Word boundary (\b)
-                    // Match at boundary between word and non-word characters
-                    let at_start = self.absolute_pos() == 0;
-                    let at_end = self.at_end();
-
-                    if at_start || at_end {
-                        // At text boundaries, check if adjacent char is word char
-                        if at_start && !at_end {
-                            let next_char = self.current_char()?;
-                            if !is_word_char(next_char) {
-                                self.backtrack()?;
-                            }
-                        } else if at_end && !at_start {
-                            let prev_is_word = if self.text_pos > 0 {
-                                is_word_char(self.text_chars[self.text_pos - 1])
-                            } else {
-                                false
-                            };
-                            if !prev_is_word {
-                                self.backtrack()?;
-                            }
-                        }
-                        // At very start or very end is always a word boundary
-                    } else {
-                        // In middle of text - check both sides
-                        let prev_is_word = if self.text_pos > 0 {
-                            is_word_char(self.text_chars[self.text_pos - 1])
-                        } else {
-                            false
-                        };
-
-                        let next_char = self.current_char()?;
-                        let next_is_word = is_word_char(next_char);
-
-                        // Word boundary exists when prev and next have different word-ness
-                        if prev_is_word == next_is_word {
-                            self.backtrack()?;
-                        }
+                    // Word boundary (\b): the chars either side differ in
+                    // word-ness; at a real text edge the other side must be a
+                    // word char, and empty text is always a boundary
+                    let boundary = match (self.prev_is_word(), self.next_is_word()) {
+                        (None, None) => true,
+                        (None, Some(next)) => next,
+                        (Some(prev), None) => prev,
+                        (Some(prev), Some(next)) => prev != next,
+                    };
+                    if !boundary {
+                        self.backtrack()?;
                     }
                 }
 
                 CompiledOp::NotWordBound => {
-                    // Not word boundary (\B)
-                    // Match when NOT at boundary between word and non-word characters
-                    let at_start = self.absolute_pos() == 0;
-                    let at_end = self.at_end();
-
-                    if at_start || at_end {
-                        // At text boundaries, this never matches
+                    // Not word boundary (\B): never matches at a real text edge
+                    let inside = match (self.prev_is_word(), self.next_is_word()) {
+                        (Some(prev), Some(next)) => prev == next,
+                        _ => false,
+                    };
+                    if !inside {
                         self.backtrack()?;
-                    } else {
-                        // In middle of text - check both sides
-                        let prev_is_word = if self.text_pos > 0 {
-                            is_word_char(self.text_chars[self.text_pos - 1])
-                        } else {
-                            false
-                        };
-
-                        let next_char = self.current_char()?;
-                        let next_is_word = is_word_char(next_char);
-
-                        // Not word boundary when prev and next have same word-ness
-                        if prev_is_word != next_is_word {
-                            self.backtrack()?;
-                        }
                     }
                 }
 
                 CompiledOp::SyntaxSpec => {
-                    // Match character with specific syntax (\w)
+                    // Match character with specific syntax (\w, \d, \s)
                     let syntax_code = self.regex.buffer[self.code_pos];
                     self.code_pos += 1;
 
                     match self.next_char() {
                         Ok(ch) => {
-                            // For now, only handle Sword (1) for word characters
-                            if syntax_code == 1 && is_word_char(ch) {
+                            if self.has_syntax(syntax_code, ch) == Some(true) {
                                 continue;
                             }
 
@@ -561,14 +1119,13 @@ impl<'a> MatchState<'a> {
                 }
 
                 CompiledOp::NotSyntaxSpec => {
-                    // Match character without specific syntax (\W)
+                    // Match character without specific syntax (\W, \D, \S)
                     let syntax_code = self.regex.buffer[self.code_pos];
                     self.code_pos += 1;
 
                     match self.next_char() {
                         Ok(ch) => {
-                            // For now, only handle Sword (1) for word characters
-                            if syntax_code == 1 && !is_word_char(ch) {
+                            if self.has_syntax(syntax_code, ch) == Some(false) {
                                 continue;
                             }
 
@@ -580,35 +1137,52 @@ impl<'a> MatchState<'a> {
                         }
                     }
                 }
-
-                _ => {
-                    // Other opcodes would be implemented here
-                    return Err(RegexError::ExecutionError);
-                }
             }
         }
     }
 
-    /// Backtrack on failure
+    /// Backtrack on failure, ending the match attempt if nothing is left to try
     fn backtrack(&mut self) -> Result<()> {
         if !self.pop_failure() {
+            self.exhausted = true;
             return Err(RegexError::ExecutionError);
         }
 
         Ok(())
     }
 
+    /// Match the text captured by `reg` again at the current position
+    ///
+    /// Both the captured text and the input go through the translate table,
+    /// so case-insensitive patterns match repeats that differ in case. A
+    /// group that has not captured anything fails to match.
+    fn match_memory(&mut self, reg: usize) -> bool {
+        let (Some(start), Some(end)) = (
+            self.reg_start_pos.get(reg).copied().flatten(),
+            self.reg_end_pos.get(reg).copied().flatten(),
+        ) else {
+            return false;
+        };
+
+        (start..end).all(|captured_pos| {
+            let expected = self.regex.translate_char(self.text_chars[captured_pos]);
+            self.next_char().is_ok_and(|ch| ch == expected)
+        })
+    }
+
     /// Build captures result
     fn build_captures(&self, match_start: usize, match_end: usize) -> Captures {
         let mut captures = Captures {
             groups: [(None, None); RE_NREGS],
+            group_count: self.regex.group_count() + 1,
+            names: self.regex.shared_group_names(),
         };
 
         // Set match group 0
         captures.groups[0] = (Some(match_start), Some(match_end));
 
-        // Set other capture groups
-        for i in 1..RE_NREGS {
+        // Set other capture groups; group-less patterns skip this entirely
+        for i in 1..=self.regex.group_count() {
             if let (Some(start), Some(end)) = (self.reg_start_pos[i], self.reg_end_pos[i]) {
                 captures.groups[i] = (Some(start), Some(end));
             }
@@ -618,12 +1192,59 @@ impl<'a> MatchState<'a> {
     }
 }
 
+/// Find the bytecode position of the `StartMemory` instruction for `group`
+fn find_group_start(buffer: &[u8], group: u8) -> Option<usize> {
+    let mut pos = 0;
+    while pos < buffer.len() {
+        if buffer[pos] == CompiledOp::StartMemory.to_byte() && buffer.get(pos + 1) == Some(&group) {
+            return Some(pos);
+        }
+        pos += instruction_len(buffer, pos)?;
+    }
+    None
+}
+
 /// Check if character is a word character
 fn is_word_char(ch: char) -> bool {
     ch.is_ascii_alphanumeric() || ch == '_'
 }
 
+/// Check if character is a word character, counting Unicode letters and digits
+fn is_unicode_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Reject input longer than `ExecLimits::max_input_chars`
+///
+/// Only counts as far as the limit, and skips counting entirely when the byte
+/// length already fits, so oversized input is never decoded in full.
+pub fn check_input_len(text: &str, limits: ExecLimits) -> Result<()> {
+    match limits.max_input_chars {
+        Some(limit) if text.len() > limit && text.chars().nth(limit).is_some() => {
+            log_event!(warn, "input length limit of {limit} chars exceeded");
+            Err(RegexError::InputTooLong { limit })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counters collected while searching
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Number of start positions a match was attempted at
+    pub start_attempts: usize,
+    /// Ticks spent over every attempt, the unit `ExecLimits::max_ticks`
+    /// bounds for each one
+    pub ticks_used: usize,
+    /// Most failure points stacked at once, the depth
+    /// `ExecLimits::max_failures` bounds
+    pub max_failure_depth: usize,
+}
+
 /// Search for pattern in text
+///
+/// Returns the char position of the first match start, or -1 if there is
+/// none. A search cut short by a limit is an error, not -1.
 pub fn search(
     regex: &Regex,
     text: &str,
@@ -631,43 +1252,165 @@ pub fn search(
     range: i32,
     limits: ExecLimits,
 ) -> Result<i32> {
-    let text_len = text.chars().count();
+    search_with_stats(
+        regex,
+        text,
+        start,
+        range,
+        limits,
+        &mut SearchStats::default(),
+    )
+}
+
+/// Search for pattern in text, recording counters into `stats`
+///
+/// Anchored patterns only attempt positions where the anchor can succeed:
+/// position 0 for `` \` ``, and line starts for `^` (regexpr.c:1382, 1439).
+/// Patterns ending in `$` or `` \' `` with a bounded match length only
+/// attempt positions within that length of a line or buffer end.
+pub fn search_with_stats(
+    regex: &Regex,
+    text: &str,
+    start: usize,
+    range: i32,
+    limits: ExecLimits,
+    stats: &mut SearchStats,
+) -> Result<i32> {
+    check_input_len(text, limits)?;
+
+    let chars: Vec<char> = text.chars().collect();
+    search_in_window(
+        regex,
+        &chars,
+        start,
+        range,
+        limits,
+        stats,
+        Window::default(),
+    )
+}
+
+/// Search for pattern in text already split into chars, with matches
+/// confined to `window`
+///
+/// Taking the chars lets callers searching the same text repeatedly, such as
+/// the match iterators, decode it only once.
+pub(crate) fn search_in_window(
+    regex: &Regex,
+    chars: &[char],
+    start: usize,
+    range: i32,
+    limits: ExecLimits,
+    stats: &mut SearchStats,
+    window: Window,
+) -> Result<i32> {
+    let text_len = chars.len();
+    let window_end = window.end.map_or(text_len, |end| end.min(text_len));
+    let newline = window.context.unwrap_or_default().newline;
     let end = if range >= 0 {
-        std::cmp::min(start + range as usize, text_len)
+        std::cmp::min(start + range as usize, window_end)
     } else {
         start.saturating_sub((-range) as usize)
     };
 
-    if range >= 0 {
-        // Forward search
-        (start..=end)
-            .take_while(|&pos| pos <= text_len)
-            .find_map(|pos| {
-                let mut state = MatchState::new(regex, text, limits);
-                state
-                    .execute(pos)
-                    .ok()
-                    .and_then(|result| result)
-                    .map(|_| pos as i32)
-            })
-            .map(Ok)
-            .unwrap_or(Ok(-1))
+    // Buffer and search anchors allow a single start, so skip the scan
+    let (low, high) = if range >= 0 {
+        (start, end)
     } else {
-        // Backward search
-        (end..=start)
-            .rev()
-            .filter(|&pos| pos <= text_len)
-            .find_map(|pos| {
-                let mut state = MatchState::new(regex, text, limits);
-                state
-                    .execute(pos)
-                    .ok()
-                    .and_then(|result| result)
-                    .map(|_| pos as i32)
-            })
-            .map(Ok)
-            .unwrap_or(Ok(-1))
+        (end, start)
+    };
+    let positions: Box<dyn Iterator<Item = usize>> = match regex.anchor() {
+        Anchor::Buffer => Box::new((low == 0).then_some(0).into_iter()),
+        Anchor::Search => Box::new(std::iter::once(start)),
+        _ if range >= 0 => Box::new(low..=high),
+        _ => Box::new((low..=high).rev()),
+    };
+    // For end-anchored patterns of bounded length, starts further from the
+    // nearest line end than the longest match cannot reach it. That line end
+    // is found lazily, remembering the last span known to hold no newline,
+    // so a search that stops early scans no further than it has to
+    let no_newline = Cell::new((text_len, text_len));
+    let line_end = |pos: usize| {
+        let (from, end) = no_newline.get();
+        let end = if (from..=end).contains(&pos) {
+            end
+        } else {
+            let scan_end = if pos < from { from } else { text_len };
+            let found = chars[pos..scan_end].iter().position(|&ch| ch == newline);
+            match found {
+                Some(offset) => pos + offset,
+                None if pos < from => end,
+                None => text_len,
+            }
+        };
+        no_newline.set((pos, end));
+        end
+    };
+    let can_end = |pos: usize| match (regex.end_anchor(), regex.max_len()) {
+        (Anchor::Buffer, Some(max_len)) => pos + max_len >= text_len,
+        (Anchor::Line, Some(max_len)) => pos + max_len >= line_end(pos),
+        _ => true,
+    };
+    let can_start = |pos: usize| {
+        let start_ok = match regex.anchor() {
+            Anchor::None => true,
+            Anchor::Line => pos == 0 || chars[pos - 1] == newline,
+            Anchor::Buffer => pos == 0,
+            Anchor::Search => pos == start,
+        };
+        // Text is folded before comparison, so test the folded char. The
+        // first set assumes the default word chars, so a caller's own
+        // predicate turns the filter off
+        let first_ok = window.context.is_some()
+            || chars
+                .get(pos)
+                .filter(|_| pos < window_end)
+                .map_or(regex.first_chars() == &FirstSet::Any, |&ch| {
+                    regex.first_chars().contains(regex.translate_char(ch))
+                });
+        start_ok && first_ok && can_end(pos)
+    };
+
+    // A pattern anchored at both ends with a fixed length matches from a
+    // line start to a line end; without a separator inside the text those
+    // are its two ends, so only text of exactly that length can match
+    if let Some(exact_len) = regex.exact_len() {
+        let buffer_anchored =
+            regex.anchor() == Anchor::Buffer && regex.end_anchor() == Anchor::Buffer;
+        if text_len != exact_len && (buffer_anchored || !chars.contains(&newline)) {
+            log_event!(trace, "search skipped: text is not {exact_len} chars");
+            return Ok(-1);
+        }
     }
+
+    log_event!(trace, "search started at {start} with range {range}");
+
+    let mut state = MatchState::new(regex, chars, limits, window);
+    state.search_start = start;
+    for pos in positions.filter(|&pos| pos <= window_end && can_start(pos)) {
+        stats.start_attempts += 1;
+        state.reset();
+        let result = state.execute(pos);
+        state.add_stats(stats);
+        match result {
+            Ok(Some(_)) => {
+                log_event!(trace, "search matched at {pos}");
+                return Ok(pos as i32);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                log_event!(debug, "search aborted at {pos}: {err}");
+                return Err(err);
+            }
+        }
+    }
+
+    log_event!(
+        trace,
+        "search failed after {} start attempts",
+        stats.start_attempts
+    );
+    Ok(-1)
 }
 
 /// Match pattern at specific position
@@ -677,9 +1420,91 @@ pub fn match_at(
     pos: usize,
     limits: ExecLimits,
 ) -> Result<Option<Captures>> {
-    let mut state = MatchState::new(regex, text, limits);
+    check_input_len(text, limits)?;
+
+    let chars: Vec<char> = text.chars().collect();
+    match_at_in_window(
+        regex,
+        &chars,
+        pos,
+        pos,
+        limits,
+        Window::default(),
+        &mut SearchStats::default(),
+    )
+}
+
+/// Match pattern at specific position, also returning the alternation
+/// branches the match took
+///
+/// The path lists, in the order they were entered, the index of the branch
+/// taken at each alternation the successful match passed through.
+pub(crate) fn match_at_with_branch_path(
+    regex: &Regex,
+    chars: &[char],
+    pos: usize,
+    limits: ExecLimits,
+) -> Result<Option<(Captures, Vec<usize>)>> {
+    let branch_points = analysis::branch_points(&regex.buffer);
+    let mut state = MatchState::new(regex, chars, limits, Window::default());
+    state.branch_points = Some(&branch_points);
+    state.search_start = pos;
+
+    Ok(state.execute(pos)?.map(|end_pos| {
+        let captures = state.build_captures(pos, end_pos);
+        (captures, std::mem::take(&mut state.branch_path))
+    }))
+}
+
+/// Whether `text` could be extended into a string the pattern matches whole
+///
+/// Matching starts at 0 and succeeds when it consumes all of `text`, or
+/// wants another char once `text` has run out.
+pub(crate) fn could_match_prefix(regex: &Regex, text: &str, limits: ExecLimits) -> Result<bool> {
+    check_input_len(text, limits)?;
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut state = MatchState::new(regex, &chars, limits, Window::default());
+    state.prefix_mode = true;
+    Ok(state.execute(0)?.is_some() || state.hit_end)
+}
+
+/// Match the pattern against all of `text`, from the first char to the last
+///
+/// Matches ending early are backtracked into, so other alternatives and
+/// repetition counts get their turn to reach the end.
+pub(crate) fn full_match(
+    regex: &Regex,
+    text: &str,
+    limits: ExecLimits,
+) -> Result<Option<Captures>> {
+    check_input_len(text, limits)?;
 
+    let chars: Vec<char> = text.chars().collect();
+    let mut state = MatchState::new(regex, &chars, limits, Window::default());
+    state.whole_text = true;
     Ok(state
-        .execute(pos)?
-        .map(|end_pos| state.build_captures(pos, end_pos)))
+        .execute(0)?
+        .map(|end_pos| state.build_captures(0, end_pos)))
+}
+
+/// Match pattern at specific position in text already split into chars, with
+/// the match confined to `window`
+///
+/// `search_start` is where the search that found `pos` began, for `\G`.
+pub(crate) fn match_at_in_window(
+    regex: &Regex,
+    chars: &[char],
+    pos: usize,
+    search_start: usize,
+    limits: ExecLimits,
+    window: Window,
+    stats: &mut SearchStats,
+) -> Result<Option<Captures>> {
+    let mut state = MatchState::new(regex, chars, limits, window);
+    state.search_start = search_start;
+
+    let result = state.execute(pos);
+    state.add_stats(stats);
+    Ok(result?.map(|end_pos| state.build_captures(pos, end_pos)))
 }
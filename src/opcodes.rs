@@ -48,8 +48,28 @@ pub enum CompiledOp {
     SyntaxSpec = 20,
     /// Matches if syntax code does not match (1 byte follows)
     NotSyntaxSpec = 21,
+    /// Recurse into a group, or the whole pattern for 0 (group number follows)
+    Call = 22,
+    /// Run of exact characters (followed by byte length and UTF-8 bytes)
+    ExactString = 23,
+    /// Start a counted repetition (min and max as little-endian u16, lazy
+    /// flag, then displacement to the matching `RepeatPop`)
+    RepeatStart = 24,
+    /// End of a counted repetition body (displacement back to `RepeatStart`)
+    RepeatEnd = 25,
+    /// Leave a counted repetition, discarding its counter
+    RepeatPop = 26,
+    /// Enter an atomic group, marking the failure stack
+    AtomicStart = 27,
+    /// Leave an atomic group, dropping failure points pushed inside it
+    AtomicEnd = 28,
+    /// Match where the search started
+    SearchStart = 29,
 }
 
+/// `RepeatStart` maximum meaning "no upper bound"
+pub const REPEAT_UNBOUNDED: u16 = u16::MAX;
+
 impl CompiledOp {
     /// Convert byte to opcode
     pub fn from_byte(byte: u8) -> Option<Self> {
@@ -76,6 +96,14 @@ impl CompiledOp {
             19 => Some(CompiledOp::NotWordBound),
             20 => Some(CompiledOp::SyntaxSpec),
             21 => Some(CompiledOp::NotSyntaxSpec),
+            22 => Some(CompiledOp::Call),
+            23 => Some(CompiledOp::ExactString),
+            24 => Some(CompiledOp::RepeatStart),
+            25 => Some(CompiledOp::RepeatEnd),
+            26 => Some(CompiledOp::RepeatPop),
+            27 => Some(CompiledOp::AtomicStart),
+            28 => Some(CompiledOp::AtomicEnd),
+            29 => Some(CompiledOp::SearchStart),
             _ => None,
         }
     }
@@ -97,24 +125,94 @@ impl CompiledOp {
             | CompiledOp::WordBeg
             | CompiledOp::WordEnd
             | CompiledOp::WordBound
-            | CompiledOp::NotWordBound => 0,
+            | CompiledOp::NotWordBound
+            | CompiledOp::RepeatPop
+            | CompiledOp::AtomicStart
+            | CompiledOp::AtomicEnd
+            | CompiledOp::SearchStart => 0,
 
             CompiledOp::Exact
+            | CompiledOp::ExactString
             | CompiledOp::StartMemory
             | CompiledOp::EndMemory
             | CompiledOp::MatchMemory
             | CompiledOp::SyntaxSpec
-            | CompiledOp::NotSyntaxSpec => 1,
+            | CompiledOp::NotSyntaxSpec
+            | CompiledOp::Call => 1,
 
             CompiledOp::Jump
             | CompiledOp::StarJump
             | CompiledOp::FailureJump
             | CompiledOp::UpdateFailureJump
-            | CompiledOp::DummyFailureJump => 2,
+            | CompiledOp::DummyFailureJump
+            | CompiledOp::RepeatEnd => 2,
+
+            CompiledOp::RepeatStart => 7,
 
             CompiledOp::Set => 0, // Variable length - depends on number of ranges
         }
     }
+
+    /// Name of the opcode in listings, after regexpr.c's `C` constants
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            CompiledOp::End => "end",
+            CompiledOp::Bol => "bol",
+            CompiledOp::Eol => "eol",
+            CompiledOp::Set => "set",
+            CompiledOp::Exact => "exact",
+            CompiledOp::AnyChar => "anychar",
+            CompiledOp::StartMemory => "start_memory",
+            CompiledOp::EndMemory => "end_memory",
+            CompiledOp::MatchMemory => "match_memory",
+            CompiledOp::Jump => "jump",
+            CompiledOp::StarJump => "star_jump",
+            CompiledOp::FailureJump => "failure_jump",
+            CompiledOp::UpdateFailureJump => "update_failure_jump",
+            CompiledOp::DummyFailureJump => "dummy_failure_jump",
+            CompiledOp::BegBuf => "begbuf",
+            CompiledOp::EndBuf => "endbuf",
+            CompiledOp::WordBeg => "wordbeg",
+            CompiledOp::WordEnd => "wordend",
+            CompiledOp::WordBound => "wordbound",
+            CompiledOp::NotWordBound => "notwordbound",
+            CompiledOp::SyntaxSpec => "syntaxspec",
+            CompiledOp::NotSyntaxSpec => "notsyntaxspec",
+            CompiledOp::Call => "call",
+            CompiledOp::ExactString => "exact_string",
+            CompiledOp::RepeatStart => "repeat_start",
+            CompiledOp::RepeatEnd => "repeat_end",
+            CompiledOp::RepeatPop => "repeat_pop",
+            CompiledOp::AtomicStart => "atomic_start",
+            CompiledOp::AtomicEnd => "atomic_end",
+            CompiledOp::SearchStart => "search_start",
+        }
+    }
+}
+
+/// Get the total length in bytes of the instruction starting at `pos`
+///
+/// Returns `None` if `pos` does not hold a valid opcode or the instruction
+/// runs past the end of the buffer.
+pub fn instruction_len(buffer: &[u8], pos: usize) -> Option<usize> {
+    let opcode = CompiledOp::from_byte(*buffer.get(pos)?)?;
+
+    let len = match opcode {
+        // Opcode followed by a length-prefixed UTF-8 char or string
+        CompiledOp::Exact | CompiledOp::ExactString => 2 + *buffer.get(pos + 1)? as usize,
+        // Opcode, complement flag, range count, then length-prefixed range bounds
+        CompiledOp::Set => {
+            let num_ranges = *buffer.get(pos + 2)? as usize;
+            let mut len = 3;
+            for _ in 0..num_ranges * 2 {
+                len += 1 + *buffer.get(pos + len)? as usize;
+            }
+            len
+        }
+        _ => 1 + opcode.arg_count(),
+    };
+
+    (pos + len <= buffer.len()).then_some(len)
 }
 
 /// Parse syntax operations (from regexpr.c:69-99)
@@ -167,6 +265,22 @@ pub enum SyntaxOp {
     WordBound = 21,
     /// Not word boundary (\B)
     NotWordBound = 22,
+    /// Recursive call into a group or the whole pattern (`(?R)`, `\g<n>`)
+    Recurse = 23,
+    /// Counted repetition ({n,m})
+    OpenBrace = 24,
+    /// Emacs match at point (\=), rejected as there is no point
+    Point = 25,
+    /// Where the search started (\G)
+    SearchStart = 26,
+    /// Digit character (\d)
+    DigitChar = 27,
+    /// Not digit character (\D)
+    NotDigitChar = 28,
+    /// Whitespace character (\s)
+    SpaceChar = 29,
+    /// Not whitespace character (\S)
+    NotSpaceChar = 30,
 }
 
 /// Syntax table entry type
@@ -176,4 +290,21 @@ pub enum SyntaxType {
     Normal = 0,
     /// Word character (letter, digit, underscore)
     Word = 1,
+    /// ASCII digit
+    Digit = 2,
+    /// ASCII whitespace: space, tab, newline, vertical tab, form feed, return
+    Space = 3,
+}
+
+impl SyntaxType {
+    /// Decode a `SyntaxSpec`/`NotSyntaxSpec` argument
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(SyntaxType::Normal),
+            1 => Some(SyntaxType::Word),
+            2 => Some(SyntaxType::Digit),
+            3 => Some(SyntaxType::Space),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,76 @@
+//! Serde support for match results, behind the `serde` feature
+//!
+//! `ExecLimits` and `SyntaxFlags` derive their impls; `Captures` is written by
+//! hand so that only the groups that were set are stored instead of all
+//! `RE_NREGS` slots, along with the group names when the pattern had any.
+
+use std::sync::Arc;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Captures, RE_NREGS};
+
+/// A group slot that was set: index, start, end
+type GroupEntry = (usize, Option<usize>, Option<usize>);
+
+/// Wire form of `Captures`
+#[derive(Serialize, Deserialize)]
+struct CapturesRepr {
+    group_count: usize,
+    groups: Vec<GroupEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    names: Option<Vec<Option<String>>>,
+}
+
+impl Serialize for Captures {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let groups = self
+            .groups
+            .iter()
+            .enumerate()
+            .filter(|(_, (start, end))| start.is_some() || end.is_some())
+            .map(|(index, &(start, end))| (index, start, end))
+            .collect();
+        CapturesRepr {
+            group_count: self.group_count,
+            groups,
+            names: self.names.as_deref().map(<[_]>::to_vec),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Captures {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = CapturesRepr::deserialize(deserializer)?;
+        if repr.group_count > RE_NREGS {
+            return Err(D::Error::custom(format!(
+                "group count {} exceeds the maximum of {RE_NREGS}",
+                repr.group_count
+            )));
+        }
+        if let Some(names) = &repr.names {
+            if names.len() != repr.group_count {
+                return Err(D::Error::custom(format!(
+                    "{} group names for {} groups",
+                    names.len(),
+                    repr.group_count
+                )));
+            }
+        }
+        let mut groups = [(None, None); RE_NREGS];
+        for (index, start, end) in repr.groups {
+            let slot = groups.get_mut(index).ok_or_else(|| {
+                D::Error::custom(format!(
+                    "group index {index} exceeds the maximum of {RE_NREGS}"
+                ))
+            })?;
+            *slot = (start, end);
+        }
+        Ok(Captures {
+            groups,
+            group_count: repr.group_count,
+            names: repr.names.map(Arc::from),
+        })
+    }
+}
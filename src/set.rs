@@ -0,0 +1,76 @@
+//! Matching one text against many patterns at once
+
+use crate::{compile_all, matcher, matcher::Window, Regex, Result, SyntaxFlags};
+
+/// A group of patterns tested against the same text together
+///
+/// Answers which of the patterns match, rather than where. The text is
+/// decoded into chars once for the whole set instead of once per pattern.
+///
+/// ```
+/// use tature::{RegexSet, SyntaxFlags};
+///
+/// let set = RegexSet::with_syntax(&["^look", "^get (.*)", "lamp$"], SyntaxFlags::AWK).unwrap();
+/// assert_eq!(set.matches("get lamp"), [1, 2]);
+/// assert!(!set.is_match("drop lamp!"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexSet {
+    regexes: Vec<Regex>,
+}
+
+impl RegexSet {
+    /// Compile every pattern with default syntax
+    pub fn new(patterns: &[&str]) -> Result<Self> {
+        Self::with_syntax(patterns, SyntaxFlags::default())
+    }
+
+    /// Compile every pattern with `syntax`, failing on the first that does
+    /// not compile
+    pub fn with_syntax(patterns: &[&str], syntax: SyntaxFlags) -> Result<Self> {
+        let regexes = compile_all(patterns, syntax)
+            .into_iter()
+            .collect::<Result<_>>()?;
+        Ok(Self { regexes })
+    }
+
+    /// Indices of the patterns that match somewhere in `text`, in order
+    pub fn matches(&self, text: &str) -> Vec<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        (0..self.regexes.len())
+            .filter(|&index| self.matches_chars(index, text, &chars))
+            .collect()
+    }
+
+    /// Whether any pattern matches somewhere in `text`
+    ///
+    /// Stops at the first pattern that matches.
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        (0..self.regexes.len()).any(|index| self.matches_chars(index, text, &chars))
+    }
+
+    /// The compiled patterns, in the order they were given
+    pub fn regexes(&self) -> &[Regex] {
+        &self.regexes
+    }
+
+    /// Number of patterns in the set
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Whether the set has no patterns, so nothing matches
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    /// Whether pattern `index` matches `text`, already decoded as `chars`
+    fn matches_chars(&self, index: usize, text: &str, chars: &[char]) -> bool {
+        let regex = &self.regexes[index];
+        matcher::check_input_len(text, regex.limits()).is_ok()
+            && regex
+                .search_chars(chars, 0, regex.limits(), Window::default())
+                .is_some()
+    }
+}
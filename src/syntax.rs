@@ -2,7 +2,22 @@
 
 /// Syntax flags that control regex compilation behavior
 /// These correspond to the RE_* flags in regexpr.h:42-49
+///
+/// Dialects are usually a preset adjusted with `with`/`without` (or `|` and
+/// `-`), e.g. AWK where a leading `*` is literal instead of an error:
+///
+/// ```
+/// use tature::SyntaxFlags;
+///
+/// let flags = SyntaxFlags::AWK.without(SyntaxFlags::CONTEXT_INDEP_OPS);
+/// assert_eq!(flags, SyntaxFlags::AWK - SyntaxFlags::CONTEXT_INDEP_OPS);
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct SyntaxFlags(u32);
 
 impl SyntaxFlags {
@@ -24,6 +39,40 @@ impl SyntaxFlags {
     pub const NO_GNU_EXTENSIONS: Self = Self(128);
     /// Case insensitive matching
     pub const CASE_INSENSITIVE: Self = Self(256);
+    /// Case insensitive matching for ASCII letters only (A-Z folds to a-z)
+    pub const CASE_INSENSITIVE_ASCII: Self = Self(512);
+    /// Enable Perl-style `(?...)` group constructs, `\g<n>` recursion and
+    /// lazy quantifiers such as `*?`
+    pub const PERL_EXTENSIONS: Self = Self(1024);
+    /// `.` also matches newline
+    pub const ANY_INCLUDES_NEWLINE: Self = Self(2048);
+    /// Ignore unescaped whitespace and `#` comments in the pattern
+    pub const EXTENDED: Self = Self(4096);
+    /// Enable counted repetition `\{n,m\}`
+    pub const INTERVALS: Self = Self(8192);
+    /// With `INTERVALS`, write counted repetition as `{n,m}` without backslashes
+    pub const NO_BK_BRACES: Self = Self(16384);
+    /// Treat Unicode letters and digits as word chars for `\w`, `\b`, `\<`
+    /// and `\>`, instead of ASCII ones only
+    pub const UNICODE_WORD: Self = Self(32768);
+
+    /// Every bit with a defined meaning
+    const KNOWN_BITS: u32 = Self::NO_BK_PARENS.0
+        | Self::NO_BK_VBAR.0
+        | Self::BK_PLUS_QM.0
+        | Self::TIGHT_VBAR.0
+        | Self::NEWLINE_OR.0
+        | Self::CONTEXT_INDEP_OPS.0
+        | Self::ANSI_HEX.0
+        | Self::NO_GNU_EXTENSIONS.0
+        | Self::CASE_INSENSITIVE.0
+        | Self::CASE_INSENSITIVE_ASCII.0
+        | Self::PERL_EXTENSIONS.0
+        | Self::ANY_INCLUDES_NEWLINE.0
+        | Self::EXTENDED.0
+        | Self::INTERVALS.0
+        | Self::NO_BK_BRACES.0
+        | Self::UNICODE_WORD.0;
 
     /// Create empty flags (no bits set)
     pub const fn empty() -> Self {
@@ -44,6 +93,44 @@ impl SyntaxFlags {
     pub const fn from_bits_truncate(bits: u32) -> Self {
         Self(bits)
     }
+
+    /// Return a copy with the flags in `other` set
+    pub const fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Return a copy with the flags in `other` cleared
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl std::ops::BitOr for SyntaxFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for SyntaxFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::ops::Sub for SyntaxFlags {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.without(rhs)
+    }
+}
+
+impl std::ops::SubAssign for SyntaxFlags {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.without(rhs);
+    }
 }
 
 impl Default for SyntaxFlags {
@@ -72,6 +159,24 @@ impl SyntaxFlags {
     /// LambdaMOO-style regex syntax (context independent operations)
     pub const MOO: Self = Self::CONTEXT_INDEP_OPS;
 
+    /// Check that the flags form a supported combination
+    ///
+    /// Rejected combinations:
+    /// - bits that do not correspond to any defined flag
+    /// - `CASE_INSENSITIVE` together with `CASE_INSENSITIVE_ASCII`, which ask
+    ///   for full and ASCII-only case folding at the same time
+    pub fn validate(self) -> Result<(), &'static str> {
+        if self.0 & !Self::KNOWN_BITS != 0 {
+            return Err("unknown syntax flag bits set");
+        }
+
+        if self.contains(Self::CASE_INSENSITIVE) && self.contains(Self::CASE_INSENSITIVE_ASCII) {
+            return Err("CASE_INSENSITIVE and CASE_INSENSITIVE_ASCII are mutually exclusive");
+        }
+
+        Ok(())
+    }
+
     /// Check if parentheses need backslash quoting
     pub fn needs_backslash_parens(self) -> bool {
         !self.contains(Self::NO_BK_PARENS)
@@ -116,4 +221,39 @@ impl SyntaxFlags {
     pub fn case_insensitive(self) -> bool {
         self.contains(Self::CASE_INSENSITIVE)
     }
+
+    /// Check if Perl-style group extensions are enabled
+    pub fn perl_extensions(self) -> bool {
+        self.contains(Self::PERL_EXTENSIONS)
+    }
+
+    /// Check if `.` matches newline
+    pub fn any_includes_newline(self) -> bool {
+        self.contains(Self::ANY_INCLUDES_NEWLINE)
+    }
+
+    /// Check if whitespace and comments in the pattern are ignored
+    pub fn extended(self) -> bool {
+        self.contains(Self::EXTENDED)
+    }
+
+    /// Check if counted repetition is enabled
+    pub fn intervals(self) -> bool {
+        self.contains(Self::INTERVALS)
+    }
+
+    /// Check if interval braces need backslash quoting
+    pub fn needs_backslash_braces(self) -> bool {
+        !self.contains(Self::NO_BK_BRACES)
+    }
+
+    /// Check if ASCII-only case insensitive matching is enabled
+    pub fn case_insensitive_ascii(self) -> bool {
+        self.contains(Self::CASE_INSENSITIVE_ASCII)
+    }
+
+    /// Check if word chars include Unicode letters and digits
+    pub fn unicode_word(self) -> bool {
+        self.contains(Self::UNICODE_WORD)
+    }
 }
@@ -0,0 +1,355 @@
+//! Differential tests against a set-based reference matcher
+//!
+//! There is no second engine in the crate yet, so the reference here is a
+//! small Thompson-style simulation over the AWK subset of literals, `.`,
+//! `*`, `+`, `?`, `|`, groups and whole-pattern anchors. It tracks every
+//! reachable position instead of backtracking, so it answers "is there a
+//! match" and "where does the leftmost match start" without any notion of
+//! branch priority. Both engines must agree on those for every pattern.
+
+use std::collections::BTreeSet;
+
+use tature::{ExecLimits, Regex, SyntaxFlags};
+
+/// Reference AST for the generated subset
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Star(Box<Node>),
+    Plus(Box<Node>),
+    Optional(Box<Node>),
+}
+
+impl Node {
+    /// Render in AWK syntax
+    fn render(&self, out: &mut String) {
+        match self {
+            Node::Char(c) => out.push(*c),
+            Node::Any => out.push('.'),
+            Node::Concat(items) => {
+                for item in items {
+                    item.render(out);
+                }
+            }
+            Node::Alt(items) => {
+                out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push('|');
+                    }
+                    item.render(out);
+                }
+                out.push(')');
+            }
+            Node::Star(inner) | Node::Plus(inner) | Node::Optional(inner) => {
+                match **inner {
+                    Node::Char(_) | Node::Any | Node::Alt(_) => inner.render(out),
+                    _ => {
+                        out.push('(');
+                        inner.render(out);
+                        out.push(')');
+                    }
+                }
+                out.push(match self {
+                    Node::Star(_) => '*',
+                    Node::Plus(_) => '+',
+                    _ => '?',
+                });
+            }
+        }
+    }
+
+    /// Every position reachable after matching `self` from any of `starts`
+    fn step(&self, text: &[char], starts: &BTreeSet<usize>) -> BTreeSet<usize> {
+        match self {
+            Node::Char(c) => starts
+                .iter()
+                .filter(|&&pos| text.get(pos) == Some(c))
+                .map(|pos| pos + 1)
+                .collect(),
+            Node::Any => starts
+                .iter()
+                .filter(|&&pos| pos < text.len() && text[pos] != '\n')
+                .map(|pos| pos + 1)
+                .collect(),
+            Node::Concat(items) => items
+                .iter()
+                .fold(starts.clone(), |acc, item| item.step(text, &acc)),
+            Node::Alt(items) => items
+                .iter()
+                .flat_map(|item| item.step(text, starts))
+                .collect(),
+            Node::Star(inner) => closure(inner, text, starts.clone()),
+            Node::Plus(inner) => closure(inner, text, inner.step(text, starts)),
+            Node::Optional(inner) => {
+                let mut ends = inner.step(text, starts);
+                ends.extend(starts);
+                ends
+            }
+        }
+    }
+}
+
+/// Positions reachable by zero or more further rounds of `inner`
+fn closure(inner: &Node, text: &[char], mut reached: BTreeSet<usize>) -> BTreeSet<usize> {
+    let mut frontier = reached.clone();
+    while !frontier.is_empty() {
+        frontier = inner
+            .step(text, &frontier)
+            .into_iter()
+            .filter(|pos| !reached.contains(pos))
+            .collect();
+        reached.extend(&frontier);
+    }
+    reached
+}
+
+/// A generated pattern with optional whole-pattern anchors
+struct Pattern {
+    node: Node,
+    start_anchor: bool,
+    end_anchor: bool,
+}
+
+impl Pattern {
+    fn render(&self) -> String {
+        let mut out = String::new();
+        if self.start_anchor {
+            out.push('^');
+        }
+        self.node.render(&mut out);
+        if self.end_anchor {
+            out.push('$');
+        }
+        out
+    }
+
+    /// Start of the leftmost match according to the reference
+    fn leftmost_start(&self, text: &str) -> Option<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        let last = if self.start_anchor { 0 } else { chars.len() };
+        (0..=last).find(|&start| {
+            let ends = self.node.step(&chars, &BTreeSet::from([start]));
+            if self.end_anchor {
+                ends.contains(&chars.len())
+            } else {
+                !ends.is_empty()
+            }
+        })
+    }
+}
+
+/// Tiny deterministic generator so failures reproduce from the seed alone
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn node(&mut self, depth: usize) -> Node {
+        let choice = if depth == 0 {
+            self.below(2)
+        } else {
+            self.below(7)
+        };
+        match choice {
+            0 => Node::Char(['a', 'b', 'c'][self.below(3)]),
+            1 if self.below(4) == 0 => Node::Any,
+            1 => Node::Char(['a', 'b'][self.below(2)]),
+            2 => Node::Concat(
+                (0..2 + self.below(2))
+                    .map(|_| self.node(depth - 1))
+                    .collect(),
+            ),
+            3 => Node::Alt(
+                (0..2 + self.below(2))
+                    .map(|_| self.node(depth - 1))
+                    .collect(),
+            ),
+            4 => Node::Star(Box::new(self.node(depth - 1))),
+            5 => Node::Plus(Box::new(self.node(depth - 1))),
+            _ => Node::Optional(Box::new(self.node(depth - 1))),
+        }
+    }
+
+    fn pattern(&mut self) -> Pattern {
+        Pattern {
+            node: self.node(3),
+            start_anchor: self.below(5) == 0,
+            end_anchor: self.below(5) == 0,
+        }
+    }
+
+    fn text(&mut self) -> String {
+        (0..self.below(7))
+            .map(|_| ['a', 'b', 'c'][self.below(3)])
+            .collect()
+    }
+}
+
+/// Assert both engines agree on `pattern` against `text`
+fn check(pattern: &Pattern, text: &str) {
+    let source = pattern.render();
+    let regex = Regex::with_syntax(&source, SyntaxFlags::AWK)
+        .unwrap_or_else(|err| panic!("{source:?} failed to compile: {err}"));
+    let expected = pattern.leftmost_start(text);
+
+    assert_eq!(
+        regex.is_match(text),
+        expected.is_some(),
+        "is_match disagrees for {source:?} on {text:?}"
+    );
+    assert_eq!(
+        regex.find(text).map(|(start, _)| start),
+        expected,
+        "leftmost start disagrees for {source:?} on {text:?}"
+    );
+}
+
+/// Parse a seed pattern written in the generated subset
+fn parse(source: &str) -> Pattern {
+    let mut chars: Vec<char> = source.chars().collect();
+    let start_anchor = chars.first() == Some(&'^');
+    if start_anchor {
+        chars.remove(0);
+    }
+    let end_anchor = chars.last() == Some(&'$');
+    if end_anchor {
+        chars.pop();
+    }
+    let mut pos = 0;
+    let node = parse_alt(&chars, &mut pos);
+    assert_eq!(pos, chars.len(), "unparsed seed pattern {source:?}");
+    Pattern {
+        node,
+        start_anchor,
+        end_anchor,
+    }
+}
+
+fn parse_alt(chars: &[char], pos: &mut usize) -> Node {
+    let mut items = vec![parse_concat(chars, pos)];
+    while chars.get(*pos) == Some(&'|') {
+        *pos += 1;
+        items.push(parse_concat(chars, pos));
+    }
+    if items.len() == 1 {
+        items.pop().unwrap()
+    } else {
+        Node::Alt(items)
+    }
+}
+
+fn parse_concat(chars: &[char], pos: &mut usize) -> Node {
+    let mut items = Vec::new();
+    while let Some(&c) = chars.get(*pos) {
+        let mut atom = match c {
+            '|' | ')' => break,
+            '(' => {
+                *pos += 1;
+                let inner = parse_alt(chars, pos);
+                assert_eq!(chars.get(*pos), Some(&')'));
+                inner
+            }
+            '.' => Node::Any,
+            c => Node::Char(c),
+        };
+        *pos += 1;
+        while let Some(&op) = chars.get(*pos) {
+            atom = match op {
+                '*' => Node::Star(Box::new(atom)),
+                '+' => Node::Plus(Box::new(atom)),
+                '?' => Node::Optional(Box::new(atom)),
+                _ => break,
+            };
+            *pos += 1;
+        }
+        items.push(atom);
+    }
+    Node::Concat(items)
+}
+
+/// Patterns that have tripped up backtracking engines before
+const SEED_CORPUS: &[&str] = &[
+    "a|ab",
+    "ab|a",
+    "(a|b)*c",
+    "(a|ab)(c|bcd)",
+    "(ab|a)*c",
+    "((a|b)c)+",
+    "a?a?aa",
+    "(ab?)+c",
+    "^(a|b)*$",
+    "^a*b?$",
+    "^$",
+    "c$",
+    "^.?b",
+    "(.|a)*c",
+    "(a|b|c)+a$",
+    "(a*)*b",
+    "(a*|b)*c",
+    "(ab|a)*b",
+    "(a?)+b",
+    "(b?)+a",
+    "(a|a?|(c|c))*",
+    "(a?|b)*$",
+];
+
+const TEXTS: &[&str] = &[
+    "", "a", "b", "c", "ab", "abc", "aab", "bac", "abab", "cabc", "aaac",
+];
+
+#[test]
+fn test_equivalence_seed_corpus() {
+    for source in SEED_CORPUS {
+        let pattern = parse(source);
+        for text in TEXTS {
+            check(&pattern, text);
+        }
+    }
+}
+
+#[test]
+fn test_equivalence_generated() {
+    let mut rng = Rng(0x7a74_7572_6521);
+    for _ in 0..2_000 {
+        let pattern = rng.pattern();
+        for _ in 0..8 {
+            let text = rng.text();
+            check(&pattern, &text);
+        }
+    }
+}
+
+#[test]
+fn test_memoize_preserves_captures() {
+    let memoized = ExecLimits {
+        memoize: true,
+        ..ExecLimits::default()
+    };
+    let mut rng = Rng(0x006d_656d_6f69_7a65);
+    for _ in 0..4_000 {
+        let source = rng.pattern().render();
+        let regex = Regex::with_syntax(&source, SyntaxFlags::AWK).unwrap();
+        for _ in 0..8 {
+            let text = rng.text();
+            assert_eq!(
+                regex.captures_with_limits(&text, memoized),
+                regex.captures(&text),
+                "memoized captures differ for {source:?} on {text:?}"
+            );
+        }
+    }
+}
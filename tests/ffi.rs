@@ -0,0 +1,90 @@
+//! Tests for the C-compatible interface, exercised through the raw functions
+
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_int, CString};
+
+use tature::ffi::{
+    tature_compile, tature_compile_len, tature_free, tature_search, TatureRegisters, TATURE_OK,
+};
+use tature::{SyntaxFlags, RE_NREGS};
+
+#[test]
+fn test_ffi_compile_search_free() {
+    let pattern = CString::new("([a-z]+)=([0-9]+)").unwrap();
+    let mut err: c_int = -1;
+    let re = unsafe { tature_compile(pattern.as_ptr(), SyntaxFlags::AWK.bits(), &mut err) };
+    assert!(!re.is_null());
+    assert_eq!(err, TATURE_OK);
+
+    // "é" is two bytes, so byte offsets differ from char positions
+    let text = "é key=42";
+    let mut regs = TatureRegisters {
+        start: [0; RE_NREGS],
+        end: [0; RE_NREGS],
+    };
+    let start = unsafe {
+        tature_search(
+            re,
+            text.as_ptr() as *const _,
+            text.len() as c_int,
+            &mut regs,
+        )
+    };
+    assert_eq!(start, 3);
+    assert_eq!((regs.start[0], regs.end[0]), (3, 9));
+    assert_eq!((regs.start[1], regs.end[1]), (3, 6));
+    assert_eq!((regs.start[2], regs.end[2]), (7, 9));
+    assert_eq!((regs.start[3], regs.end[3]), (-1, -1));
+
+    let miss = "nothing here";
+    let start = unsafe {
+        tature_search(
+            re,
+            miss.as_ptr() as *const _,
+            miss.len() as c_int,
+            std::ptr::null_mut(),
+        )
+    };
+    assert_eq!(start, -1);
+
+    unsafe { tature_free(re) };
+}
+
+#[test]
+fn test_ffi_compile_error() {
+    let pattern = CString::new("[unclosed").unwrap();
+    let mut err: c_int = TATURE_OK;
+    let re = unsafe { tature_compile(pattern.as_ptr(), 0, &mut err) };
+    assert!(re.is_null());
+    assert_ne!(err, TATURE_OK);
+
+    // Freeing null is a no-op
+    unsafe { tature_free(re) };
+}
+
+#[test]
+fn test_ffi_compile_len() {
+    // The length, not a NUL, ends the pattern
+    let pattern = b"a\0b";
+    let mut err: c_int = -1;
+    let re = unsafe { tature_compile_len(pattern.as_ptr() as *const _, 3, 0, &mut err) };
+    assert!(!re.is_null());
+    assert_eq!(err, TATURE_OK);
+
+    let text = "xa\0b";
+    let start = unsafe {
+        tature_search(
+            re,
+            text.as_ptr() as *const _,
+            text.len() as c_int,
+            std::ptr::null_mut(),
+        )
+    };
+    assert_eq!(start, 1);
+    unsafe { tature_free(re) };
+
+    let re = unsafe { tature_compile_len(std::ptr::null(), 0, 0, &mut err) };
+    assert!(re.is_null());
+    assert_ne!(err, TATURE_OK);
+}
@@ -0,0 +1,82 @@
+//! Tests for `ExecLimits::max_input_chars`
+//!
+//! Kept in their own binary so the counting allocator only sees this test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tature::{ExecLimits, Regex, RegexBuilder, RegexError};
+
+/// Allocator that tallies the bytes requested through it
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_max_input_chars() {
+    let regex = Regex::new("b").unwrap();
+    let limits = ExecLimits {
+        max_input_chars: Some(1_000),
+        ..Default::default()
+    };
+    let text = "a".repeat(1_000_000);
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let result = regex.try_captures_with_limits(&text, limits);
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+
+    assert_eq!(result, Err(RegexError::InputTooLong { limit: 1_000 }));
+    // Decoding the input would have needed four bytes per char
+    assert!(allocated < text.len(), "allocated {allocated} bytes");
+    assert!(!regex.is_match_with_limits(&text, limits));
+
+    // Iterators and replacements built with the limit skip the text too
+    let limited = RegexBuilder::new("a")
+        .max_input_chars(1_000)
+        .build()
+        .unwrap();
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    assert_eq!(limited.find_iter(&text).count(), 0);
+    assert_eq!(limited.captures_iter(&text).count(), 0);
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+    assert!(allocated < text.len(), "allocated {allocated} bytes");
+
+    let limited = RegexBuilder::new("a").max_input_chars(3).build().unwrap();
+    assert_eq!(limited.find_iter("aaaaaaa").count(), 0);
+    assert_eq!(limited.find_iter_overlapping("aaaaaaa").count(), 0);
+    assert_eq!(limited.find_iter_merged("aaaaaaa").count(), 0);
+    assert_eq!(limited.split("aaaaaaa").count(), 0);
+    assert_eq!(limited.split_captures("aaaaaaa").count(), 0);
+    assert_eq!(limited.captures_iter_with_gaps("aaaaaaa").count(), 0);
+    assert!(matches!(
+        limited.replace_all("aaaaaaa", "b"),
+        Cow::Borrowed("aaaaaaa")
+    ));
+    assert_eq!(
+        limited.replace_all_with("aaaaaaa", |_, _| "b".into()),
+        "aaaaaaa"
+    );
+    assert_eq!(limited.find_iter("aaa").count(), 3);
+    assert_eq!(limited.split("aba").collect::<Vec<_>>(), ["", "b", ""]);
+    assert_eq!(limited.replace_all("aba", "c"), "cbc");
+
+    // Input at the limit is matched normally, counting chars rather than bytes
+    let text = format!("{}b", "é".repeat(999));
+    let captures = regex.try_captures_with_limits(&text, limits).unwrap();
+    assert_eq!(captures.unwrap().get(0), Some((999, 1000)));
+}
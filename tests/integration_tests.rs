@@ -2,7 +2,10 @@
 //!
 //! These tests verify compatibility with the original regexpr.c behavior
 
-use tature::{ExecLimits, Regex, SyntaxFlags};
+use tature::{
+    Boundaries, ExecLimits, MatchContext, MooMatch, Regex, RegexBuilder, RegexError, SplitItem,
+    SyntaxFlags,
+};
 
 #[test]
 fn test_basic_exact_match() {
@@ -112,9 +115,38 @@ fn test_groups_awk_syntax() {
 
 #[test]
 fn test_backreferences() {
-    let _regex = Regex::new("\\(\\([a-z]*\\)\\1\\)").unwrap();
-    // This should match something like "(hello hello)"
-    // Note: simplified test - full backreference support would need more implementation
+    let regex = Regex::new("\\(a*\\)b\\1").unwrap();
+    assert_eq!(regex.find("xaabaa"), Some((1, 6)));
+    assert_eq!(regex.find("aaba"), Some((1, 4)));
+
+    let regex = Regex::with_syntax("([a-z]+) \\1", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find("it is the the end"), Some((6, 13)));
+    assert_eq!(regex.find("the The"), None);
+
+    // Case-insensitive backreferences fold both the capture and the input
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_syntax("(?i)(a)\\1", syntax).unwrap();
+    assert_eq!(regex.find("aA"), Some((0, 2)));
+    assert_eq!(regex.find("Aa"), Some((0, 2)));
+    let regex = Regex::with_syntax(
+        "([a-z]+) \\1",
+        SyntaxFlags::AWK | SyntaxFlags::CASE_INSENSITIVE,
+    )
+    .unwrap();
+    assert_eq!(regex.find("The tHE end"), Some((0, 7)));
+
+    // A group that never participated cannot be matched again
+    let regex = Regex::with_syntax("(a)|b\\1", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find("b"), None);
+    let regex = Regex::with_syntax("(abc)\\1", SyntaxFlags::AWK).unwrap();
+    assert!(regex.is_match("abcabc"));
+    assert!(!regex.is_match("abcabd"));
+
+    // An empty capture matches the empty string
+    let regex = Regex::with_syntax("x()\\1y", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find("xy"), Some((0, 2)));
+    let regex = Regex::with_syntax("(a*)\\1", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find(""), Some((0, 0)));
 }
 
 #[test]
@@ -148,6 +180,7 @@ fn test_execution_limits() {
     let limits = ExecLimits {
         max_ticks: Some(1000),
         max_failures: 100,
+        ..Default::default()
     };
 
     // This should timeout due to excessive backtracking
@@ -186,6 +219,22 @@ fn test_hex_escapes() {
     assert!(regex.is_match("ABC")); // \x41 = 'A'
 }
 
+#[test]
+fn test_control_escapes() {
+    let regex = Regex::with_syntax("\\cA", SyntaxFlags::ANSI_HEX).unwrap();
+    assert_eq!(regex.find("ab\x01c"), Some((2, 3)));
+    assert!(!regex.is_match("A"));
+
+    // Lowercase names the same char, and escapes work inside sets too
+    let regex = Regex::with_syntax("\\ci", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(regex.is_match("tab\there"));
+    let regex = Regex::with_syntax("[\\cI\\c[]", SyntaxFlags::ANSI_HEX).unwrap();
+    assert_eq!(regex.find("a\x1b"), Some((1, 2)));
+
+    assert!(Regex::with_syntax("\\c", SyntaxFlags::ANSI_HEX).is_err());
+    assert!(Regex::with_syntax("\\cé", SyntaxFlags::ANSI_HEX).is_err());
+}
+
 #[test]
 fn test_case_insensitive_with_translate() {
     // This would require implementing the translate table feature
@@ -258,3 +307,2970 @@ fn test_utf8_support() {
     assert!(regex.is_match("ω"));
     assert!(!regex.is_match("Α")); // Different case
 }
+
+#[test]
+fn test_case_insensitive_ascii() {
+    let regex = Regex::with_syntax("abc", SyntaxFlags::CASE_INSENSITIVE_ASCII).unwrap();
+    assert!(regex.is_match("ABC"));
+    assert!(regex.is_match("aBc"));
+    assert_eq!(regex.find("xxAbC"), Some((2, 5)));
+
+    // Only ASCII letters fold: dotted capital I is not an 'i'
+    let regex = Regex::with_syntax("i", SyntaxFlags::CASE_INSENSITIVE_ASCII).unwrap();
+    assert!(regex.is_match("I"));
+    assert!(!regex.is_match("İ"));
+
+    let regex = Regex::with_syntax("İ", SyntaxFlags::CASE_INSENSITIVE_ASCII).unwrap();
+    assert!(!regex.is_match("i"));
+    assert!(regex.is_match("İ"));
+
+    // Character sets fold too
+    let regex = Regex::with_syntax("[a-c]+", SyntaxFlags::CASE_INSENSITIVE_ASCII).unwrap();
+    assert_eq!(regex.find("xxBCA"), Some((2, 5)));
+}
+
+#[test]
+fn test_find_iter() {
+    let regex = Regex::new("[0-9]+").unwrap();
+    let matches: Vec<_> = regex.find_iter("a1b22c333").collect();
+    assert_eq!(matches, vec![(1, 2), (3, 5), (6, 9)]);
+
+    // Empty matches advance so iteration terminates
+    let regex = Regex::new("x*").unwrap();
+    let matches: Vec<_> = regex.find_iter("ab").collect();
+    assert_eq!(matches, vec![(0, 0), (1, 1), (2, 2)]);
+}
+
+#[test]
+fn test_replace_all_borrows_when_unchanged() {
+    use std::borrow::Cow;
+
+    let regex = Regex::new("[0-9]").unwrap();
+    let text = "no digits here";
+    let result = regex.replace_all(text, "#");
+    match result {
+        Cow::Borrowed(borrowed) => assert!(std::ptr::eq(borrowed, text)),
+        Cow::Owned(_) => panic!("expected a borrowed result when nothing matched"),
+    }
+
+    let result = regex.replace_all("a1b2", "#");
+    assert!(matches!(result, Cow::Owned(_)));
+    assert_eq!(result, "a#b#");
+}
+
+#[test]
+fn test_translate_table_is_deterministic() {
+    // Two compilations of the same pattern must render identically, which
+    // requires the translate table to iterate in a stable order
+    let first = Regex::with_syntax("hello", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    let second = Regex::with_syntax("hello", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert_eq!(format!("{first:?}"), format!("{second:?}"));
+}
+
+#[test]
+fn test_find_iter_overlapping_step() {
+    let regex = Regex::new("aa").unwrap();
+    let text = "aaaaaa";
+
+    let all: Vec<_> = regex.find_iter_overlapping(text).collect();
+    assert_eq!(all, vec![(0, 2), (1, 3), (2, 4), (3, 5), (4, 6)]);
+
+    let sparse: Vec<_> = regex.find_iter_overlapping_step(text, 2).collect();
+    assert_eq!(sparse, vec![(0, 2), (2, 4), (4, 6)]);
+
+    let sparser: Vec<_> = regex.find_iter_overlapping_step(text, 3).collect();
+    assert_eq!(sparser, vec![(0, 2), (3, 5)]);
+}
+
+#[test]
+fn test_nul_character() {
+    // NUL written as a hex escape
+    let regex = Regex::with_syntax("a\\x00b", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(regex.is_match("a\0b"));
+    assert!(!regex.is_match("ab"));
+    assert_eq!(regex.find("xx a\0b"), Some((3, 6)));
+
+    // NUL as a literal pattern character
+    let regex = Regex::new("a\0b").unwrap();
+    assert!(regex.is_match("a\0b"));
+    assert!(!regex.is_match("a"));
+
+    // Trailing NUL must not be mistaken for the end of the pattern
+    let regex = Regex::new("a\0").unwrap();
+    assert!(regex.is_match("a\0"));
+    assert!(!regex.is_match("a"));
+
+    let regex = Regex::new("[\0]").unwrap();
+    assert!(regex.is_match("\0"));
+}
+
+#[test]
+fn test_captures_into_slice() {
+    let regex = Regex::with_syntax("([a-z]+)(x)?([0-9]+)", SyntaxFlags::AWK).unwrap();
+    let mut regs = [(0, 0); 10];
+
+    assert!(regex.captures_into_slice("id abc123", &mut regs));
+    assert_eq!(regs[0], (3, 9));
+    assert_eq!(regs[1], (3, 6));
+    assert_eq!(regs[2], (-1, -1)); // optional group did not participate
+    assert_eq!(regs[3], (6, 9));
+    assert!(regs[4..].iter().all(|&reg| reg == (-1, -1)));
+
+    assert!(!regex.captures_into_slice("no digits", &mut regs));
+    assert!(regs.iter().all(|&reg| reg == (-1, -1)));
+}
+
+#[test]
+fn test_recursion() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+
+    // Balanced parentheses via whole-pattern recursion
+    let regex = Regex::with_syntax("\\(([^()]|(?R))*\\)", syntax).unwrap();
+    assert_eq!(regex.find("(a(b)c)"), Some((0, 7)));
+    assert_eq!(regex.find("x(a(b(c))d)y"), Some((1, 11)));
+    assert_eq!(regex.find("((("), None);
+
+    // Recursion into a numbered group
+    let regex = Regex::with_syntax("<(\\[([^][]|\\g<1>)*\\])>", syntax).unwrap();
+    assert!(regex.is_match("<[a[b]c]>"));
+    assert!(!regex.is_match("<[a[b c]>"));
+
+    // Left recursion fails instead of looping forever
+    let regex = Regex::with_syntax("(?R)a", syntax).unwrap();
+    assert!(!regex.is_match("aaa"));
+
+    // Recursion into a group that does not exist is rejected
+    assert!(Regex::with_syntax("(a)\\g<2>", syntax).is_err());
+
+    // Without the flag (?R) keeps its old meaning
+    assert!(Regex::with_syntax("(?R)", SyntaxFlags::AWK).is_err());
+}
+
+#[test]
+fn test_syntax_flag_validation() {
+    for preset in [
+        SyntaxFlags::EMACS,
+        SyntaxFlags::AWK,
+        SyntaxFlags::EGREP,
+        SyntaxFlags::GREP,
+        SyntaxFlags::MOO,
+    ] {
+        assert_eq!(preset.validate(), Ok(()));
+    }
+
+    let both_cases = SyntaxFlags::CASE_INSENSITIVE | SyntaxFlags::CASE_INSENSITIVE_ASCII;
+    assert!(both_cases.validate().is_err());
+    assert!(matches!(
+        Regex::with_syntax("abc", both_cases),
+        Err(tature::RegexError::InvalidSyntax(_))
+    ));
+
+    let unknown = SyntaxFlags::from_bits_truncate(1 << 31);
+    assert!(unknown.validate().is_err());
+}
+
+#[test]
+fn test_anchored_search_skips_positions() {
+    use tature::matcher::{search_with_stats, SearchStats};
+
+    // Every position starts with `f`, so only anchoring rules positions out
+    let text = "f".repeat(10_000);
+    let anchored = Regex::new("^foo").unwrap();
+    let unanchored = Regex::new("foo").unwrap();
+    assert!(anchored.is_anchored_start());
+    assert!(!unanchored.is_anchored_start());
+
+    let mut anchored_stats = SearchStats::default();
+    let result = search_with_stats(
+        &anchored,
+        &text,
+        0,
+        text.len() as i32,
+        ExecLimits::default(),
+        &mut anchored_stats,
+    );
+    assert_eq!(result.unwrap(), -1);
+
+    let mut unanchored_stats = SearchStats::default();
+    let result = search_with_stats(
+        &unanchored,
+        &text,
+        0,
+        text.len() as i32,
+        ExecLimits::default(),
+        &mut unanchored_stats,
+    );
+    assert_eq!(result.unwrap(), -1);
+
+    assert_eq!(anchored_stats.start_attempts, 1);
+    assert_eq!(unanchored_stats.start_attempts, 10_000);
+
+    // `^` still tries every line start
+    let mut stats = SearchStats::default();
+    let text = "fx\nfoo\nbar\nfoo";
+    let result = search_with_stats(&anchored, text, 0, 14, ExecLimits::default(), &mut stats);
+    assert_eq!(result.unwrap(), 3);
+    assert_eq!(stats.start_attempts, 2);
+    assert_eq!(anchored.find("bar\nfoo"), Some((4, 7)));
+}
+
+#[test]
+fn test_replace_all_preserves_unmatched_bytes() {
+    // Decomposed "é" (e + U+0301), CJK, and emoji around the matches
+    let text = "cafe\u{301} x 日本 x 🎉\u{200d}🎉 x";
+    let regex = Regex::new("x").unwrap();
+    let replaced = regex.replace_all(text, "Ω");
+
+    let pieces: Vec<&str> = text.split('x').collect();
+    let replaced_pieces: Vec<&str> = replaced.split('Ω').collect();
+    assert_eq!(pieces.len(), replaced_pieces.len());
+    for (original, kept) in pieces.iter().zip(&replaced_pieces) {
+        assert_eq!(original.as_bytes(), kept.as_bytes());
+    }
+    assert_eq!(replaced, "cafe\u{301} Ω 日本 Ω 🎉\u{200d}🎉 Ω");
+}
+
+#[test]
+fn test_literal_runs_coalesce() {
+    use tature::opcodes::CompiledOp;
+
+    let regex = Regex::new("hello").unwrap();
+    let mut expected = vec![CompiledOp::ExactString.to_byte(), 5];
+    expected.extend_from_slice(b"hello");
+    expected.push(CompiledOp::End.to_byte());
+    assert_eq!(regex.buffer, expected);
+
+    // Coalesced runs match the same as char-by-char literals would
+    let cases = [
+        ("hello", "say hello world", Some((4, 9))),
+        ("hello", "help hell hello", Some((10, 15))),
+        ("hel*o", "heo hello", Some((0, 3))),
+        ("ab+c", "abbbc", Some((0, 5))),
+        ("colou?r", "color colour", Some((0, 5))),
+        ("foo|bar", "xbar", Some((1, 4))),
+        ("日本語", "これは日本語です", Some((3, 6))),
+        ("x(ab)y", "xaby", Some((0, 4))),
+        ("abc", "ab", None),
+    ];
+    for (pattern, text, expected) in cases {
+        let regex = Regex::with_syntax(pattern, SyntaxFlags::EGREP).unwrap();
+        assert_eq!(regex.find(text), expected, "{pattern} on {text:?}");
+    }
+
+    // Quantifiers still bind to the final char only
+    let regex = Regex::new("ab*").unwrap();
+    assert_eq!(regex.find("abbb"), Some((0, 4)));
+    assert_eq!(regex.find("a"), Some((0, 1)));
+
+    // Runs longer than a length byte can hold are split
+    let long = "a".repeat(300);
+    let regex = Regex::new(&long).unwrap();
+    assert_eq!(regex.find(&format!("b{long}")), Some((1, 301)));
+}
+
+#[test]
+fn test_rcaptures() {
+    let regex = Regex::with_syntax("([a-z])([0-9])", SyntaxFlags::AWK).unwrap();
+    let text = "a1b2c3";
+
+    let captures = regex.rcaptures(text).unwrap();
+    assert_eq!(captures.get(0), Some((4, 6)));
+    assert_eq!(captures.get(1), Some((4, 5)));
+    assert_eq!(captures.get(2), Some((5, 6)));
+
+    let forward = regex.captures(text).unwrap();
+    assert_eq!(forward.get(1), Some((0, 1)));
+
+    assert!(regex.rcaptures("123").is_none());
+}
+
+#[test]
+fn test_syntax_flags_without() {
+    // AWK rejects a leading `*`
+    assert!(Regex::with_syntax("*a", SyntaxFlags::AWK).is_err());
+
+    let relaxed = SyntaxFlags::AWK.without(SyntaxFlags::CONTEXT_INDEP_OPS);
+    assert!(!relaxed.contains(SyntaxFlags::CONTEXT_INDEP_OPS));
+    assert!(relaxed.contains(SyntaxFlags::NO_BK_PARENS));
+    assert_eq!(
+        relaxed.with(SyntaxFlags::CONTEXT_INDEP_OPS),
+        SyntaxFlags::AWK
+    );
+
+    let mut subtracted = SyntaxFlags::AWK;
+    subtracted -= SyntaxFlags::CONTEXT_INDEP_OPS;
+    assert_eq!(subtracted, relaxed);
+
+    // Without context independence the leading `*` is a literal
+    let regex = Regex::with_syntax("*a", relaxed).unwrap();
+    assert_eq!(regex.find("x*a"), Some((1, 3)));
+    assert_eq!(regex.find("a"), None);
+}
+
+#[test]
+fn test_dangling_escape() {
+    assert!(matches!(
+        Regex::new("a\\"),
+        Err(RegexError::DanglingEscape { position: 1 })
+    ));
+    assert!(matches!(
+        Regex::new("日本\\"),
+        Err(RegexError::DanglingEscape { position: 2 })
+    ));
+
+    // Running out of pattern elsewhere is still a premature end
+    assert!(matches!(Regex::new("[ab"), Err(RegexError::PrematureEnd)));
+    assert!(matches!(
+        Regex::with_syntax("\\x4", SyntaxFlags::ANSI_HEX),
+        Err(RegexError::PrematureEnd)
+    ));
+
+    // Braces carry no meaning in any mode, so `a{` is a literal
+    assert_eq!(Regex::new("a{").unwrap().find("xa{"), Some((1, 3)));
+}
+
+#[test]
+fn test_inline_flags() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+
+    let regex = Regex::with_syntax("(?s)a.b", syntax).unwrap();
+    assert_eq!(regex.find("a\nb"), Some((0, 3)));
+    let regex = Regex::with_syntax("a.b", syntax).unwrap();
+    assert_eq!(regex.find("a\nb"), None);
+
+    let regex = Regex::with_syntax("(?m)^b", syntax).unwrap();
+    assert_eq!(regex.find("a\nb"), Some((2, 3)));
+
+    let regex = Regex::with_syntax("(?i)hello", syntax).unwrap();
+    assert_eq!(regex.find("say HeLLo"), Some((4, 9)));
+
+    let regex = Regex::with_syntax("(?ism)^A.B$", syntax).unwrap();
+    assert_eq!(regex.find("x\na\nb"), Some((2, 5)));
+    let regex = Regex::with_syntax("(?i)(?s)A.B", syntax).unwrap();
+    assert_eq!(regex.find("a\nb"), Some((0, 3)));
+
+    // Flags cover the whole pattern, so they must lead it
+    assert!(Regex::with_syntax("a(?i)b", syntax).is_err());
+    assert!(Regex::with_syntax("(?q)a", syntax).is_err());
+    assert!(Regex::with_syntax("(?i", syntax).is_err());
+}
+
+#[test]
+fn test_captures_named() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_syntax("(?P<key>[a-z]+)=([0-9]+)", syntax).unwrap();
+    assert_eq!(regex.group_name(1), Some("key"));
+    assert_eq!(regex.group_name(2), None);
+
+    let named = regex.captures_named("set x=42").unwrap();
+    assert_eq!(
+        named,
+        vec![
+            (None, Some((4, 8))),
+            (Some("key".to_string()), Some((4, 5))),
+            (None, Some((6, 8))),
+        ]
+    );
+
+    let regex = Regex::with_syntax("(?<word>a)|(b)", syntax).unwrap();
+    assert_eq!(
+        regex.captures_named("b").unwrap(),
+        vec![
+            (None, Some((0, 1))),
+            (Some("word".to_string()), None),
+            (None, Some((0, 1))),
+        ]
+    );
+    assert!(regex.captures_named("c").is_none());
+
+    assert!(Regex::with_syntax("(?P<1x>a)", syntax).is_err());
+    assert!(Regex::with_syntax("(?P<a>x)(?P<a>y)", syntax).is_err());
+}
+
+#[test]
+fn test_end_anchored_search_skips_positions() {
+    use tature::matcher::{search_with_stats, SearchStats};
+
+    let attempts = |regex: &Regex, text: &str| {
+        let mut stats = SearchStats::default();
+        let result = search_with_stats(
+            regex,
+            text,
+            0,
+            text.chars().count() as i32,
+            ExecLimits::default(),
+            &mut stats,
+        )
+        .unwrap();
+        (result, stats.start_attempts)
+    };
+
+    let text = format!("{}foo", "f".repeat(10_000));
+    let anchored = Regex::new("foo$").unwrap();
+    let unanchored = Regex::new("foo").unwrap();
+    assert!(anchored.is_anchored_end());
+    assert!(!unanchored.is_anchored_end());
+
+    let (anchored_pos, anchored_attempts) = attempts(&anchored, &text);
+    let (unanchored_pos, unanchored_attempts) = attempts(&unanchored, &text);
+    assert_eq!(anchored_pos, 10_000);
+    assert_eq!(unanchored_pos, 10_000);
+    assert_eq!(anchored_attempts, 1);
+    assert_eq!(unanchored_attempts, 10_001);
+
+    // `$` also matches before each newline
+    assert_eq!(anchored.find("foo\nxfoo"), Some((0, 3)));
+    let optional = Regex::with_syntax("fo?o$", SyntaxFlags::AWK).unwrap();
+    assert!(optional.is_anchored_end());
+    assert_eq!(optional.find("fooo\nxfo\nx"), Some((6, 8)));
+    assert!(Regex::new("foo\\'").unwrap().is_anchored_end());
+
+    // Each search looks only as far as the next newline, so iterating over
+    // many lines stays linear, and searching backwards sees every line end
+    let lines = Regex::with_syntax("x$", SyntaxFlags::AWK).unwrap();
+    let text = "ax\n".repeat(20_000);
+    assert_eq!(lines.find_iter(&text).count(), 20_000);
+    let text = "ax\nbx\nxc";
+    let backwards = search_with_stats(
+        &lines,
+        text,
+        8,
+        -8,
+        ExecLimits::default(),
+        &mut SearchStats::default(),
+    );
+    assert_eq!(backwards, Ok(4));
+
+    // An alternative without the anchor, or an unbounded length, disables it
+    assert!(!Regex::with_syntax("a|b$", SyntaxFlags::AWK)
+        .unwrap()
+        .is_anchored_end());
+    let unbounded = Regex::new("x*$").unwrap();
+    assert!(unbounded.is_anchored_end());
+    assert_eq!(
+        unbounded.find("abxx"),
+        Some((0, 0)).filter(|_| false).or(Some((2, 4)))
+    );
+}
+
+#[test]
+fn test_structured_compile_errors() {
+    use tature::CompileError;
+
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+
+    let error = Regex::with_syntax("(?Q)", syntax).unwrap_err();
+    assert_eq!(
+        error,
+        RegexError::CompileError(CompileError::UnknownGroupExtension('Q'))
+    );
+    assert_eq!(
+        error.to_string(),
+        "Regex compilation error: Unknown group extension '(?Q'"
+    );
+
+    let error = Regex::with_syntax("(a)\\g<1x", syntax).unwrap_err();
+    assert_eq!(
+        error,
+        RegexError::CompileError(CompileError::ExpectedChar {
+            expected: '>',
+            position: 7
+        })
+    );
+
+    let error = Regex::with_syntax("(?R]", syntax).unwrap_err();
+    assert_eq!(
+        error,
+        RegexError::CompileError(CompileError::ExpectedChar {
+            expected: ')',
+            position: 3
+        })
+    );
+    assert_eq!(
+        error.to_string(),
+        "Regex compilation error: Expected ')' at position 3"
+    );
+
+    assert!(matches!(
+        Regex::with_syntax("(?P<a>x)(?P<a>y)", syntax),
+        Err(RegexError::CompileError(CompileError::DuplicateGroupName(name))) if name == "a"
+    ));
+    assert!(matches!(
+        Regex::with_syntax("x(?s)", syntax),
+        Err(RegexError::CompileError(CompileError::MisplacedInlineFlags))
+    ));
+}
+
+#[test]
+fn test_captures_iter_limited() {
+    let regex = Regex::with_syntax("([a-z])([0-9])", SyntaxFlags::AWK).unwrap();
+    let text = "a1b2c3";
+
+    let all: Vec<_> = regex
+        .captures_iter(text)
+        .map(|captures| captures.get(1).unwrap())
+        .collect();
+    assert_eq!(all, vec![(0, 1), (2, 3), (4, 5)]);
+
+    // Empty matches everywhere would otherwise yield one per char
+    let empty = Regex::new("x*").unwrap();
+    let text = "y".repeat(10_000);
+    assert_eq!(empty.captures_iter_limited(&text, 5).count(), 5);
+    assert_eq!(empty.captures_iter_limited(&text, 0).count(), 0);
+    assert_eq!(regex.captures_iter_limited("a1b2c3", 10).count(), 3);
+}
+
+#[test]
+fn test_captures_iter_subgroups() {
+    let regex = Regex::with_syntax("([a-z]+)([0-9]+)", SyntaxFlags::AWK).unwrap();
+    let all: Vec<_> = regex
+        .captures_iter("ab12 cd34")
+        .map(|c| c.to_vec())
+        .collect();
+    assert_eq!(
+        all,
+        [
+            [Some((0, 4)), Some((0, 2)), Some((2, 4))],
+            [Some((5, 9)), Some((5, 7)), Some((7, 9))],
+        ]
+    );
+
+    // Empty matches advance one char at a time, ending past the last char
+    let empty = Regex::new("x*").unwrap();
+    let spans: Vec<_> = empty.captures_iter("ab").map(|c| c.get(0)).collect();
+    assert_eq!(spans, [Some((0, 0)), Some((1, 1)), Some((2, 2))]);
+}
+
+#[test]
+fn test_extended_patterns() {
+    let compact = Regex::with_syntax("([a-z]+)=([0-9]+|none)", SyntaxFlags::AWK).unwrap();
+    let extended = Regex::with_syntax(
+        "
+        ([a-z]+)        # key
+        =
+        ( [0-9]+ | none ) # value
+        ",
+        SyntaxFlags::AWK | SyntaxFlags::EXTENDED,
+    )
+    .unwrap();
+
+    for text in ["key=42", "x = 1", "flag=none", "a=b"] {
+        assert_eq!(extended.captures(text), compact.captures(text), "{text:?}");
+    }
+
+    // Escaped whitespace and whitespace in sets are literal
+    let regex = Regex::with_syntax("a\\ b [ ]c", SyntaxFlags::AWK | SyntaxFlags::EXTENDED).unwrap();
+    assert_eq!(regex.find("xa b c"), Some((1, 6)));
+    assert_eq!(regex.find("abc"), None);
+
+    // The inline form enables the same mode
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_syntax("(?x) f o+ $  # trailing", syntax).unwrap();
+    assert_eq!(regex.find("a foo"), Some((2, 5)));
+}
+
+#[test]
+fn test_backtracking_resets_captures() {
+    let regex = Regex::with_syntax("(a)(b)|(c)(d)", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures("cd").unwrap();
+    assert_eq!(captures.get(1), None);
+    assert_eq!(captures.get(2), None);
+    assert_eq!(captures.get(3), Some((0, 1)));
+    assert_eq!(captures.get(4), Some((1, 2)));
+
+    // Group 2 completes inside the abandoned branch before `b` fails
+    let regex = Regex::with_syntax("((a)b|ac)", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures("ac").unwrap();
+    assert_eq!(captures.get(1), Some((0, 2)));
+    assert_eq!(captures.get(2), None);
+
+    // Completed loop iterations keep their captures
+    let regex = Regex::with_syntax("(a|b)*c", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures("abac").unwrap();
+    assert_eq!(captures.get(0), Some((0, 4)));
+    assert_eq!(captures.get(1), Some((2, 3)));
+}
+
+#[test]
+fn test_group_count() {
+    let syntax = SyntaxFlags::AWK;
+    assert_eq!(Regex::with_syntax("ab+c", syntax).unwrap().group_count(), 0);
+    assert_eq!(
+        Regex::with_syntax("(a)(b(c))", syntax)
+            .unwrap()
+            .group_count(),
+        3
+    );
+
+    // Group-less patterns report the same whole match, and nothing else
+    for (pattern, text) in [("ab+c", "xabbc"), ("[0-9]+", "ab123"), ("x|y", "zzy")] {
+        let plain = Regex::with_syntax(pattern, syntax).unwrap();
+        let grouped = Regex::with_syntax(&format!("({pattern})"), syntax).unwrap();
+        let captures = plain.captures(text).unwrap();
+        assert_eq!(captures.get(0), grouped.captures(text).unwrap().get(0));
+        assert_eq!(captures.get(1), None);
+    }
+}
+
+#[test]
+fn test_one_shot_functions() {
+    assert_eq!(tature::is_match("a.c", "abc"), Ok(true));
+    assert_eq!(tature::is_match("a.c", "ac"), Ok(false));
+    assert_eq!(tature::find("b+", "abbc"), Ok(Some((1, 3))));
+    assert_eq!(tature::find("z", "abc"), Ok(None));
+    assert!(matches!(
+        tature::is_match("a\\", "a"),
+        Err(RegexError::DanglingEscape { .. })
+    ));
+}
+
+#[test]
+fn test_virtual_boundaries() {
+    use tature::Boundaries;
+
+    let regex = Regex::new("\\bword").unwrap();
+    let chunk_edge = Boundaries {
+        bol_at_start: false,
+        eol_at_end: true,
+    };
+
+    // The previous chunk may have ended mid-word, so no boundary at its edge
+    assert!(regex.captures_with_boundaries("word", chunk_edge).is_none());
+    assert!(regex
+        .captures_with_boundaries("word", Boundaries::default())
+        .is_some());
+    assert_eq!(
+        regex
+            .captures_with_boundaries("a word", chunk_edge)
+            .and_then(|c| c.get(0)),
+        Some((2, 6))
+    );
+
+    // `^` and `$` only match at real edges
+    let anchored = Regex::new("^ab$").unwrap();
+    assert!(anchored
+        .captures_with_boundaries("ab", chunk_edge)
+        .is_none());
+    let open_end = Boundaries {
+        bol_at_start: true,
+        eol_at_end: false,
+    };
+    assert!(anchored.captures_with_boundaries("ab", open_end).is_none());
+    assert!(anchored
+        .captures_with_boundaries("x\nab\n", open_end)
+        .is_some());
+
+    // A range sees the real text around it
+    assert!(regex.captures_in_range("sword", 1..5).is_none());
+    assert_eq!(
+        regex
+            .captures_in_range(" word", 1..5)
+            .and_then(|c| c.get(0)),
+        Some((1, 5))
+    );
+    let tail = Regex::new("wor.").unwrap();
+    assert_eq!(
+        tail.captures_in_range("words", 0..4).and_then(|c| c.get(0)),
+        Some((0, 4))
+    );
+    assert!(tail.captures_in_range("words", 0..3).is_none());
+    let end = Regex::new("d$").unwrap();
+    assert!(end.captures_in_range("words", 0..4).is_none());
+}
+
+#[test]
+fn test_captures_debug() {
+    let regex = Regex::with_syntax("([a-z]+) (x)?([a-z]+)", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures("hello all").unwrap();
+    let debug = format!("{captures:?}");
+    assert_eq!(debug, "Captures { 0: (0, 9), 1: (0, 5), 3: (6, 9) }");
+    assert!(!debug.contains("None"));
+    assert_eq!(
+        captures.to_vec(),
+        [Some((0, 9)), Some((0, 5)), None, Some((6, 9))]
+    );
+    assert_eq!(regex.group_count() + 1, captures.to_vec().len());
+}
+
+#[test]
+fn test_backreference_validation() {
+    let syntax = SyntaxFlags::AWK;
+    assert_eq!(
+        Regex::with_syntax("\\1abc", syntax).unwrap_err(),
+        RegexError::BadBackReference
+    );
+    // Forward references are invalid too
+    assert_eq!(
+        Regex::with_syntax("\\1(a)", syntax).unwrap_err(),
+        RegexError::BadBackReference
+    );
+    assert_eq!(
+        Regex::with_syntax("(a)\\2", syntax).unwrap_err(),
+        RegexError::BadBackReference
+    );
+
+    assert!(Regex::with_syntax("(a)\\1", syntax).is_ok());
+    // A group may be referenced once opened, even before it closes
+    assert!(Regex::with_syntax("(a\\1)", syntax).is_ok());
+    assert!(Regex::new("\\(a\\)\\1").is_ok());
+
+    let ansi = SyntaxFlags::AWK | SyntaxFlags::ANSI_HEX;
+    assert_eq!(
+        Regex::with_syntax("(a)\\v12", ansi).unwrap_err(),
+        RegexError::BadBackReference
+    );
+}
+
+#[test]
+fn test_first_chars() {
+    use std::collections::BTreeSet;
+    use tature::FirstSet;
+
+    let regex = Regex::with_syntax("(foo|bar)", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        regex.first_chars(),
+        &FirstSet::Chars(BTreeSet::from(['b', 'f']))
+    );
+    assert_eq!(Regex::new(".x").unwrap().first_chars(), &FirstSet::Any);
+
+    // Optional prefixes contribute their own first chars and what follows
+    let regex = Regex::with_syntax("a?[0-9]x", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        regex.first_chars(),
+        &FirstSet::Ranges(vec![('0', '9'), ('a', 'a')])
+    );
+
+    // Patterns that can match empty, or start with a negated set, start anywhere
+    assert_eq!(Regex::new("a*").unwrap().first_chars(), &FirstSet::Any);
+    assert_eq!(Regex::new("[^a]b").unwrap().first_chars(), &FirstSet::Any);
+
+    // Case folding includes both cases
+    let regex = Regex::with_syntax("hello", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert_eq!(
+        regex.first_chars(),
+        &FirstSet::Chars(BTreeSet::from(['H', 'h']))
+    );
+    assert_eq!(regex.find("say HELLO"), Some((4, 9)));
+
+    // The pre-filter leaves search results unchanged
+    let regex = Regex::with_syntax("(foo|bar)+", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find("xxbarfooy"), Some((2, 8)));
+}
+
+#[test]
+fn test_counted_repetition() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES;
+
+    // The body is compiled once and run under a counter, not unrolled
+    let regex = Regex::with_syntax("^a{1000}$", syntax).unwrap();
+    assert!(regex.buffer.len() < 32);
+    assert!(regex.is_match(&"a".repeat(1000)));
+    assert!(!regex.is_match(&"a".repeat(999)));
+    assert!(!regex.is_match(&"a".repeat(1001)));
+
+    let regex = Regex::with_syntax("x(ab){2,3}y", syntax).unwrap();
+    assert!(!regex.is_match("xaby"));
+    assert!(regex.is_match("xababy"));
+    assert!(regex.is_match("xabababy"));
+    assert!(!regex.is_match("xababababy"));
+    let caps = regex.captures("-xabababy").unwrap();
+    assert_eq!(caps.get(0), Some((1, 9)));
+    assert_eq!(caps.get(1), Some((6, 8)));
+
+    // Open-ended and optional counts, backtracking out of greedy iterations
+    let regex = Regex::with_syntax("a{2,}ab", syntax).unwrap();
+    assert_eq!(regex.find("aaaab"), Some((0, 5)));
+    assert_eq!(regex.find("aab"), None);
+    let regex = Regex::with_syntax("ba{0,2}c", syntax).unwrap();
+    assert!(regex.is_match("bc"));
+    assert!(regex.is_match("baac"));
+    assert!(!regex.is_match("baaac"));
+
+    // An empty body cannot loop forever
+    let regex = Regex::with_syntax("(a*){3,}b", syntax).unwrap();
+    assert_eq!(regex.find("xb"), Some((1, 2)));
+
+    // Without NO_BK_BRACES the braces are quoted, and plain braces are literal
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::INTERVALS;
+    let regex = Regex::with_syntax("a\\{2\\}", syntax).unwrap();
+    assert_eq!(regex.find("baaa"), Some((1, 3)));
+    assert!(Regex::with_syntax("a{2}", syntax).unwrap().is_match("a{2}"));
+
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES;
+    for pattern in ["a{", "a{x}", "a{3,2}", "a{2", "a{70000}"] {
+        assert!(Regex::with_syntax(pattern, syntax).is_err(), "{pattern}");
+    }
+}
+
+#[test]
+fn test_split_captures() {
+    let regex = Regex::with_syntax("([0-9]+)", SyntaxFlags::AWK).unwrap();
+    let text = "a1b22c";
+
+    let items: Vec<String> = regex
+        .split_captures(text)
+        .map(|item| match item {
+            SplitItem::Field(field) => format!("field {field}"),
+            SplitItem::Separator(captures) => {
+                let (start, end) = captures.get(1).unwrap();
+                format!("separator {}", &text[start..end])
+            }
+        })
+        .collect();
+    assert_eq!(
+        items,
+        [
+            "field a",
+            "separator 1",
+            "field b",
+            "separator 22",
+            "field c"
+        ]
+    );
+
+    // Separators at the edges leave empty fields, as with Python's re.split
+    let fields: Vec<_> = regex
+        .split_captures("1é2")
+        .filter_map(|item| match item {
+            SplitItem::Field(field) => Some(field),
+            SplitItem::Separator(_) => None,
+        })
+        .collect();
+    assert_eq!(fields, ["", "é", ""]);
+}
+
+#[test]
+fn test_matches_everything() {
+    let regex = Regex::new(".*").unwrap();
+    assert!(regex.matches_everything());
+
+    // A single tick would time the matcher out, so a match proves it never ran
+    let limits = ExecLimits {
+        max_ticks: Some(1),
+        ..Default::default()
+    };
+    let text = "x".repeat(100_000);
+    assert!(regex.is_match_with_limits(&text, limits));
+    assert_eq!(regex.find_with_limits(&text, limits), Some((0, 100_000)));
+    assert!(regex.is_match(""));
+
+    // The short cut agrees with the matcher on where `.` stops
+    assert_eq!(regex.find("ab\ncd"), Some((0, 2)));
+    assert_eq!(regex.captures("ab\ncd").unwrap().get(0), Some((0, 2)));
+    let dotall = Regex::with_syntax(".*", SyntaxFlags::ANY_INCLUDES_NEWLINE).unwrap();
+    assert!(dotall.matches_everything());
+    assert_eq!(dotall.find("ab\ncd"), Some((0, 5)));
+
+    // Input limits still apply, before the short cut is taken
+    let limits = ExecLimits {
+        max_input_chars: Some(10),
+        ..Default::default()
+    };
+    assert!(!regex.is_match_with_limits(&text, limits));
+    assert_eq!(regex.find_with_limits(&text, limits), None);
+    assert_eq!(regex.find_with_limits("short", limits), Some((0, 5)));
+    let limited = RegexBuilder::new(".*").max_input_chars(10).build().unwrap();
+    assert!(limited.matches_everything());
+    assert!(!limited.is_match(&text));
+    assert_eq!(limited.find(&text), None);
+
+    for pattern in ["a.*", ".*a", ".", "\\(.*\\)"] {
+        assert!(
+            !Regex::new(pattern).unwrap().matches_everything(),
+            "{pattern}"
+        );
+    }
+}
+
+#[test]
+fn test_alternate() {
+    let foo = Regex::new("foo").unwrap();
+    let bar = Regex::new("bar").unwrap();
+    let either = foo.alternate(&bar).unwrap();
+    assert!(either.is_match("foo"));
+    assert!(either.is_match("bar"));
+    assert!(!either.is_match("baz"));
+    assert_eq!(either.find("xxbarfoo"), Some((2, 5)));
+
+    // Groups of the second regex follow those of the first, back-references included
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let key = Regex::with_syntax("(?P<key>[a-z]+)=", syntax).unwrap();
+    let pair = Regex::with_syntax("([0-9])(?P<digit>[0-9])\\2", syntax).unwrap();
+    let either = key.alternate(&pair).unwrap();
+    assert_eq!(either.group_count(), 3);
+    assert_eq!(either.group_name(3), Some("digit"));
+    let caps = either.captures("x 1222").unwrap();
+    assert_eq!(
+        caps.to_vec(),
+        [Some((2, 5)), None, Some((2, 3)), Some((3, 4))]
+    );
+    assert_eq!(either.captures("ab=").unwrap().get(1), Some((0, 2)));
+
+    // Chained alternation keeps precedence and the analysis stays sound
+    let chained = Regex::new("a")
+        .unwrap()
+        .alternate(&Regex::new("b").unwrap())
+        .unwrap()
+        .alternate(&Regex::new("c").unwrap())
+        .unwrap();
+    assert_eq!(chained.find("xxc"), Some((2, 3)));
+    assert!(!chained.is_anchored_start());
+
+    assert!(key.alternate(&key).is_err());
+    assert!(foo.alternate(&key).is_err());
+}
+
+#[test]
+fn test_split() {
+    let splitter = Regex::whitespace_splitter();
+    assert!(std::ptr::eq(splitter, Regex::whitespace_splitter()));
+
+    let fields: Vec<_> = splitter.split_trimmed("  a  b c ").collect();
+    assert_eq!(fields, ["a", "b", "c"]);
+    let fields: Vec<_> = splitter.split_trimmed("a\tb\r\nc").collect();
+    assert_eq!(fields, ["a", "b", "c"]);
+    assert_eq!(splitter.split_trimmed("").count(), 0);
+    assert_eq!(splitter.split_trimmed(" \n ").count(), 0);
+
+    // Plain split keeps the empty fields at the ends
+    let fields: Vec<_> = splitter.split("  a  b c ").collect();
+    assert_eq!(fields, ["", "a", "b", "c", ""]);
+
+    // Only the ends are trimmed; empty fields inside are kept
+    let comma = Regex::new(",").unwrap();
+    let fields: Vec<_> = comma.split_trimmed(",a,,b,").collect();
+    assert_eq!(fields, ["a", "", "b"]);
+}
+
+#[test]
+fn test_zero_max_failures() {
+    let limits = ExecLimits {
+        max_failures: 0,
+        ..Default::default()
+    };
+
+    // Patterns that never push a failure point still match, and mismatch cleanly
+    let linear = Regex::new("abc").unwrap();
+    assert_eq!(linear.find_with_limits("xxabc", limits), Some((2, 5)));
+    let captures = linear.try_captures_with_limits("xxabc", limits).unwrap();
+    assert_eq!(captures.unwrap().get(0), Some((2, 5)));
+    assert_eq!(linear.try_captures_with_limits("abd", limits), Ok(None));
+
+    // Backtracking is refused, which is reported apart from a mismatch
+    let star = Regex::new("a*b").unwrap();
+    assert_eq!(star.find_with_limits("aab", limits), None);
+    assert_eq!(
+        star.try_captures_with_limits("aab", limits),
+        Err(RegexError::ExecutionError)
+    );
+    assert_eq!(star.find("aab"), Some((0, 3)));
+
+    // The other limits are reported the same way
+    let limits = ExecLimits {
+        max_ticks: Some(10),
+        ..Default::default()
+    };
+    assert_eq!(
+        Regex::new("a*c")
+            .unwrap()
+            .try_captures_with_limits(&"a".repeat(100), limits),
+        Err(RegexError::Timeout)
+    );
+}
+
+#[test]
+fn test_anchor_all_alternatives() {
+    // `^` applies to the first alternative only
+    let partial = Regex::with_syntax("^foo|bar", SyntaxFlags::AWK).unwrap();
+    assert_eq!(partial.find("xfoo xbar"), Some((6, 9)));
+
+    let regex = Regex::with_syntax("foo|bar", SyntaxFlags::AWK)
+        .unwrap()
+        .anchor_all_alternatives()
+        .unwrap();
+    assert!(regex.is_match("foo"));
+    assert!(regex.is_match("bar"));
+    assert!(!regex.is_match("xfoo"));
+    assert!(!regex.is_match("xbar"));
+    assert_eq!(regex.find("xfoo\nbar"), Some((5, 8)));
+
+    // Branches with their own structure, or already anchored, are kept intact
+    let regex = Regex::with_syntax("^(x|y)z|w*v|u", SyntaxFlags::AWK)
+        .unwrap()
+        .anchor_all_alternatives()
+        .unwrap();
+    assert_eq!(
+        regex.captures("a\nyz").unwrap().to_vec(),
+        [Some((2, 4)), Some((2, 3))]
+    );
+    assert_eq!(regex.find("awwv\nwwv"), Some((5, 8)));
+    assert_eq!(regex.find("vu\nu"), Some((0, 1)));
+    assert!(!regex.is_match("au"));
+
+    // A single alternative is simply anchored
+    let regex = Regex::new("a*b")
+        .unwrap()
+        .anchor_all_alternatives()
+        .unwrap();
+    assert!(regex.is_anchored_start());
+    assert!(!regex.is_match("cab"));
+    assert!(regex.is_match("aab"));
+
+    // Alternatives holding nested groups and loops move whole
+    let regex = Regex::with_syntax("(a|b)*c|d(e(f|g)+)?", SyntaxFlags::AWK)
+        .unwrap()
+        .anchor_all_alternatives()
+        .unwrap();
+    assert_eq!(
+        regex.find(
+            "x
+abac"
+        ),
+        Some((2, 6))
+    );
+    assert_eq!(
+        regex.find(
+            "xc
+defgf"
+        ),
+        Some((3, 8))
+    );
+    assert_eq!(
+        regex.captures("defg").unwrap().to_vec(),
+        [Some((0, 4)), None, Some((1, 4)), Some((3, 4))]
+    );
+    assert!(!regex.is_match("xd"));
+
+    // An optional group is not an alternation, even when the bytes before
+    // its end read like a jump to the end: here the tab and the register of
+    // `end_memory` decode as `jump` with a displacement of exactly the tail
+    let tail = "b".repeat(259);
+    let regex = Regex::with_syntax(&format!("(a\t)?{tail}"), SyntaxFlags::AWK)
+        .unwrap()
+        .anchor_all_alternatives()
+        .unwrap();
+    assert!(regex.is_match(&format!("a\t{tail}")));
+    assert!(regex.is_match(&format!("x\n{tail}")));
+    assert!(!regex.is_match(&format!("x{tail}")));
+    assert!(!regex.disassemble().contains("match_memory"));
+}
+
+#[test]
+fn test_from_bytes_with_len() {
+    // The slice is the whole pattern, NUL included
+    let regex = Regex::from_bytes_with_len(b"a\0b", SyntaxFlags::default()).unwrap();
+    assert_eq!(regex.find("xa\0b"), Some((1, 4)));
+    assert!(!regex.is_match("ab"));
+
+    // Bytes are Latin-1, so non-UTF-8 patterns are accepted
+    let regex = Regex::from_bytes_with_len(&[b'c', 0xE9], SyntaxFlags::default()).unwrap();
+    assert_eq!(regex.find("accé"), Some((2, 4)));
+}
+
+#[test]
+fn test_captures_iter_with_gaps() {
+    let regex = Regex::new("[0-9]").unwrap();
+    let items: Vec<_> = regex
+        .captures_iter_with_gaps("a1b2")
+        .map(|(gap, captures)| (gap, captures.and_then(|captures| captures.get(0))))
+        .collect();
+    assert_eq!(
+        items,
+        [("a", Some((1, 2))), ("b", Some((3, 4))), ("", None)]
+    );
+
+    // Gaps and matches rebuild the text, here with each digit doubled
+    let text = "x7yy42z";
+    let mut rebuilt = String::new();
+    for (gap, captures) in regex.captures_iter_with_gaps(text) {
+        rebuilt.push_str(gap);
+        if let Some((start, end)) = captures.and_then(|captures| captures.get(0)) {
+            rebuilt.push_str(&text[start..end].repeat(2));
+        }
+    }
+    assert_eq!(rebuilt, "x77yy4422z");
+
+    let items: Vec<_> = regex.captures_iter_with_gaps("none").collect();
+    assert_eq!(items, [("none", None)]);
+}
+
+#[test]
+fn test_point_escape_rejected() {
+    use tature::CompileError;
+
+    // Emacs `\=` matches at point, which has no equivalent here, so it is an
+    // error rather than a silent literal `=`
+    for syntax in [SyntaxFlags::EMACS, SyntaxFlags::MOO, SyntaxFlags::AWK] {
+        assert_eq!(
+            Regex::with_syntax("a\\=b", syntax).unwrap_err(),
+            RegexError::CompileError(CompileError::UnsupportedPoint)
+        );
+    }
+    assert_eq!(
+        CompileError::UnsupportedPoint.to_string(),
+        "\\= (match at point) is not supported"
+    );
+
+    // Without GNU extensions the escape is an ordinary quoted `=`
+    let regex = Regex::with_syntax("a\\=b", SyntaxFlags::NO_GNU_EXTENSIONS).unwrap();
+    assert!(regex.is_match("a=b"));
+}
+
+#[test]
+fn test_captures_at_window() {
+    let regex = Regex::new("ab*").unwrap();
+    let text = "xxxxxxabbb";
+
+    assert_eq!(
+        regex.captures_at(text, 2, None).unwrap().get(0),
+        Some((6, 10))
+    );
+    // Starts up to 4 chars past 2 are tried; the match may run past that
+    assert_eq!(
+        regex.captures_at(text, 2, Some(4)).unwrap().get(0),
+        Some((6, 10))
+    );
+    assert!(regex.captures_at(text, 2, Some(3)).is_none());
+    assert!(regex.captures_at(text, 7, None).is_none());
+    assert_eq!(
+        regex.captures_at(text, 6, Some(0)).unwrap().get(0),
+        Some((6, 10))
+    );
+}
+
+#[test]
+fn test_buffer_anchors() {
+    let regex = Regex::new("\\`foo").unwrap();
+    assert_eq!(regex.find("foo\nfoo"), Some((0, 3)));
+    assert_eq!(regex.find("xfoo"), None);
+    assert_eq!(regex.find("x\nfoo"), None);
+
+    let regex = Regex::new("foo\\'").unwrap();
+    assert_eq!(regex.find("foo\nfoo"), Some((4, 7)));
+    assert_eq!(regex.find("foo\n"), None);
+    assert_eq!(regex.find("foox"), None);
+
+    // `^` and `$` by contrast match around the newlines
+    let regex = Regex::new("^foo$").unwrap();
+    assert_eq!(regex.find("x\nfoo\n"), Some((2, 5)));
+
+    // Chunk edges that are not input edges are not buffer edges either
+    let regex = Regex::new("\\`foo\\'").unwrap();
+    assert!(regex.is_match("foo"));
+    let chunk = Boundaries {
+        bol_at_start: false,
+        eol_at_end: true,
+    };
+    assert!(regex.captures_with_boundaries("foo", chunk).is_none());
+}
+
+#[test]
+fn test_try_from_utf8_bytes() {
+    let regex = Regex::try_from_utf8_bytes("caf\u{e9}".as_bytes(), SyntaxFlags::default()).unwrap();
+    assert!(regex.is_match("un café"));
+
+    // The Latin-1 encoding of the same pattern is not UTF-8
+    let latin1 = [b'c', b'a', b'f', 0xE9];
+    assert_eq!(
+        Regex::try_from_utf8_bytes(&latin1, SyntaxFlags::default()).unwrap_err(),
+        RegexError::InvalidUtf8
+    );
+    let regex = Regex::from_bytes_with_len(&latin1, SyntaxFlags::default()).unwrap();
+    assert!(regex.is_match("un café"));
+}
+
+#[test]
+fn test_atomic_groups() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+
+    // The atomic `a+` keeps every `a`, leaving none for the final `a`
+    let regex = Regex::with_syntax("(?>a+)a", syntax).unwrap();
+    assert!(!regex.is_match("aaaa"));
+    assert!(Regex::with_syntax("a+a", syntax).unwrap().is_match("aaaa"));
+    assert_eq!(
+        Regex::with_syntax("(?>a+)b", syntax).unwrap().find("xaaab"),
+        Some((1, 5))
+    );
+
+    // Alternatives inside the group are not retried once it has matched
+    let regex = Regex::with_syntax("(?>a|ab)c", syntax).unwrap();
+    assert!(!regex.is_match("abc"));
+    assert!(regex.is_match("ac"));
+    assert!(Regex::with_syntax("(a|ab)c", syntax)
+        .unwrap()
+        .is_match("abc"));
+
+    // Atomic groups do not capture, and groups inside them keep their numbers
+    let regex = Regex::with_syntax("(?>(a+))(b)", syntax).unwrap();
+    assert_eq!(regex.group_count(), 2);
+    assert_eq!(
+        regex.captures("aab").unwrap().to_vec(),
+        [Some((0, 3)), Some((0, 2)), Some((2, 3))]
+    );
+
+    // Backtracking into what precedes the group still works
+    let regex = Regex::with_syntax("(x|xy)(?>y+)z", syntax).unwrap();
+    assert_eq!(regex.find("xyyz"), Some((0, 4)));
+}
+
+#[test]
+fn test_replace_all_group_references() {
+    let regex = Regex::with_syntax("([a-z]+)=([0-9]+)", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.replace_all("a=1, bc=23", "$2:$1"), "1:a, 23:bc");
+    assert_eq!(regex.replace_all("a=1", "[$0]"), "[a=1]");
+    assert_eq!(regex.replace_all("a=1", "$$1 ${2}0 $x $"), "$1 10 $x $");
+    assert_eq!(regex.replace_all("a=1", "${2"), "${2");
+
+    // Groups that did not take part, or do not exist, expand to nothing
+    let regex = Regex::with_syntax("(a)|(b)", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.replace_all("ab", "<$1|$2|${12}>"), "<a||><|b|>");
+
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_syntax("(?P<key>[a-z]+)=(?P<value>[0-9]+)", syntax).unwrap();
+    assert_eq!(regex.replace_all("k=9", "${value}=${key}"), "9=k");
+}
+
+#[test]
+fn test_match_len_bounds() {
+    let intervals = SyntaxFlags::AWK | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES;
+    let bounds = |pattern: &str, syntax: SyntaxFlags| {
+        Regex::with_syntax(pattern, syntax)
+            .unwrap()
+            .match_len_bounds()
+    };
+
+    assert_eq!(bounds("a{2,4}", intervals), (2, Some(4)));
+    assert_eq!(bounds("a{3}", intervals), (3, Some(3)));
+    assert_eq!(bounds("a{2,}", intervals), (2, None));
+    assert_eq!(bounds("(ab|c){1,2}d", intervals), (2, Some(5)));
+    assert_eq!(bounds("a+", SyntaxFlags::AWK), (1, None));
+    assert_eq!(bounds("a*", SyntaxFlags::AWK), (0, None));
+    assert_eq!(bounds("ab?c", SyntaxFlags::AWK), (2, Some(3)));
+    assert_eq!(bounds("héllo|x", SyntaxFlags::AWK), (1, Some(5)));
+    assert_eq!(bounds("(a+)b\\1", SyntaxFlags::AWK), (2, None));
+    assert_eq!(bounds("^$", SyntaxFlags::AWK), (0, Some(0)));
+
+    // Long patterns must not exhaust the stack working out the bounds
+    let long = ".".repeat(100_000);
+    assert_eq!(bounds(&long, SyntaxFlags::AWK), (100_000, Some(100_000)));
+}
+
+#[test]
+fn test_replace_all_with() {
+    let regex = Regex::with_syntax("[a-z]+", SyntaxFlags::AWK).unwrap();
+    let upper = regex.replace_all_with("héllo, wörld 42!", |captures, text| {
+        captures.matched_str(0, text).unwrap().to_uppercase()
+    });
+    assert_eq!(upper, "HéLLO, WöRLD 42!");
+    assert_eq!(regex.replace_all_with("42", |_, _| unreachable!()), "42");
+
+    let regex = Regex::with_syntax("([a-z]+)=([0-9]+)", SyntaxFlags::AWK).unwrap();
+    let swapped = regex.replace_all_with("a=1, bc=23", |captures, text| {
+        let group = |index| captures.matched_str(index, text).unwrap();
+        format!("{}={}", group(2), group(1))
+    });
+    assert_eq!(swapped, "1=a, 23=bc");
+
+    // Empty matches step over one char at a time
+    let regex = Regex::with_syntax("x*", SyntaxFlags::AWK).unwrap();
+    let mut calls = 0;
+    let marked = regex.replace_all_with("ab", |_, _| {
+        calls += 1;
+        "-".to_string()
+    });
+    assert_eq!(marked, "-a-b-");
+    assert_eq!(calls, 3);
+}
+
+#[test]
+fn test_captures_matched_str() {
+    let text = "café crème, naïve";
+    let regex = Regex::with_syntax("([a-zé]+) (cr(è)me)", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures(text).unwrap();
+    assert_eq!(captures.matched_str(0, text), Some("café crème"));
+    assert_eq!(captures.matched_str(1, text), Some("café"));
+    assert_eq!(captures.matched_str(2, text), Some("crème"));
+    assert_eq!(captures.matched_str(3, text), Some("è"));
+    assert_eq!(captures.matched_str(4, text), None);
+
+    // Char and byte offsets diverge after the first multibyte char
+    let regex = Regex::with_syntax("na(ï)(v?)e", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures(text).unwrap();
+    assert_eq!(captures.get(1), Some((14, 15)));
+    assert_eq!(captures.matched_str(1, text), Some("ï"));
+    assert_eq!(captures.matched_str(2, text), Some("v"));
+
+    // An empty group slices to an empty string at its position
+    let regex = Regex::with_syntax("é(x*)", SyntaxFlags::AWK).unwrap();
+    let captures = regex.captures(text).unwrap();
+    assert_eq!(captures.matched_str(1, text), Some(""));
+
+    // A text shorter than the match cannot be sliced
+    assert_eq!(captures.matched_str(0, "ca"), None);
+}
+
+#[test]
+fn test_captures_with_branch_path() {
+    let regex = Regex::with_syntax("(a|b(c|d))", SyntaxFlags::AWK).unwrap();
+    let (captures, path) = regex.captures_with_branch_path("bd").unwrap();
+    assert_eq!(captures.get(2), Some((1, 2)));
+    assert_eq!(path, vec![1, 1]);
+    assert_eq!(regex.captures_with_branch_path("xa").unwrap().1, vec![0]);
+    assert_eq!(regex.captures_with_branch_path("bc").unwrap().1, vec![1, 0]);
+    assert!(regex.captures_with_branch_path("x").is_none());
+
+    // Abandoned branches are dropped; loops add one entry per iteration
+    let regex = Regex::with_syntax("(ab|a|c)+d|x", SyntaxFlags::AWK).unwrap();
+    let (captures, path) = regex.captures_with_branch_path("acabd").unwrap();
+    assert_eq!(captures.get(0), Some((0, 5)));
+    assert_eq!(path, vec![0, 1, 2, 0]);
+    assert_eq!(regex.captures_with_branch_path("x").unwrap().1, vec![1]);
+
+    // Patterns without alternations take no branches
+    let regex = Regex::with_syntax("a(b?)c*", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.captures_with_branch_path("abcc").unwrap().1, vec![]);
+}
+
+#[test]
+fn test_could_match_prefix() {
+    let regex = Regex::new("abcdef").unwrap();
+    assert!(regex.could_match_prefix("abc"));
+    assert!(regex.could_match_prefix(""));
+    assert!(regex.could_match_prefix("abcdef"));
+    assert!(!regex.could_match_prefix("abx"));
+    assert!(!regex.could_match_prefix("abcdefg"));
+    assert!(!regex.could_match_prefix("xabc"));
+
+    let regex = Regex::with_syntax("[0-9]+-[0-9]+|n/a", SyntaxFlags::AWK).unwrap();
+    assert!(regex.could_match_prefix("12"));
+    assert!(regex.could_match_prefix("12-"));
+    assert!(regex.could_match_prefix("12-3"));
+    assert!(regex.could_match_prefix("n/"));
+    assert!(!regex.could_match_prefix("12x"));
+    assert!(!regex.could_match_prefix("-"));
+
+    // A shorter complete match does not make longer text a prefix
+    let regex = Regex::with_syntax("ab?", SyntaxFlags::AWK).unwrap();
+    assert!(regex.could_match_prefix("a"));
+    assert!(regex.could_match_prefix("ab"));
+    assert!(!regex.could_match_prefix("ac"));
+
+    let regex = Regex::with_syntax("(a+)b\\1", SyntaxFlags::AWK).unwrap();
+    assert!(regex.could_match_prefix("aaba"));
+    assert!(!regex.could_match_prefix("aabaaa"));
+}
+
+#[test]
+fn test_interval_forms() {
+    use tature::CompileError;
+
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES;
+    let full = |pattern: &str| Regex::with_syntax(&format!("^{pattern}$"), syntax).unwrap();
+
+    let exactly_two = full("a{2}");
+    let at_least_two = full("a{2,}");
+    let one_to_three = full("a{1,3}");
+    for (text, two, two_or_more, one_to_three_matches) in [
+        ("", false, false, false),
+        ("a", false, false, true),
+        ("aa", true, true, true),
+        ("aaa", false, true, true),
+        ("aaaa", false, true, false),
+        ("aab", false, false, false),
+    ] {
+        assert_eq!(exactly_two.is_match(text), two, "a{{2}} on {text:?}");
+        assert_eq!(
+            at_least_two.is_match(text),
+            two_or_more,
+            "a{{2,}} on {text:?}"
+        );
+        assert_eq!(
+            one_to_three.is_match(text),
+            one_to_three_matches,
+            "a{{1,3}} on {text:?}"
+        );
+    }
+
+    // Unanchored, the greediest count wins at the leftmost start
+    let regex = Regex::with_syntax("a{1,3}", syntax).unwrap();
+    assert_eq!(regex.find("baaaaa"), Some((1, 4)));
+
+    assert!(matches!(
+        Regex::with_syntax("a{3,2}", syntax),
+        Err(RegexError::CompileError(CompileError::InvalidInterval {
+            position: 2
+        }))
+    ));
+    assert!(matches!(
+        Regex::with_syntax("a{1,x}", syntax),
+        Err(RegexError::CompileError(
+            CompileError::InvalidInterval { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_compile_verbose() {
+    // A bad back-reference, then an unterminated set
+    let errors = Regex::compile_verbose("\\(a\\)\\9b[cd", SyntaxFlags::empty()).unwrap_err();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert_eq!(errors[0], RegexError::BadBackReference);
+    assert_eq!(Err(errors[1].clone()), Regex::new("[cd").map(|_| ()));
+
+    // Each error is reported where compiling stops at the first
+    let errors = Regex::compile_verbose("a\\9", SyntaxFlags::empty()).unwrap_err();
+    assert_eq!(Err(errors[0].clone()), Regex::new("a\\9").map(|_| ()));
+    assert_eq!(errors.len(), 1);
+
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES;
+    let errors = Regex::compile_verbose("a{3,2}b|c{x}", syntax).unwrap_err();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+
+    let regex = Regex::compile_verbose("\\(a\\)\\1", SyntaxFlags::empty()).unwrap();
+    assert!(regex.is_match("aa"));
+}
+
+#[test]
+fn test_lazy_quantifiers() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let find = |pattern: &str, text: &str| Regex::with_syntax(pattern, syntax).unwrap().find(text);
+
+    assert_eq!(find("<.*?>", "<a><b>"), Some((0, 3)));
+    assert_eq!(find("<.*>", "<a><b>"), Some((0, 6)));
+    assert_eq!(find("<.+?>", "<><a>"), Some((0, 5)));
+    assert_eq!(find("ab??", "abb"), Some((0, 1)));
+    assert_eq!(find("ab??c", "abc"), Some((0, 3)));
+    assert_eq!(find("a+?", "aaa"), Some((0, 1)));
+    assert_eq!(find("(a|b)*?c", "abac"), Some((0, 4)));
+
+    // Lazy intervals take the minimum count the rest allows
+    let intervals = syntax | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES;
+    let regex = Regex::with_syntax("a{2,4}?", intervals).unwrap();
+    assert_eq!(regex.find("aaaa"), Some((0, 2)));
+    let regex = Regex::with_syntax("a{2,4}?b", intervals).unwrap();
+    assert_eq!(regex.find("aaab"), Some((0, 4)));
+
+    // Captures reflect the shortest expansion
+    let regex = Regex::with_syntax("(.*?)=(.*)", syntax).unwrap();
+    let caps = regex.captures("a=b=c").unwrap();
+    assert_eq!(caps.get(1), Some((0, 1)));
+    assert_eq!(caps.get(2), Some((2, 5)));
+
+    // Nullable bodies cannot spin
+    assert_eq!(find("(a*)*?b", "aab"), Some((0, 3)));
+
+    // Without PERL_EXTENSIONS the `?` makes the starred expression optional
+    let regex = Regex::with_syntax("<.*?>", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find("<a><b>"), Some((0, 6)));
+}
+
+#[test]
+fn test_regex_builder() {
+    let regex = RegexBuilder::new("h\\(el\\)lo")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("say HELLO"), Some((4, 9)));
+
+    // The case flag applies whichever order the options are set in
+    let regex = RegexBuilder::new("a+")
+        .case_insensitive(true)
+        .syntax(SyntaxFlags::AWK)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("xAaA"), Some((1, 4)));
+    let regex = RegexBuilder::new("a")
+        .syntax(SyntaxFlags::CASE_INSENSITIVE)
+        .case_insensitive(false)
+        .build()
+        .unwrap();
+    assert!(!regex.is_match("A"));
+
+    assert!(RegexBuilder::new("a\\9").build().is_err());
+    assert_eq!(Regex::new("a").unwrap().limits().max_ticks, None);
+}
+
+#[test]
+fn test_regex_builder_limits() {
+    let text = format!("{}c", "a".repeat(1000));
+
+    // Tick limits stored on the regex stop the implicit-limit methods
+    let regex = RegexBuilder::new("a*c")
+        .syntax(SyntaxFlags::AWK)
+        .max_ticks(100)
+        .build()
+        .unwrap();
+    assert_eq!(regex.limits().max_ticks, Some(100));
+    assert!(regex.is_match("aac"));
+    assert!(!regex.is_match(&text));
+    assert_eq!(regex.find(&text), None);
+    assert!(regex.captures(&text).is_none());
+    assert_eq!(regex.find_iter(&text).count(), 0);
+    assert!(Regex::with_syntax("a*c", SyntaxFlags::AWK)
+        .unwrap()
+        .is_match(&text));
+
+    // Explicit limits still take precedence
+    assert!(regex.is_match_with_limits(&text, ExecLimits::default()));
+
+    let regex = RegexBuilder::new("(a|b)*c")
+        .syntax(SyntaxFlags::AWK)
+        .max_failures(10)
+        .build()
+        .unwrap();
+    assert!(regex.is_match("ababc"));
+    assert!(!regex.is_match(&format!("{}c", "ab".repeat(20))));
+
+    let regex = RegexBuilder::new("c").max_input_chars(10).build().unwrap();
+    assert!(regex.is_match("abc"));
+    assert!(!regex.is_match(&text));
+}
+
+#[test]
+fn test_search_start_anchor() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_syntax("\\G([0-9]+|[a-z]+)", syntax).unwrap();
+    assert!(regex.is_anchored_start());
+    assert_eq!(regex.find("ab12"), Some((0, 2)));
+    assert_eq!(regex.find(" ab"), None);
+    assert_eq!(
+        regex.captures_at(" ab", 1, None).unwrap().get(0),
+        Some((1, 3))
+    );
+
+    // Each iteration resumes at the previous match end, so a gap stops it
+    let tokens: Vec<_> = regex.find_iter("ab12cd ef").collect();
+    assert_eq!(tokens, vec![(0, 2), (2, 4), (4, 6)]);
+
+    // Mid-pattern, `\G` still refers to where the search started
+    let regex = Regex::with_syntax("x|\\Gy", syntax).unwrap();
+    assert_eq!(regex.find("ay"), None);
+    assert_eq!(regex.find("yay"), Some((0, 1)));
+    assert_eq!(
+        regex.find_iter("yyaxy").collect::<Vec<_>>(),
+        vec![(0, 1), (1, 2), (3, 4), (4, 5)]
+    );
+
+    // Without PERL_EXTENSIONS it is an ordinary escaped `G`
+    let regex = Regex::with_syntax("\\Gx", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.find("aGx"), Some((1, 3)));
+}
+
+#[test]
+fn test_captures_iter_contiguous() {
+    let regex = Regex::with_syntax("([0-9]+)|([a-z]+)", SyntaxFlags::AWK).unwrap();
+    let text = "ab12cd ef34";
+
+    let spans = |iter: tature::CaptureMatches| iter.map(|c| c.get(0).unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        spans(regex.captures_iter(text)),
+        vec![(0, 2), (2, 4), (4, 6), (7, 9), (9, 11)]
+    );
+    // The space is a gap, ending the contiguous iteration early
+    assert_eq!(
+        spans(regex.captures_iter_contiguous(text)),
+        vec![(0, 2), (2, 4), (4, 6)]
+    );
+    let numbers: Vec<_> = regex
+        .captures_iter_contiguous(text)
+        .filter_map(|c| c.get(1))
+        .collect();
+    assert_eq!(numbers, vec![(2, 4)]);
+
+    // Nothing at the start means nothing at all
+    assert_eq!(spans(regex.captures_iter_contiguous(" ab")), vec![]);
+
+    // An empty match ends iteration rather than skipping a char
+    let regex = Regex::with_syntax("a*", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        spans(regex.captures_iter_contiguous("aab")),
+        vec![(0, 2), (2, 2)]
+    );
+}
+
+#[test]
+fn test_case_insensitive_folding() {
+    let regex = Regex::with_syntax("Hello", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert!(regex.is_match("HELLO"));
+    assert!(regex.is_match("hello"));
+    assert!(regex.is_match("hElLo"));
+    assert!(!regex.is_match("help"));
+
+    // Latin-1 accented letters fold too, in the pattern and in sets
+    let regex = Regex::with_syntax("CAFÉ", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert_eq!(regex.find("un café"), Some((3, 7)));
+    let regex =
+        Regex::with_syntax("[à-ö]+", SyntaxFlags::AWK | SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert_eq!(regex.find("xÀéÖy"), Some((1, 4)));
+    let regex = Regex::with_syntax("garçon", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert!(regex.is_match("GARÇON"));
+
+    // The multiplication and division signs are not letters
+    let regex = Regex::with_syntax("×", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert!(!regex.is_match("÷"));
+
+    // Every member of a range is folded, not just its ends, so folded sets
+    // match what the plain ones do
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::CASE_INSENSITIVE;
+    let regex = Regex::with_syntax("^[À-ÿ]$", syntax).unwrap();
+    for ch in ["ß", "×", "÷", "À", "à", "ÿ"] {
+        assert!(regex.is_match(ch), "{ch}");
+    }
+    let regex = Regex::with_syntax("^[À-Þ]$", syntax).unwrap();
+    assert!(regex.is_match("×"));
+    assert!(!regex.is_match("÷"));
+    assert!(!regex.is_match("ß"));
+    assert!(regex.is_match("é"));
+    let regex = Regex::with_syntax("^[^À-Þ]$", syntax).unwrap();
+    assert!(!regex.is_match("×"));
+    assert!(regex.is_match("÷"));
+    let regex = Regex::with_syntax("^[Z-a]$", syntax).unwrap();
+    assert!(regex.is_match("_"));
+    assert!(regex.is_match("z"));
+    assert!(regex.is_match("A"));
+    assert!(!regex.is_match("b"));
+
+    // CASE_INSENSITIVE_ASCII leaves non-ASCII letters alone
+    let regex = Regex::with_syntax("É", SyntaxFlags::CASE_INSENSITIVE_ASCII).unwrap();
+    assert!(!regex.is_match("é"));
+}
+
+#[test]
+fn test_heap_size() {
+    let trivial = Regex::new("a").unwrap();
+    assert!(trivial.heap_size() >= trivial.buffer.len());
+
+    // The case folding table is counted
+    let folded = Regex::with_syntax("a", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert!(folded.heap_size() > trivial.heap_size() + 56 * 8);
+
+    // So are the bytecode and group names
+    let long = Regex::new(&"abc".repeat(100)).unwrap();
+    assert!(long.heap_size() >= 300);
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let named = Regex::with_syntax("(?P<a_rather_long_group_name>a)", syntax).unwrap();
+    let unnamed = Regex::with_syntax("(a)", syntax).unwrap();
+    assert!(named.heap_size() >= unnamed.heap_size() + "a_rather_long_group_name".len());
+}
+
+#[test]
+fn test_collating_elements_in_sets() {
+    use tature::CompileError;
+
+    // With no locale, an equivalence class is just its char
+    let regex = Regex::new("[[=a=]]").unwrap();
+    assert!(regex.is_match("a"));
+    assert!(!regex.is_match("á"));
+    assert!(!regex.is_match("="));
+    assert!(!regex.is_match("["));
+
+    // Single-char collating elements work alone and as range ends
+    let regex = Regex::new("[[.-.]x]+").unwrap();
+    assert_eq!(regex.find("a-x-b"), Some((1, 4)));
+    let regex = Regex::new("[[.a.]-c]+").unwrap();
+    assert_eq!(regex.find("xabcd"), Some((1, 4)));
+    let regex = Regex::new("[[.].]]").unwrap();
+    assert!(regex.is_match("]"));
+
+    // Multi-char elements need collation data and are rejected
+    assert_eq!(
+        Regex::new("[[.ch.]]").unwrap_err(),
+        RegexError::CompileError(CompileError::UnsupportedCollation("[.ch.]".to_string()))
+    );
+    assert_eq!(
+        Regex::new("[[=ae=]]").unwrap_err(),
+        RegexError::CompileError(CompileError::UnsupportedCollation("[=ae=]".to_string()))
+    );
+    assert_eq!(Regex::new("[[=a").unwrap_err(), RegexError::PrematureEnd);
+
+    // A lone bracket in a set is still literal
+    assert!(Regex::new("[[a]").unwrap().is_match("["));
+}
+
+#[test]
+fn test_moo_match_registers() {
+    // Examples from the LambdaMOO Programmer's Manual, with `%` escapes
+    // translated to `\`
+    let moo = |pattern: &str| Regex::with_syntax(pattern, SyntaxFlags::MOO).unwrap();
+    let unset = [(0, -1); 9];
+
+    assert_eq!(moo("^f*o$").moo_match("foo"), None);
+    assert_eq!(
+        moo("o*").moo_match("foo"),
+        Some(MooMatch {
+            start: 1,
+            end: 0,
+            replacements: unset,
+        })
+    );
+    assert_eq!(
+        moo("o*b").moo_match("foobar"),
+        Some(MooMatch {
+            start: 2,
+            end: 4,
+            replacements: unset,
+        })
+    );
+
+    let mut replacements = unset;
+    replacements[0] = (2, 3);
+    assert_eq!(
+        moo("f\\(o*\\)b").moo_match("foobar"),
+        Some(MooMatch {
+            start: 1,
+            end: 4,
+            replacements,
+        })
+    );
+
+    assert_eq!(
+        moo("o*b").moo_rmatch("foobar"),
+        Some(MooMatch {
+            start: 4,
+            end: 4,
+            replacements: unset,
+        })
+    );
+}
+
+#[test]
+fn test_search_long_input() {
+    use tature::matcher::{search_with_stats, SearchStats};
+
+    // Every position is a candidate start that fails after a few steps
+    let mut text = "a".repeat(64 * 1024);
+    text.push_str("bc");
+    let regex = Regex::new("\\(a\\|b\\)c").unwrap();
+
+    // Tick limits apply to each start position, not the whole search
+    let limits = ExecLimits {
+        max_ticks: Some(100),
+        ..ExecLimits::default()
+    };
+    let mut stats = SearchStats::default();
+    let result = search_with_stats(&regex, &text, 0, text.len() as i32, limits, &mut stats);
+    assert_eq!(result, Ok(64 * 1024));
+    assert_eq!(stats.start_attempts, 64 * 1024 + 1);
+    assert_eq!(regex.find(&text), Some((64 * 1024, 64 * 1024 + 2)));
+}
+
+#[test]
+fn test_first_char_prefilter_matches_exhaustive_search() {
+    use tature::matcher::{match_at, search_with_stats, SearchStats};
+
+    let text = "xxfoobar\nfoo.bar\n  foobarfoobar";
+    let len = text.chars().count();
+    for pattern in [
+        "foobar",
+        "(foo|bar)+",
+        ".bar",
+        "^foo",
+        "^ *f",
+        "[fb]o*",
+        "r$",
+        "x*",
+    ] {
+        let regex = Regex::with_syntax(pattern, SyntaxFlags::AWK).unwrap();
+
+        // Trying every position without the prefilter finds the same start
+        let exhaustive = (0..=len)
+            .find(|&pos| {
+                match_at(&regex, text, pos, ExecLimits::default())
+                    .unwrap()
+                    .is_some()
+            })
+            .map_or(-1, |pos| pos as i32);
+        let mut stats = SearchStats::default();
+        let result = search_with_stats(
+            &regex,
+            text,
+            0,
+            len as i32,
+            ExecLimits::default(),
+            &mut stats,
+        );
+        assert_eq!(result, Ok(exhaustive), "{pattern:?}");
+    }
+
+    // A literal prefix only runs the matcher where its first char occurs
+    let regex = Regex::new("foobar").unwrap();
+    let mut stats = SearchStats::default();
+    let result = search_with_stats(
+        &regex,
+        text,
+        0,
+        len as i32,
+        ExecLimits::default(),
+        &mut stats,
+    );
+    assert_eq!(result, Ok(2));
+    assert_eq!(stats.start_attempts, 1);
+}
+
+#[test]
+fn test_append_literal() {
+    let regex = Regex::new("foo").unwrap().append_literal("bar").unwrap();
+    assert!(regex.is_match("foobar"));
+    assert!(!regex.is_match("foo"));
+    assert_eq!(regex.find("xfoobarx"), Some((1, 7)));
+
+    // The literal follows every alternative and existing groups keep their spans
+    let regex = Regex::new("\\(fo+\\)\\|ba")
+        .unwrap()
+        .append_literal("r!")
+        .unwrap();
+    assert_eq!(regex.find("bar!"), Some((0, 4)));
+    assert_eq!(regex.captures("foor!").unwrap().get(1), Some((0, 3)));
+    assert!(!regex.is_match("foo"));
+    assert_eq!(regex.match_len_bounds(), (4, None));
+
+    // Appended chars are folded like the rest of the pattern
+    let regex = Regex::with_syntax("é", SyntaxFlags::CASE_INSENSITIVE)
+        .unwrap()
+        .append_literal("TÉ")
+        .unwrap();
+    assert!(regex.is_match("ÉtÉ"));
+
+    // Long literals span several runs
+    let long = "ab".repeat(200);
+    let regex = Regex::new("x").unwrap().append_literal(&long).unwrap();
+    assert!(regex.is_match(&format!("x{long}")));
+    assert!(!regex.is_match(&format!("x{}", &long[1..])));
+
+    // Whole-pattern recursion would take the literal along
+    let recursive =
+        Regex::with_syntax("a(?R)?b", SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS).unwrap();
+    assert!(recursive.append_literal("c").is_err());
+}
+
+#[test]
+fn test_set_range_cap() {
+    // Repeated and overlapping ranges merge into one
+    let regex = Regex::new(&format!("[{}]", "a-b".repeat(2000))).unwrap();
+    assert!(regex.is_match("b"));
+    assert!(!regex.is_match("c"));
+    let regex = Regex::new("[a-cb-fx-z0-9g]+").unwrap();
+    assert_eq!(regex.find("--agz09--"), Some((2, 7)));
+
+    // Thousands of disjoint chars cannot be stored
+    let huge: String = ('\u{4e00}'..).step_by(2).take(3000).collect();
+    assert_eq!(
+        Regex::new(&format!("[{huge}]")).unwrap_err(),
+        RegexError::TooComplex
+    );
+
+    // Up to the cap, membership is exact on both sides of each range
+    let wide: String = ('\u{4e00}'..).step_by(2).take(255).collect();
+    let regex = Regex::new(&format!("[{wide}]")).unwrap();
+    assert!(regex.is_match("\u{4e00}"));
+    assert!(regex.is_match("\u{4ffc}"));
+    assert!(!regex.is_match("\u{4e01}"));
+    assert!(!regex.is_match("\u{4ffe}"));
+    let regex = Regex::new(&format!("[^{wide}]")).unwrap();
+    assert!(regex.is_match("\u{4e01}"));
+    assert!(!regex.is_match("\u{4e02}"));
+
+    // The builder can lower the cap
+    let mut builder = RegexBuilder::new("[a-cx-z]");
+    assert!(builder.build().is_ok());
+    assert_eq!(
+        builder.max_set_ranges(1).build().unwrap_err(),
+        RegexError::TooComplex
+    );
+    assert!(RegexBuilder::new("[a-cd-f]")
+        .max_set_ranges(1)
+        .build()
+        .is_ok());
+
+    // A range written backwards is an error, not a set matching nothing
+    use tature::CompileError;
+    assert_eq!(
+        Regex::new("[z-a]").unwrap_err(),
+        RegexError::CompileError(CompileError::InvalidRange {
+            start: 'z',
+            end: 'a'
+        })
+    );
+    assert!(Regex::new("[a-a]").is_ok());
+
+    // Order is checked as written, so folding cannot drop a range
+    let regex = Regex::with_syntax("[Z-a]", SyntaxFlags::CASE_INSENSITIVE).unwrap();
+    assert!(regex.is_match("_"));
+    assert!(matches!(
+        Regex::with_syntax("[a-Z]", SyntaxFlags::CASE_INSENSITIVE),
+        Err(RegexError::CompileError(CompileError::InvalidRange { .. }))
+    ));
+}
+
+#[test]
+fn test_buffer_anchored_search_tries_one_position() {
+    use tature::matcher::{search_with_stats, SearchStats};
+
+    let text = format!("abx\n{}", "abc".repeat(20_000));
+    let len = text.chars().count() as i32;
+    let regex = Regex::new("\\`abc").unwrap();
+
+    let mut stats = SearchStats::default();
+    let result = search_with_stats(&regex, &text, 0, len, ExecLimits::default(), &mut stats);
+    assert_eq!(result, Ok(-1));
+    assert_eq!(stats.start_attempts, 1);
+
+    // A search that cannot reach position 0 makes no attempt at all
+    let mut stats = SearchStats::default();
+    let result = search_with_stats(&regex, &text, 5, len - 5, ExecLimits::default(), &mut stats);
+    assert_eq!(result, Ok(-1));
+    assert_eq!(stats.start_attempts, 0);
+
+    // Backward searches still find position 0
+    let mut stats = SearchStats::default();
+    let result = search_with_stats(&regex, "abcabc", 6, -6, ExecLimits::default(), &mut stats);
+    assert_eq!(result, Ok(0));
+    assert_eq!(stats.start_attempts, 1);
+
+    // `^` matches after each embedded newline
+    assert_eq!(Regex::new("^abc").unwrap().find(&text), Some((4, 7)));
+}
+
+#[test]
+fn test_compile_all() {
+    let results = tature::compile_all(&["fo+", "[ab", "[b-c]", "*x", "a{2}"], SyntaxFlags::EGREP);
+    assert_eq!(results.len(), 5);
+    assert!(results[0].as_ref().unwrap().is_match("foo"));
+    assert!(results[1].is_err());
+    assert!(results[2].as_ref().unwrap().is_match("c"));
+    assert!(results[3].is_err());
+    assert!(results[4].as_ref().unwrap().is_match("a{2}"));
+
+    assert!(tature::compile_all(&[], SyntaxFlags::EGREP).is_empty());
+}
+
+#[test]
+fn test_memoized_backtracking() {
+    let regex = Regex::with_syntax("(a+a+)+b", SyntaxFlags::AWK).unwrap();
+    let text = format!("{}c", "a".repeat(30));
+    let limits = ExecLimits {
+        max_ticks: Some(100_000),
+        ..ExecLimits::default()
+    };
+    let memoized = ExecLimits {
+        memoize: true,
+        ..limits
+    };
+
+    assert_eq!(regex.try_captures_with_limits(&text, memoized), Ok(None));
+
+    // Exponential without memoization, bounded with it
+    let nested = Regex::with_syntax("(a|aa)+b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        nested.try_captures_with_limits(&text, limits),
+        Err(RegexError::Timeout)
+    );
+    assert_eq!(nested.try_captures_with_limits(&text, memoized), Ok(None));
+
+    let text = format!("{}b", "a".repeat(30));
+    assert_eq!(
+        regex
+            .captures_with_limits(&text, memoized)
+            .unwrap()
+            .to_vec(),
+        vec![Some((0, 31)), Some((0, 30))]
+    );
+
+    // Captures are the ones found without memoization
+    for (pattern, text) in [
+        ("(foo|foob)(ar|bar)", "xfoobarx"),
+        ("x(a|b)+y", "xabbay"),
+        ("(a|ab)(c|bcd)(d*)", "abcd"),
+        ("((a)|b)+", "abab"),
+        ("(a*)*b.", "abbcbc"),
+        (".(a*)*", "babbaa"),
+        ("(a*)*ba", "ccbaa"),
+    ] {
+        let regex = Regex::with_syntax(pattern, SyntaxFlags::AWK).unwrap();
+        assert_eq!(
+            regex.captures_with_limits(text, memoized),
+            regex.captures(text),
+            "{pattern:?} on {text:?}"
+        );
+    }
+
+    // The builder sets it for every method
+    let regex = RegexBuilder::new("(a|aa)+b")
+        .syntax(SyntaxFlags::AWK)
+        .memoize(true)
+        .max_ticks(100_000)
+        .build()
+        .unwrap();
+    assert!(!regex.is_match(&"a".repeat(30)));
+}
+
+#[test]
+fn test_empty_groups() {
+    let regex = Regex::with_syntax("(a)()b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.group_count(), 2);
+    let caps = regex.captures("ab").unwrap();
+    assert_eq!(caps.get(1), Some((0, 1)));
+    assert_eq!(caps.get(2), Some((1, 1)));
+
+    let regex = Regex::new("\\(a\\)\\(\\)b").unwrap();
+    assert_eq!(regex.captures("ab").unwrap().get(2), Some((1, 1)));
+
+    let regex = Regex::with_syntax("x()", SyntaxFlags::AWK).unwrap();
+    assert_eq!(regex.captures("yx").unwrap().get(1), Some((2, 2)));
+
+    // Quantified empty groups run once and then leave the loop
+    for pattern in ["()*a", "()+a", "()?a"] {
+        let regex = Regex::with_syntax(pattern, SyntaxFlags::AWK).unwrap();
+        let caps = regex.captures("a").unwrap();
+        assert_eq!(caps.get(0), Some((0, 1)), "{pattern:?}");
+        assert_eq!(caps.get(1), Some((0, 0)), "{pattern:?}");
+    }
+}
+
+#[test]
+fn test_regex_bytes_round_trip() {
+    let perl = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let cases = [
+        ("\\(fo+\\)\\|ba[rz]", SyntaxFlags::EMACS, "xx bar foooo"),
+        ("(?P<word>[a-z]+)-\\1", perl, "go ab-ab"),
+        (
+            "^h[éa]llo",
+            SyntaxFlags::AWK | SyntaxFlags::CASE_INSENSITIVE,
+            "x\nHÉLLO",
+        ),
+        (
+            "a{2,3}b",
+            SyntaxFlags::EGREP | SyntaxFlags::INTERVALS | SyntaxFlags::NO_BK_BRACES,
+            "caaab",
+        ),
+        ("\\(([^()]|(?R))*\\)", perl, "f((a)(b))"),
+    ];
+    for (pattern, syntax, text) in cases {
+        let regex = Regex::with_syntax(pattern, syntax).unwrap();
+        let restored = Regex::from_bytes(&regex.to_bytes()).unwrap();
+        assert_eq!(restored.buffer, regex.buffer, "{pattern:?}");
+        assert_eq!(restored.group_count(), regex.group_count());
+        assert_eq!(restored.group_name(1), regex.group_name(1));
+        assert_eq!(restored.captures(text), regex.captures(text), "{pattern:?}");
+        assert!(restored.captures(text).is_some(), "{pattern:?}");
+        assert_eq!(restored.to_bytes(), regex.to_bytes());
+    }
+
+    let bytes = Regex::new("a\\(b\\)").unwrap().to_bytes();
+    let reject = |bytes: &[u8]| {
+        matches!(
+            Regex::from_bytes(bytes),
+            Err(RegexError::InvalidBytecode(_))
+        )
+    };
+    assert!(reject(b"not a regex"));
+    assert!(reject(&bytes[..bytes.len() - 1]));
+
+    let mut other_version = bytes.clone();
+    other_version[6] += 1;
+    assert_eq!(
+        Regex::from_bytes(&other_version).unwrap_err(),
+        RegexError::InvalidBytecode("unsupported format version")
+    );
+
+    // Corrupt bytecode is caught before it reaches the matcher
+    let mut corrupt = bytes.clone();
+    let last = corrupt.len() - 1;
+    corrupt[last] = 0xff;
+    assert!(reject(&corrupt));
+
+    // Jumps the compiler never produces, which could spin forever, are
+    // rejected: `ab*c` is `exact a; failure_jump 12; exact b; star_jump 3;
+    // exact c; end` at the end of its bytes
+    let looping = Regex::new("ab*c").unwrap();
+    let bytes = looping.to_bytes();
+    let code = bytes.len() - looping.buffer.len();
+    assert!(Regex::from_bytes(&bytes).is_ok());
+    let patched = |at: usize, patch: &[u8]| {
+        let mut bytes = bytes.clone();
+        bytes[code + at..code + at + patch.len()].copy_from_slice(patch);
+        bytes
+    };
+    // star_jump to itself, or to the `exact b` instead of its failure_jump
+    assert!(reject(&patched(9, &[0x0a, 0xfd, 0xff])));
+    assert!(reject(&patched(9, &[0x0a, 0xfa, 0xff])));
+    // Plain jumps and failure jumps back to themselves or earlier
+    assert!(reject(&patched(9, &[0x09, 0xfd, 0xff])));
+    assert!(reject(&patched(3, &[0x0b, 0xfd, 0xff])));
+    assert!(reject(&patched(3, &[0x0b, 0xfa, 0xff])));
+    assert!(reject(&patched(9, &[0x0c, 0xfa, 0xff])));
+}
+
+#[test]
+fn test_find_iter_merged() {
+    let regex = Regex::new("[0-9]").unwrap();
+    assert_eq!(
+        regex.find_iter_merged("123").collect::<Vec<_>>(),
+        vec![(0, 3)]
+    );
+    assert_eq!(
+        regex.find_iter_merged("12 3x45").collect::<Vec<_>>(),
+        vec![(0, 2), (3, 4), (5, 7)]
+    );
+    assert_eq!(regex.find_iter_merged("none").count(), 0);
+
+    // Empty matches touching a match fold into it
+    let regex = Regex::new("x*").unwrap();
+    assert_eq!(
+        regex.find_iter_merged("xxa").collect::<Vec<_>>(),
+        vec![(0, 2), (3, 3)]
+    );
+}
+
+#[test]
+fn test_disassemble() {
+    let listing = Regex::new("ab*c").unwrap().disassemble();
+    assert_eq!(
+        listing,
+        "   0: exact \"a\"\n   3: failure_jump -> 12\n   6: exact \"b\"\n   9: star_jump -> 3\n  12: exact \"c\"\n  15: end\n"
+    );
+
+    let syntax = SyntaxFlags::AWK
+        | SyntaxFlags::INTERVALS
+        | SyntaxFlags::NO_BK_BRACES
+        | SyntaxFlags::PERL_EXTENSIONS;
+    let listing = Regex::with_syntax("(x[^a-cz]){2,}?", syntax)
+        .unwrap()
+        .disassemble();
+    for expected in [
+        "repeat_start 2, lazy ->",
+        "start_memory 1",
+        "set [^a-cz]",
+        "end_memory 1",
+        "repeat_end -> 0",
+        "repeat_pop",
+    ] {
+        assert!(
+            listing.contains(expected),
+            "{expected:?} missing from\n{listing}"
+        );
+    }
+
+    assert_eq!(tature::disasm::disassemble(&[4, 9]), "   0: invalid 0x04\n");
+}
+
+#[test]
+fn test_anchored_at_position() {
+    let regex = Regex::new("[0-9]+").unwrap();
+    let text = "ab123 45";
+
+    assert!(regex.is_match_at(text, 2));
+    assert_eq!(
+        regex.captures_anchored_at(text, 2).unwrap().get(0),
+        Some((2, 5))
+    );
+    assert_eq!(
+        regex.captures_anchored_at(text, 3).unwrap().get(0),
+        Some((3, 5))
+    );
+
+    // No scanning ahead to the next digits
+    assert!(!regex.is_match_at(text, 0));
+    assert!(regex.captures_anchored_at(text, 5).is_none());
+    assert_eq!(
+        regex.captures_anchored_at(text, 6).unwrap().get(0),
+        Some((6, 8))
+    );
+
+    // Out of bounds positions never match, even for empty patterns
+    assert!(!regex.is_match_at(text, 9));
+    assert!(Regex::new("x*").unwrap().is_match_at(text, 8));
+    assert!(!Regex::new("x*").unwrap().is_match_at(text, 9));
+
+    // A tokenizer stepping through the text by hand
+    let word = Regex::new("[a-z]+").unwrap();
+    let space = Regex::new(" +").unwrap();
+    let mut pos = 0;
+    let mut tokens = Vec::new();
+    while let Some(caps) = [&word, &regex, &space]
+        .iter()
+        .find_map(|token| token.captures_anchored_at(text, pos))
+    {
+        let (start, end) = caps.get(0).unwrap();
+        tokens.push(&text[start..end]);
+        pos = end;
+    }
+    assert_eq!(tokens, ["ab", "123", " ", "45"]);
+}
+
+#[test]
+fn test_match_context_word_chars() {
+    let regex = Regex::with_syntax(r"\bfoo\b", SyntaxFlags::PERL_EXTENSIONS).unwrap();
+    let limits = ExecLimits::default();
+    let text = "foo-bar";
+
+    // By default `-` is not a word char, so `foo` stands alone
+    let default = MatchContext::default();
+    assert_eq!(regex.find_with_context(text, limits, default), Some((0, 3)));
+    assert_eq!(regex.find(text), Some((0, 3)));
+
+    // Treating `-` as a word char joins `foo-bar` into one word
+    let hyphenated = MatchContext {
+        word_char: |ch| ch.is_alphanumeric() || ch == '-',
+        ..Default::default()
+    };
+    assert!(!regex.is_match_with_context(text, limits, hyphenated));
+    assert_eq!(
+        regex.find_with_context("x foo-bar foo", limits, hyphenated),
+        Some((10, 13))
+    );
+
+    // `\w` follows the predicate too
+    let word = Regex::with_syntax(r"\w+", SyntaxFlags::PERL_EXTENSIONS).unwrap();
+    assert_eq!(
+        word.find_with_context("-a-b c", limits, hyphenated),
+        Some((0, 4))
+    );
+}
+
+#[test]
+fn test_match_context_newline() {
+    let regex = Regex::new("^b.$").unwrap();
+    let limits = ExecLimits::default();
+    let crlf = MatchContext {
+        newline: '\r',
+        ..Default::default()
+    };
+
+    assert!(regex.is_match("a\nbc\nd"));
+    assert!(!regex.is_match("a\rbc\rd"));
+    assert_eq!(
+        regex.find_with_context("a\rbc\rd", limits, crlf),
+        Some((2, 4))
+    );
+    // `.` stops at the chosen separator instead of `\n`
+    assert!(!regex.is_match_with_context("b\r", limits, crlf));
+    assert!(regex.is_match_with_context("b\n", limits, crlf));
+}
+
+#[test]
+fn test_posix_char_classes() {
+    use tature::CompileError;
+
+    let alpha = Regex::new("[[:alpha:]]+").unwrap();
+    assert_eq!(alpha.find("12abC3"), Some((2, 5)));
+    assert!(!alpha.is_match("123 _"));
+
+    let not_space = Regex::new("[^[:space:]]+").unwrap();
+    assert_eq!(not_space.find(" \t foo\nbar"), Some((3, 6)));
+    assert!(!not_space.is_match(" \t\r\n\x0b\x0c"));
+
+    // Classes mix with literals and ranges
+    let ident = Regex::new("[[:digit:]_]+").unwrap();
+    assert_eq!(ident.find("ab1_2c"), Some((2, 5)));
+    let hex = Regex::new("^[[:xdigit:]x-z]+$").unwrap();
+    assert!(hex.is_match("0xDeadBeefz"));
+    assert!(!hex.is_match("0xG"));
+
+    let cases = [
+        ("[[:alnum:]]", "a", true),
+        ("[[:alnum:]]", "-", false),
+        ("[[:upper:]]", "A", true),
+        ("[[:upper:]]", "a", false),
+        ("[[:lower:]]", "a", true),
+        ("[[:lower:]]", "A", false),
+        ("[[:punct:]]", "~", true),
+        ("[[:punct:]]", "a", false),
+        ("[[:blank:]]", "\t", true),
+        ("[[:blank:]]", "\n", false),
+        ("[[:cntrl:]]", "\x7f", true),
+        ("[[:print:]]", " ", true),
+        ("[[:graph:]]", " ", false),
+        ("[[:digit:]-]", "-", true),
+    ];
+    for (pattern, text, expected) in cases {
+        let regex = Regex::new(pattern).unwrap();
+        assert_eq!(regex.is_match(text), expected, "{pattern:?} on {text:?}");
+    }
+
+    // Case folding folds the class ranges with the text
+    let upper = RegexBuilder::new("^[[:upper:]]+$")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert!(upper.is_match("MiXeD"));
+
+    assert_eq!(
+        Regex::new("[[:word:]]").unwrap_err(),
+        RegexError::CompileError(CompileError::UnknownCharClass("word".to_string()))
+    );
+    assert_eq!(
+        Regex::new("[[:alpha").unwrap_err(),
+        RegexError::PrematureEnd
+    );
+}
+
+#[test]
+fn test_fixed_length_anchored_rejects_other_lengths() {
+    use tature::matcher::{search_with_stats, SearchStats};
+
+    let regex = Regex::new("^[0-9][0-9][0-9]$").unwrap();
+    assert!(regex.is_match("123"));
+    assert!(!regex.is_match("12a"));
+
+    // A single tick is enough to time out, so these never reach the VM
+    let one_tick = ExecLimits {
+        max_ticks: Some(1),
+        ..Default::default()
+    };
+    for text in ["12", "1234"] {
+        assert_eq!(regex.try_captures_with_limits(text, one_tick), Ok(None));
+        let mut stats = SearchStats::default();
+        let result = search_with_stats(&regex, text, 0, 4, ExecLimits::default(), &mut stats);
+        assert_eq!(result.unwrap(), -1);
+        assert_eq!(stats.start_attempts, 0, "{text:?}");
+    }
+    assert_eq!(
+        regex.try_captures_with_limits("123", one_tick),
+        Err(RegexError::Timeout)
+    );
+
+    // Line anchors can still match one line of longer text
+    assert_eq!(regex.find("ab\n123\ncd"), Some((3, 6)));
+    assert!(!regex.is_match("ab\n1234"));
+
+    // Buffer anchors need the whole text, newlines or not
+    let buffer = Regex::with_syntax(r"\`[0-9][0-9]\'", SyntaxFlags::EMACS).unwrap();
+    assert!(buffer.is_match("12"));
+    assert!(!buffer.is_match("12\n"));
+    let mut stats = SearchStats::default();
+    let result = search_with_stats(&buffer, "1\n2", 0, 3, ExecLimits::default(), &mut stats);
+    assert_eq!(result.unwrap(), -1);
+    assert_eq!(stats.start_attempts, 0);
+}
+
+#[test]
+fn test_digit_and_space_shorthands() {
+    let digits = Regex::new(r"\d+").unwrap();
+    assert_eq!(digits.find("abc 1234 x"), Some((4, 8)));
+    assert!(!digits.is_match("no digits"));
+
+    let non_digits = Regex::new(r"\D+").unwrap();
+    assert_eq!(non_digits.find("12ab3"), Some((2, 4)));
+    assert!(!non_digits.is_match("0123"));
+
+    let space = Regex::new(r"a\sb").unwrap();
+    for text in ["a b", "a\tb", "a\nb", "a\rb", "a\x0bb", "a\x0cb"] {
+        assert!(space.is_match(text), "{text:?}");
+    }
+    assert!(!space.is_match("a_b"));
+
+    let non_space = Regex::new(r"\S+").unwrap();
+    assert_eq!(non_space.find(" \t foo bar"), Some((3, 6)));
+    assert!(!non_space.is_match(" \t\n"));
+
+    // Shorthands combine with other syntax
+    let pair = Regex::with_syntax(r"(\d+)\s*=\s*(\S+)", SyntaxFlags::AWK).unwrap();
+    let captures = pair.captures("x 42 =  y;").unwrap();
+    assert_eq!(captures.get(1), Some((2, 4)));
+    assert_eq!(captures.get(2), Some((8, 10)));
+
+    // Only ASCII digits count
+    assert!(!digits.is_match("\u{0663}"));
+
+    // Without GNU extensions the letters are literal
+    let literal = Regex::with_syntax(r"\d", SyntaxFlags::NO_GNU_EXTENSIONS).unwrap();
+    assert!(literal.is_match("d"));
+    assert!(!literal.is_match("1"));
+}
+
+#[test]
+fn test_unicode_word_chars() {
+    let ascii = Regex::new(r"\bcafé\b").unwrap();
+    let unicode = Regex::with_syntax(r"\bcafé\b", SyntaxFlags::UNICODE_WORD).unwrap();
+
+    // In ASCII mode `é` is a non-word char, so there is no boundary after
+    // it at the end of the text, but there is one before the `s` of `cafés`
+    assert!(!ascii.is_match("un café"));
+    assert!(unicode.is_match("un café"));
+    assert!(ascii.is_match("cafés"));
+    assert!(!unicode.is_match("cafés"));
+
+    // A preceding accented letter joins the word only in Unicode mode
+    assert!(ascii.is_match("écafés"));
+    assert!(!unicode.is_match("écafé "));
+
+    let word = Regex::with_syntax(r"\w+", SyntaxFlags::UNICODE_WORD).unwrap();
+    assert_eq!(word.find("-- naïve --"), Some((3, 8)));
+    assert_eq!(word.find("日本語_x!"), Some((0, 5)));
+    assert_eq!(
+        Regex::new(r"\w+").unwrap().find("-- naïve --"),
+        Some((3, 5))
+    );
+
+    let word_start = Regex::with_syntax(r"\<é", SyntaxFlags::UNICODE_WORD).unwrap();
+    assert_eq!(word_start.find("céé éa"), Some((4, 5)));
+    assert!(!Regex::new(r"\<é").unwrap().is_match("céé éa"));
+
+    // An explicit context still overrides the flag
+    let limits = ExecLimits::default();
+    assert!(unicode.is_match_with_context("cafés", limits, MatchContext::default()));
+}
+
+#[test]
+fn test_perl_buffer_anchors() {
+    let start = Regex::new(r"\Afoo").unwrap();
+    assert_eq!(start.find("foo bar"), Some((0, 3)));
+    assert!(!start.is_match("bar foo"));
+    assert!(!start.is_match("bar\nfoo"));
+    assert!(Regex::new("^foo").unwrap().is_match("bar\nfoo"));
+
+    let end = Regex::new(r"foo\z").unwrap();
+    assert_eq!(end.find("bar foo"), Some((4, 7)));
+    assert!(!end.is_match("foo bar"));
+    assert!(!end.is_match("foo\nbar"));
+    assert!(!end.is_match("foo\n"));
+    assert!(Regex::new("foo$").unwrap().is_match("foo\nbar"));
+
+    // Same instructions as the GNU spellings
+    assert_eq!(
+        Regex::new(r"\Afoo\z").unwrap().buffer,
+        Regex::new(r"\`foo\'").unwrap().buffer
+    );
+
+    // Without GNU extensions the letters are literal
+    let literal = Regex::with_syntax(r"\Az", SyntaxFlags::NO_GNU_EXTENSIONS).unwrap();
+    assert!(literal.is_match("xAz"));
+}
+
+#[test]
+fn test_braced_hex_escapes() {
+    let emoji = Regex::with_syntax(r"a\x{1F600}b", SyntaxFlags::ANSI_HEX).unwrap();
+    assert_eq!(emoji.find("xa😀b"), Some((1, 4)));
+    assert!(!emoji.is_match("a😁b"));
+
+    // Two digits cover Latin-1, not just ASCII
+    let latin1 = Regex::with_syntax(r"caf\xE9", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(latin1.is_match("café"));
+    assert!(Regex::with_syntax(r"\xff", SyntaxFlags::ANSI_HEX)
+        .unwrap()
+        .is_match("ÿ"));
+
+    // Braces take any number of digits, and work inside sets
+    let short = Regex::with_syntax(r"\x{41}\x{0000e9}", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(short.is_match("Aé"));
+    let range = Regex::with_syntax(r"^[\x{3B1}-\x{3C9}]+$", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(range.is_match("αβγω"));
+    assert!(!range.is_match("abc"));
+
+    for bad in [
+        r"\x{}",
+        r"\x{D800}",
+        r"\x{110000}",
+        r"\x{FFFFFFFFF}",
+        r"\x{12G}",
+    ] {
+        assert_eq!(
+            Regex::with_syntax(bad, SyntaxFlags::ANSI_HEX).unwrap_err(),
+            RegexError::BadHexEscape,
+            "{bad:?}"
+        );
+    }
+    assert_eq!(
+        Regex::with_syntax(r"\x{41", SyntaxFlags::ANSI_HEX).unwrap_err(),
+        RegexError::PrematureEnd
+    );
+}
+
+#[test]
+fn test_unicode_escapes() {
+    let e_acute = Regex::with_syntax(r"caf\u00e9", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(e_acute.is_match("café"));
+    assert!(!e_acute.is_match("cafe"));
+
+    let upper = Regex::with_syntax(r"^[\u0041-\u005a]+$", SyntaxFlags::ANSI_HEX).unwrap();
+    assert!(upper.is_match("HELLO"));
+    assert!(!upper.is_match("Hello"));
+
+    let cjk = Regex::with_syntax(r"\u65e5\u672C", SyntaxFlags::ANSI_HEX).unwrap();
+    assert_eq!(cjk.find("x日本"), Some((1, 3)));
+
+    assert_eq!(
+        Regex::with_syntax(r"\ud800", SyntaxFlags::ANSI_HEX).unwrap_err(),
+        RegexError::BadHexEscape
+    );
+    assert_eq!(
+        Regex::with_syntax(r"\u12G4", SyntaxFlags::ANSI_HEX).unwrap_err(),
+        RegexError::BadHexEscape
+    );
+    assert_eq!(
+        Regex::with_syntax(r"\u12", SyntaxFlags::ANSI_HEX).unwrap_err(),
+        RegexError::PrematureEnd
+    );
+
+    // Without ANSI sequences the escape is still a plain `u`
+    assert!(Regex::new(r"\u0041").unwrap().is_match("u0041"));
+}
+
+#[test]
+fn test_captures_by_name() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let date = Regex::with_syntax(
+        "(?<year>[0-9]+)-(?<month>[0-9]+)-(?<day>[0-9]+)( (?<time>[0-9:]+))?",
+        syntax,
+    )
+    .unwrap();
+    assert_eq!(
+        date.capture_names().collect::<Vec<_>>(),
+        [
+            None,
+            Some("year"),
+            Some("month"),
+            Some("day"),
+            None,
+            Some("time")
+        ]
+    );
+
+    let line = "logged 2024-03-17: disk full";
+    let caps = date.captures(line).unwrap();
+    assert_eq!(caps.name("year", line), Some("2024"));
+    assert_eq!(caps.name("month", line), Some("03"));
+    assert_eq!(caps.name("day", line), Some("17"));
+    // Named but not matched, and not a name at all
+    assert_eq!(caps.name("time", line), None);
+    assert_eq!(caps.name("hour", line), None);
+    // Unnamed groups keep their numbers
+    assert_eq!(caps.get(4), None);
+
+    let line = "2024-03-17 12:30 up";
+    let caps = date.captures(line).unwrap();
+    assert_eq!(caps.name("time", line), Some("12:30"));
+    assert_eq!(caps.matched_str(4, line), Some(" 12:30"));
+
+    // Iterated matches carry the names too
+    let days: Vec<_> = date
+        .captures_iter("1999-12-31, 2000-01-01")
+        .map(|caps| caps.get(3))
+        .collect();
+    assert_eq!(days, [Some((8, 10)), Some((20, 22))]);
+    let text = "1999-12-31, 2000-01-01";
+    let years: Vec<_> = date
+        .captures_iter(text)
+        .map(|caps| caps.name("year", text).unwrap().to_string())
+        .collect();
+    assert_eq!(years, ["1999", "2000"]);
+
+    // Patterns without names report none
+    let plain = Regex::with_syntax("([0-9]+)", syntax).unwrap();
+    assert_eq!(plain.capture_names().collect::<Vec<_>>(), [None, None]);
+    assert_eq!(plain.captures("42").unwrap().name("0", "42"), None);
+}
+
+#[test]
+fn test_extended_documented_pattern() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::EXTENDED;
+    let regex = Regex::with_syntax(
+        "
+        # A range of two numbers
+        ( [0-9] + )     # low end
+        -               # separator
+        ( [0-9]+ )      # high end
+        ",
+        syntax,
+    )
+    .unwrap();
+    let captures = regex.captures("12-34").unwrap();
+    assert_eq!(captures.get(0), Some((0, 5)));
+    assert_eq!(captures.get(1), Some((0, 2)));
+    assert_eq!(captures.get(2), Some((3, 5)));
+    assert!(!regex.is_match("12 - 34"));
+
+    // `#` is literal when escaped or inside a set, as is whitespace
+    let regex = Regex::with_syntax(r"\# [# ]+ x # comment", syntax).unwrap();
+    assert_eq!(regex.find("## #x"), Some((0, 5)));
+    assert!(!regex.is_match("#x"));
+}
+
+#[test]
+fn test_dot_matches_newline_flag() {
+    for syntax in [SyntaxFlags::EMACS, SyntaxFlags::AWK, SyntaxFlags::MOO] {
+        let plain = Regex::with_syntax("a.b", syntax).unwrap();
+        assert!(!plain.is_match("a\nb"), "{syntax:?}");
+        assert!(plain.is_match("a-b"), "{syntax:?}");
+
+        let dotall = Regex::with_syntax("a.b", syntax | SyntaxFlags::ANY_INCLUDES_NEWLINE).unwrap();
+        assert_eq!(dotall.find("xa\nb"), Some((1, 4)), "{syntax:?}");
+        assert!(dotall.is_match("a-b"), "{syntax:?}");
+    }
+
+    // Only `.` changes; `^` and `$` still see the line break
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::ANY_INCLUDES_NEWLINE;
+    let regex = Regex::with_syntax("^b.*$", syntax).unwrap();
+    assert_eq!(regex.find("a\nb\nc"), Some((2, 5)));
+
+    // Repeated dots cross any number of lines
+    let regex = Regex::with_syntax("start.*end", syntax).unwrap();
+    assert_eq!(regex.find("start\n\nmiddle\nend!"), Some((0, 17)));
+    let regex = Regex::with_syntax("start.*end", SyntaxFlags::AWK).unwrap();
+    assert!(!regex.is_match("start\n\nmiddle\nend!"));
+}
+
+#[test]
+fn test_custom_translate_table() {
+    use std::collections::HashMap;
+
+    let accents = HashMap::from([('é', 'e'), ('è', 'e'), ('à', 'a')]);
+    let regex = Regex::with_translate("café", SyntaxFlags::EMACS, accents.clone()).unwrap();
+    assert!(regex.is_match("cafe"));
+    assert!(regex.is_match("café"));
+    assert!(regex.is_match("cafè"));
+    assert!(!regex.is_match("cafa"));
+    assert!(!regex.is_match("CAFE"));
+
+    // The table applies to the pattern side too, and inside sets
+    let regex =
+        Regex::with_translate("^v[aeiou]+la$", SyntaxFlags::EMACS, accents.clone()).unwrap();
+    assert!(regex.is_match("voilà"));
+
+    let quotes = HashMap::from([('“', '"'), ('”', '"')]);
+    let regex = Regex::with_translate(r#""[a-z]+""#, SyntaxFlags::AWK, quotes).unwrap();
+    assert_eq!(regex.find("say “hi” now"), Some((4, 8)));
+
+    // Combined with case folding, both apply
+    let syntax = SyntaxFlags::EMACS | SyntaxFlags::CASE_INSENSITIVE;
+    let regex = Regex::with_translate("cafe", syntax, accents.clone()).unwrap();
+    assert!(regex.is_match("CAFÉ"));
+    assert!(regex.is_match("Café"));
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_translate("(?i)cafe", syntax, accents).unwrap();
+    assert!(regex.is_match("CAFÉ"));
+
+    // The table survives a round trip through bytes
+    let restored = Regex::from_bytes(&regex.to_bytes()).unwrap();
+    assert!(restored.is_match("CAFÉ"));
+}
+
+#[test]
+fn test_captures_with_report() {
+    let limits = ExecLimits::default();
+
+    let trivial = Regex::new("abc").unwrap();
+    let (captures, trivial_stats) = trivial.captures_with_report("xxabc", limits);
+    assert_eq!(captures.unwrap().get(0), Some((2, 5)));
+    assert!(trivial_stats.ticks_used > 0);
+    assert_eq!(trivial_stats.max_failure_depth, 0);
+
+    let heavy = Regex::with_syntax("(a|aa)+b", SyntaxFlags::AWK).unwrap();
+    let (captures, heavy_stats) = heavy.captures_with_report(&"a".repeat(16), limits);
+    assert!(captures.is_none());
+    assert!(
+        heavy_stats.ticks_used > 100 * trivial_stats.ticks_used,
+        "{heavy_stats:?} vs {trivial_stats:?}"
+    );
+    assert!(heavy_stats.max_failure_depth >= 16);
+    assert_eq!(heavy_stats.start_attempts, 16);
+
+    // A search cut short still reports the work done before the limit
+    let tight = ExecLimits {
+        max_ticks: Some(50),
+        ..Default::default()
+    };
+    let (captures, stats) = heavy.captures_with_report(&"a".repeat(16), tight);
+    assert!(captures.is_none());
+    assert_eq!(stats.start_attempts, 1);
+    assert_eq!(stats.ticks_used, 50);
+}
+
+#[test]
+fn test_wall_clock_timeout() {
+    use std::time::{Duration, Instant};
+
+    let regex = Regex::with_syntax("(a|aa)+b", SyntaxFlags::AWK).unwrap();
+    let text = "a".repeat(80);
+    let limits = ExecLimits {
+        timeout: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    assert_eq!(
+        regex.try_captures_with_limits(&text, limits),
+        Err(RegexError::Timeout)
+    );
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(!regex.is_match_with_limits(&text, limits));
+
+    // Quick matches finish well inside the same limit
+    assert_eq!(regex.find_with_limits("aab", limits), Some((0, 3)));
+
+    // The builder keeps it for the plain methods
+    let regex = RegexBuilder::new("(a|aa)+b")
+        .syntax(SyntaxFlags::AWK)
+        .timeout(Duration::from_millis(20))
+        .build()
+        .unwrap();
+    assert_eq!(regex.limits().timeout, Some(Duration::from_millis(20)));
+    assert!(!regex.is_match(&text));
+}
+
+#[test]
+fn test_eol_context_follows_syntax() {
+    let emacs = |pattern| Regex::with_syntax(pattern, SyntaxFlags::EMACS).unwrap();
+    let awk = |pattern| Regex::with_syntax(pattern, SyntaxFlags::AWK).unwrap();
+
+    // At the end of the pattern `$` is always an anchor
+    for regex in [emacs("foo$"), awk("foo$")] {
+        assert_eq!(regex.find("foo\nfoo"), Some((0, 3)));
+        assert!(!regex.is_match("foo$"));
+        assert!(!regex.is_match("food"));
+    }
+
+    // Before an alternation it is an anchor only when the syntax spells
+    // alternation that way
+    let regex = awk("a$|b");
+    assert_eq!(regex.find("xa"), Some((1, 2)));
+    assert_eq!(regex.find("ab"), Some((1, 2)));
+    let regex = emacs(r"a$\|b");
+    assert_eq!(regex.find("xa"), Some((1, 2)));
+    assert_eq!(regex.find("ab"), Some((1, 2)));
+    let regex = emacs("a$|b");
+    assert_eq!(regex.find("xa$|b"), Some((1, 5)));
+    assert!(!regex.is_match("a"));
+
+    // Likewise before the end of a group
+    assert_eq!(awk("(a$)").find("ba"), Some((1, 2)));
+    assert_eq!(emacs(r"\(a$\)").find("ba"), Some((1, 2)));
+    assert_eq!(emacs("(a$)").find("(a$)"), Some((0, 4)));
+
+    // Elsewhere it is literal, or an error where operators are context
+    // independent
+    assert_eq!(emacs("a$b").find("xa$b"), Some((1, 4)));
+    assert_eq!(emacs("$foo").find("$foo"), Some((0, 4)));
+    assert_eq!(
+        Regex::with_syntax("a$b", SyntaxFlags::AWK).unwrap_err(),
+        RegexError::BadSpecialChar
+    );
+}
+
+#[test]
+fn test_regex_set() {
+    use tature::RegexSet;
+
+    let set = RegexSet::with_syntax(&["^get (.+)", "lamp", "^look$"], SyntaxFlags::AWK).unwrap();
+    assert_eq!(set.len(), 3);
+
+    assert_eq!(set.matches("get lamp"), [0, 1]);
+    assert_eq!(set.matches("look"), [2]);
+    assert_eq!(set.matches("drop lamp"), [1]);
+    assert_eq!(set.matches("inventory"), Vec::<usize>::new());
+    assert!(set.is_match("get sword"));
+    assert!(!set.is_match("look around"));
+
+    // Each pattern behaves as it does on its own
+    for text in ["get lamp", "look", "lamp post", ""] {
+        let expected: Vec<usize> = set
+            .regexes()
+            .iter()
+            .enumerate()
+            .filter(|(_, regex)| regex.is_match(text))
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(set.matches(text), expected, "{text:?}");
+    }
+
+    let empty = RegexSet::new(&[]).unwrap();
+    assert!(empty.is_empty());
+    assert!(!empty.is_match("anything"));
+
+    assert!(matches!(
+        RegexSet::new(&["ok", "[bad"]),
+        Err(RegexError::PrematureEnd)
+    ));
+}
+
+#[test]
+fn test_full_match() {
+    let digits = Regex::with_syntax("[0-9]+", SyntaxFlags::AWK).unwrap();
+    assert!(digits.is_full_match("123"));
+    assert!(!digits.is_full_match("123x"));
+    assert!(!digits.is_full_match("x123"));
+    assert!(!digits.is_full_match(""));
+
+    // A shorter alternative ending early gives way to one reaching the end
+    let alt = Regex::with_syntax("a|ab", SyntaxFlags::AWK).unwrap();
+    assert_eq!(alt.find("ab"), Some((0, 1)));
+    assert!(alt.is_full_match("ab"));
+    assert!(alt.is_full_match("a"));
+
+    let lazy = Regex::with_syntax("a+?", SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS).unwrap();
+    assert!(lazy.is_full_match("aaa"));
+
+    let empty = Regex::new("").unwrap();
+    assert!(empty.is_full_match(""));
+    assert!(!empty.is_full_match("a"));
+
+    // Line anchors still only need line boundaries, not the whole text
+    let line = Regex::with_syntax("^a$", SyntaxFlags::AWK).unwrap();
+    assert!(line.is_full_match("a"));
+    assert!(!line.is_full_match("a\nb"));
+    let lines = Regex::with_syntax("^a\nb$", SyntaxFlags::AWK).unwrap();
+    assert!(lines.is_full_match("a\nb"));
+}
+
+#[test]
+fn test_loops_over_empty_bodies_terminate() {
+    let limits = ExecLimits {
+        max_failures: 1_000,
+        ..ExecLimits::default()
+    };
+
+    let nested = Regex::with_syntax("(a*)*", SyntaxFlags::AWK).unwrap();
+    let captures = nested
+        .try_captures_with_limits("aaab", limits)
+        .unwrap()
+        .unwrap();
+    assert_eq!(captures.get(0), Some((0, 3)));
+    assert_eq!(captures.get(1), Some((3, 3)));
+    assert_eq!(nested.find("baa"), Some((0, 0)));
+
+    let then_b = Regex::with_syntax("(a*)*b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        then_b
+            .try_captures_with_limits("aab", limits)
+            .unwrap()
+            .unwrap()
+            .get(0),
+        Some((0, 3))
+    );
+    assert_eq!(then_b.try_captures_with_limits("aac", limits), Ok(None));
+
+    let empty_first = Regex::with_syntax("(|a)*", SyntaxFlags::AWK).unwrap();
+    let captures = empty_first
+        .try_captures_with_limits("aa", limits)
+        .unwrap()
+        .unwrap();
+    assert_eq!(captures.get(0), Some((0, 0)));
+    assert_eq!(captures.get(1), Some((0, 0)));
+
+    let empty_first_b = Regex::with_syntax("(|a)*b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        empty_first_b
+            .try_captures_with_limits("aab", limits)
+            .unwrap()
+            .unwrap()
+            .get(0),
+        Some((0, 3))
+    );
+
+    let plus = Regex::with_syntax("(a?)+b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(
+        plus.try_captures_with_limits("aab", limits)
+            .unwrap()
+            .unwrap()
+            .get(0),
+        Some((0, 3))
+    );
+    assert_eq!(
+        plus.try_captures_with_limits("b", limits)
+            .unwrap()
+            .unwrap()
+            .get(0),
+        Some((0, 1))
+    );
+
+    // A body that must give back a round still gets to
+    let give_back = Regex::with_syntax("(ab|a)*b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(give_back.find("abab"), Some((0, 4)));
+    assert_eq!(give_back.find("aab"), Some((0, 3)));
+}
+
+#[test]
+fn test_simple_loops_keep_one_failure_point() {
+    // Nothing after `a*` can start with `a`, so rounds are never given
+    // back and the loop updates a single failure point
+    let regex = Regex::new("a*b").unwrap();
+    let text = format!("{}b", "a".repeat(10_000));
+    let (captures, stats) = regex.captures_with_report(&text, ExecLimits::default());
+    assert_eq!(captures.unwrap().get(0), Some((0, 10_001)));
+    assert!(stats.max_failure_depth <= 1, "{stats:?}");
+
+    let plus = Regex::with_syntax("x[0-9]+y", SyntaxFlags::AWK).unwrap();
+    let text = format!("x{}y", "7".repeat(10_000));
+    let (captures, stats) = plus.captures_with_report(&text, ExecLimits::default());
+    assert_eq!(captures.unwrap().get(0), Some((0, 10_002)));
+    assert!(stats.max_failure_depth <= 1, "{stats:?}");
+
+    // Where the rest could start with the body's char, rounds are kept
+    let overlapping = Regex::new("a*ab").unwrap();
+    let (captures, stats) = overlapping.captures_with_report("aaab", ExecLimits::default());
+    assert_eq!(captures.unwrap().get(0), Some((0, 4)));
+    assert!(stats.max_failure_depth > 1, "{stats:?}");
+    assert!(Regex::new("a*\\B").unwrap().is_match("aa"));
+
+    // Folded patterns compare folded chars, so they update just the same
+    for syntax in [
+        SyntaxFlags::EMACS | SyntaxFlags::CASE_INSENSITIVE,
+        SyntaxFlags::EMACS | SyntaxFlags::CASE_INSENSITIVE_ASCII,
+    ] {
+        let folded = Regex::with_syntax("a*B", syntax).unwrap();
+        let text = format!("{}b", "aA".repeat(5_000));
+        let (captures, stats) = folded.captures_with_report(&text, ExecLimits::default());
+        assert_eq!(captures.unwrap().get(0), Some((0, 10_001)));
+        assert!(stats.max_failure_depth <= 1, "{stats:?}");
+
+        // and still give rounds back where a folded char could follow
+        let find =
+            |pattern: &str, text: &str| Regex::with_syntax(pattern, syntax).unwrap().find(text);
+        assert_eq!(find("a*A", "aaA"), Some((0, 3)));
+        assert_eq!(find("[a-z]*Z", "xyz"), Some((0, 3)));
+        assert_eq!(find("[Z-a]*z", "zZ"), Some((0, 2)));
+        assert_eq!(find("\\(a\\|B\\)*b", "AbB"), Some((0, 3)));
+    }
+    let inline =
+        Regex::with_syntax("(?i)x*X", SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS).unwrap();
+    assert_eq!(inline.find("xXx"), Some((0, 3)));
+}
+
+#[test]
+fn test_find_from_offset() {
+    let regex = Regex::new("ab").unwrap();
+    assert_eq!(regex.find_from("abXab", 0), Some((0, 2)));
+    assert_eq!(regex.find_from("abXab", 1), Some((3, 5)));
+    assert_eq!(regex.find_from("abXab", 2), Some((3, 5)));
+    assert_eq!(regex.find_from("abXab", 4), None);
+    assert_eq!(regex.find_from("abXab", 5), None);
+    assert_eq!(regex.find_from("abXab", 6), None);
+
+    // Only patterns that can match empty match at the end
+    let empty = Regex::new("x*").unwrap();
+    assert_eq!(empty.find_from("abXab", 5), Some((5, 5)));
+    assert_eq!(empty.find_from("abXab", 6), None);
+
+    // Char positions, with the earlier text still in view for anchors
+    assert_eq!(regex.find_from("éab", 1), Some((1, 3)));
+    let anchored = Regex::with_syntax("^ab", SyntaxFlags::AWK).unwrap();
+    assert_eq!(anchored.find_from("abXab", 2), None);
+    assert_eq!(anchored.find_from("ab\nab", 1), Some((3, 5)));
+}
+
+#[test]
+fn test_rfind_rightmost_start() {
+    let letter = Regex::with_syntax("[a-z]", SyntaxFlags::AWK).unwrap();
+    assert_eq!(letter.rfind("a.b.c"), Some((4, 5)));
+    assert_eq!(letter.find("a.b.c"), Some((0, 1)));
+
+    // The last delimiter of a path-like string
+    let dot = Regex::new("\\.").unwrap();
+    assert_eq!(dot.rfind("a.b.c"), Some((3, 4)));
+    assert_eq!(dot.rfind("abc"), None);
+    assert_eq!(dot.rfind(""), None);
+
+    // The largest start wins, not the longest match
+    let run = Regex::with_syntax("a+", SyntaxFlags::AWK).unwrap();
+    assert_eq!(run.rfind("aaab"), Some((2, 3)));
+    let alt = Regex::with_syntax("ab|b", SyntaxFlags::AWK).unwrap();
+    assert_eq!(alt.rfind("ab"), Some((1, 2)));
+
+    // Empty-capable patterns match at the end; positions are in chars
+    assert_eq!(Regex::new("x*").unwrap().rfind("abc"), Some((3, 3)));
+    assert_eq!(letter.rfind("é-a-é"), Some((2, 3)));
+
+    let line = Regex::with_syntax("^[a-z]+", SyntaxFlags::AWK).unwrap();
+    assert_eq!(line.rfind("ab\ncd\n12"), Some((3, 5)));
+}
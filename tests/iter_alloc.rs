@@ -0,0 +1,57 @@
+//! Tests that the match iterators decode their text only once
+//!
+//! Kept in their own binary so the counting allocator only sees this test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tature::{Regex, SyntaxFlags};
+
+/// Allocator that tallies the bytes requested through it
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Bytes allocated while running `f`
+fn allocated_by(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    f();
+    ALLOCATED.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn test_iterators_decode_text_once() {
+    let regex = Regex::with_syntax("([0-9])", SyntaxFlags::AWK).unwrap();
+
+    // Bytes allocated iterating over `pairs` matches, for each iterator
+    let allocations = |pairs: usize| {
+        let text = "a1".repeat(pairs);
+        [
+            allocated_by(|| assert_eq!(regex.find_iter(&text).count(), pairs)),
+            allocated_by(|| assert_eq!(regex.captures_iter(&text).count(), pairs)),
+            allocated_by(|| assert_eq!(regex.split_captures(&text).count(), 2 * pairs + 1)),
+        ]
+    };
+
+    // Decoding the text at every step would make allocation grow with the
+    // square of the text length; decoding it once keeps it linear
+    let small = allocations(10_000);
+    let large = allocations(40_000);
+    for (small, large) in small.into_iter().zip(large) {
+        assert!(large < 5 * small, "{small} bytes, then {large} bytes");
+    }
+}
@@ -0,0 +1,64 @@
+//! Tests for the events emitted behind the `log` feature
+
+#![cfg(feature = "log")]
+
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use tature::{ExecLimits, Regex, SyntaxFlags};
+
+/// Logger that records every `tature` event it receives
+struct CaptureLogger {
+    events: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "tature"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.events
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger {
+    events: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn test_logging_events() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    // Each `a` can match either branch, so failing on the `c` is exponential
+    let regex = Regex::with_syntax("(a|a)*b", SyntaxFlags::AWK).unwrap();
+    let limits = ExecLimits {
+        max_ticks: Some(1000),
+        max_failures: 100_000,
+        ..Default::default()
+    };
+    assert!(!regex.is_match_with_limits("aaaaaaaaaaaaaaaaaaaaaaaac", limits));
+    assert!(!regex.is_match("c"));
+
+    let events = LOGGER.events.lock().unwrap();
+    assert!(events.iter().any(|(level, message)| *level == Level::Debug
+        && message.contains("1 groups")
+        && message.contains("bytecode bytes")));
+    assert!(events
+        .iter()
+        .any(|(level, message)| *level == Level::Warn && message == "tick limit of 1000 exceeded"));
+    assert!(events
+        .iter()
+        .any(|(_, message)| message.starts_with("search aborted at 0")));
+    assert!(events
+        .iter()
+        .any(|(_, message)| message.starts_with("search failed")));
+}
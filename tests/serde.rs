@@ -0,0 +1,94 @@
+//! Round trips through the serde impls behind the `serde` feature
+
+#![cfg(feature = "serde")]
+
+use tature::{Captures, ExecLimits, Regex, SyntaxFlags};
+
+#[test]
+fn test_captures_json_round_trip() {
+    let regex = Regex::with_syntax("(a+)(x)?(b+)", SyntaxFlags::AWK).unwrap();
+    let caps = regex.captures("zzaabb").unwrap();
+
+    let json = serde_json::to_string(&caps).unwrap();
+    // Only the groups that matched are written out
+    assert_eq!(
+        json,
+        r#"{"group_count":4,"groups":[[0,2,6],[1,2,4],[3,4,6]]}"#
+    );
+
+    let back: Captures = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, caps);
+    assert_eq!(back.get(2), None);
+    assert_eq!(back.get(3), Some((4, 6)));
+}
+
+#[test]
+fn test_captures_rejects_out_of_range_group() {
+    let json = r#"{"group_count":1,"groups":[[100,0,1]]}"#;
+    assert!(serde_json::from_str::<Captures>(json).is_err());
+}
+
+#[test]
+fn test_limits_and_flags_round_trip() {
+    let limits = ExecLimits {
+        max_ticks: Some(500),
+        max_failures: 64,
+        max_input_chars: None,
+        memoize: true,
+        timeout: Some(std::time::Duration::from_millis(250)),
+    };
+    let back: ExecLimits = serde_json::from_str(&serde_json::to_string(&limits).unwrap()).unwrap();
+    assert_eq!(back.max_ticks, Some(500));
+    assert_eq!(back.max_failures, 64);
+    assert_eq!(back.max_input_chars, None);
+    assert!(back.memoize);
+    assert_eq!(back.timeout, Some(std::time::Duration::from_millis(250)));
+
+    // Fields missing from older data take their defaults
+    let back: ExecLimits = serde_json::from_str(r#"{"max_ticks":10}"#).unwrap();
+    assert_eq!(back.max_ticks, Some(10));
+    assert_eq!(back.max_failures, ExecLimits::default().max_failures);
+    assert!(!back.memoize);
+
+    let flags = SyntaxFlags::AWK | SyntaxFlags::CASE_INSENSITIVE;
+    let json = serde_json::to_string(&flags).unwrap();
+    assert_eq!(serde_json::from_str::<SyntaxFlags>(&json).unwrap(), flags);
+}
+
+#[test]
+fn test_named_captures_round_trip() {
+    let syntax = SyntaxFlags::AWK | SyntaxFlags::PERL_EXTENSIONS;
+    let regex = Regex::with_syntax("(?<key>[a-z]+)=([0-9]+)", syntax).unwrap();
+    let text = "x=1 ab=42";
+    let caps = regex.captures(text).unwrap();
+
+    let json = serde_json::to_string(&caps).unwrap();
+    assert!(json.ends_with(r#""names":[null,"key",null]}"#), "{json}");
+    let back: Captures = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, caps);
+    assert_eq!(back.name("key", text), Some("x"));
+
+    let json = r#"{"group_count":2,"groups":[],"names":[null]}"#;
+    assert!(serde_json::from_str::<Captures>(json).is_err());
+}
+
+#[test]
+fn test_config_and_log_round_trip() {
+    // Flags are stored as their raw bits
+    assert_eq!(serde_json::to_string(&SyntaxFlags::AWK).unwrap(), "35");
+    let flags: SyntaxFlags = serde_json::from_str("35").unwrap();
+    assert_eq!(flags, SyntaxFlags::AWK);
+    let regex = Regex::with_syntax("(a|b)+", flags).unwrap();
+
+    // A log of every match restores to the same results
+    let text = "xaby ba";
+    let matches: Vec<Captures> = regex.captures_iter(text).collect();
+    let json = serde_json::to_string(&matches).unwrap();
+    let back: Vec<Captures> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, matches);
+    assert_eq!(back[1].matched_str(0, text), Some("ba"));
+
+    // Unknown bits deserialize but are still rejected when compiling
+    let flags: SyntaxFlags = serde_json::from_str("4294967295").unwrap();
+    assert!(Regex::with_syntax("a", flags).is_err());
+}